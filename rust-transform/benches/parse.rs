@@ -0,0 +1,207 @@
+// =============================================================================
+// Field parsing benchmark — the per-row hot path, before and after
+// =============================================================================
+//   cargo bench --bench parse                                    # the sample APMG file
+//   TURBO_BENCH_CSV=/data/big_AB4.csv cargo bench --bench parse  # any supplier CSV
+//
+// Criterion benches of the numeric/delivery parsing of every row of the
+// file, two ways: the String-building parsers the transform used to have
+// (kept below, verbatim, as the baseline) on csv::StringRecord, and the
+// byte-level ones in turbo_transform::parse on csv::ByteRecord; then the
+// whole reader loop (Records::next_record). Throughput is per row. A
+// counting allocator prints heap allocations per row for each case before
+// criterion times it.
+// =============================================================================
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use turbo_transform::parse::{delimiter_of, get_bytes, get_field, parse_delivery, parse_f64, parse_i64};
+use turbo_transform::{ColumnMap, MappingConfig, Records};
+
+const SAMPLE: &str = "../APMG price  7 days_AB4_part1.csv";
+
+// =============================================================================
+// Allocation counter
+// =============================================================================
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+// SAFETY: defers to the system allocator; only counts calls
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// =============================================================================
+// Baseline — the previous String-based parsers
+// =============================================================================
+mod legacy {
+    pub fn get_field(record: &csv::StringRecord, idx: Option<usize>) -> &str {
+        match idx {
+            Some(i) => record.get(i).unwrap_or("").trim().trim_matches(|c: char| c == '"' || c == '\''),
+            None => "",
+        }
+    }
+
+    #[allow(clippy::if_same_then_else)]
+    pub fn parse_f64(s: &str) -> f64 {
+        if s.is_empty() {
+            return 0.0;
+        }
+        let cleaned: String = s
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-' || *c == ',')
+            .collect();
+        if cleaned.is_empty() {
+            return 0.0;
+        }
+        if cleaned.contains('.') {
+            cleaned.replace(',', "").parse::<f64>().unwrap_or(0.0)
+        } else if cleaned.ends_with(',') {
+            cleaned.replace(',', "").parse::<f64>().unwrap_or(0.0)
+        } else {
+            let comma_count = cleaned.matches(',').count();
+            if comma_count == 1 {
+                let parts: Vec<&str> = cleaned.split(',').collect();
+                if parts.len() == 2 && parts[1].len() <= 2 {
+                    cleaned.replacen(',', ".", 1).parse::<f64>().unwrap_or(0.0)
+                } else {
+                    cleaned.replace(',', "").parse::<f64>().unwrap_or(0.0)
+                }
+            } else {
+                cleaned.replace(',', "").parse::<f64>().unwrap_or(0.0)
+            }
+        }
+    }
+
+    pub fn parse_i64(s: &str) -> i64 {
+        if s.is_empty() {
+            return 0;
+        }
+        let mut num_str = String::new();
+        for c in s.chars() {
+            if c.is_ascii_digit() {
+                num_str.push(c);
+            } else if !num_str.is_empty() {
+                break;
+            }
+        }
+        num_str.parse::<i64>().unwrap_or(0)
+    }
+
+    pub fn parse_delivery(raw: &str) -> String {
+        if raw.is_empty() {
+            return String::new();
+        }
+        let trimmed = raw.trim().trim_matches(|c: char| c == '"' || c == '\'');
+        if trimmed.starts_with('=') {
+            trimmed.trim_start_matches('=').trim_matches('"').trim().to_string()
+        } else {
+            trimmed.to_string()
+        }
+    }
+}
+
+// =============================================================================
+// Benches
+// =============================================================================
+/// Print the heap allocations per row of one `pass` over `rows` rows
+fn report_allocations(name: &str, rows: usize, mut pass: impl FnMut()) {
+    pass(); // warm-up
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    pass();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{:<32} {:>8.2} allocs/row", name, allocations as f64 / rows.max(1) as f64);
+}
+
+fn reader(data: &[u8]) -> csv::Reader<&[u8]> {
+    let first_line = data.split(|&b| b == b'\n').next().unwrap_or_default();
+    csv::ReaderBuilder::new()
+        .delimiter(delimiter_of(&String::from_utf8_lossy(first_line)))
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data)
+}
+
+fn parse(c: &mut Criterion) {
+    let path = std::env::var_os("TURBO_BENCH_CSV")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(SAMPLE));
+    let data = std::fs::read(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+    let mut csv = reader(&data);
+    let columns = ColumnMap::from_headers(csv.headers().expect("header row"));
+    let string_rows: Vec<csv::StringRecord> = csv.records().filter_map(Result::ok).collect();
+    let byte_rows: Vec<csv::ByteRecord> = reader(&data).byte_records().filter_map(Result::ok).collect();
+    let rows = byte_rows.len();
+    println!("{}: {} rows, {} bytes", name, rows, data.len());
+
+    // Price, weight, volume, quantity, min order qty and delivery — the
+    // fields map_row parses rather than borrows
+    let string_parsers = || {
+        for row in &string_rows {
+            black_box(legacy::parse_f64(legacy::get_field(row, columns.price)));
+            black_box(legacy::parse_f64(legacy::get_field(row, columns.weight)));
+            black_box(legacy::parse_f64(legacy::get_field(row, columns.volume)));
+            black_box(legacy::parse_i64(legacy::get_field(row, columns.quantity)));
+            black_box(legacy::parse_i64(legacy::get_field(row, columns.min_order_qty)));
+            let delivery = legacy::parse_delivery(legacy::get_field(row, columns.delivery_days));
+            // delivery_time, and both again for the ES document
+            black_box((delivery.clone(), delivery.clone(), delivery.clone(), delivery));
+        }
+    };
+    let byte_parsers = || {
+        for row in &byte_rows {
+            black_box(parse_f64(get_bytes(row, columns.price)));
+            black_box(parse_f64(get_bytes(row, columns.weight)));
+            black_box(parse_f64(get_bytes(row, columns.volume)));
+            black_box(parse_i64(get_bytes(row, columns.quantity)));
+            black_box(parse_i64(get_bytes(row, columns.min_order_qty)));
+            black_box(parse_delivery(get_field(row, columns.delivery_days)));
+        }
+    };
+    let config = MappingConfig::new("507f1f77bcf86cd799439011", "APMG");
+    let next_record = || {
+        let mut records = Records::new(data.as_slice(), &name, &config).expect("records");
+        while let Some(r) = records.next_record() {
+            black_box(&r);
+        }
+    };
+    report_allocations("fields/string_parsers", rows, string_parsers);
+    report_allocations("fields/byte_parsers", rows, byte_parsers);
+    report_allocations("records/next_record", rows, next_record);
+
+    let mut fields = c.benchmark_group("fields");
+    fields.throughput(Throughput::Elements(rows as u64));
+    fields.bench_function("string_parsers", |b| b.iter(string_parsers));
+    fields.bench_function("byte_parsers", |b| b.iter(byte_parsers));
+    fields.finish();
+
+    let mut records = c.benchmark_group("records");
+    records.throughput(Throughput::Elements(rows as u64));
+    records.sample_size(20);
+    records.bench_function("next_record", |b| b.iter(next_record));
+    records.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,119 @@
+// =============================================================================
+// turbo-transform Node addon — in-process CSV parsing for small uploads
+// =============================================================================
+// Spawning the binary for a single supplier upload costs more than parsing
+// it, so small uploads can use this addon instead: services/
+// turboTransformNative.js loads it, and csvParserService.parseCSV parses
+// with it when called with { native: true }. It runs the exact column
+// mapping and normalisation of the bulk transform (turbo_transform::Records),
+// so an uploaded file and the same file arriving through a bulk sync produce
+// identical documents.
+//
+//   const { parseRecords, toNdjson } = require('./turbo_transform.node');
+//   parseRecords(bufferOrPath, { integrationId, integrationName, fileName })
+//     → [{ partNumber, description, ..., importedAt }, ...]
+//   toNdjson(bufferOrPath, options)
+//     → Buffer, byte-identical to the <stem>.ndjson the binary writes
+//
+// Both are synchronous; they are meant for uploads, not 10M-row syncs.
+// =============================================================================
+
+use napi::bindgen_prelude::{Buffer, Either};
+use napi::{Env, Error, JsUnknown, Result};
+use napi_derive::napi;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use turbo_transform::{MappingConfig, Part, Records};
+
+#[napi(object)]
+#[derive(Default)]
+pub struct ParseOptions {
+    /// MongoDB ObjectId of the integration (default "")
+    pub integration_id: Option<String>,
+    pub integration_name: Option<String>,
+    /// Stamped as fileName and used for the stock code fallback
+    /// ("..._AB4_part1.csv" → "AB4"). Defaults to the file name of a path.
+    pub file_name: Option<String>,
+    /// importedAt in milliseconds since the epoch (default now)
+    pub imported_at: Option<f64>,
+}
+
+/// A path or an in-memory CSV
+type Input = Either<Buffer, String>;
+
+fn open(input: &Input, options: Option<ParseOptions>) -> Result<Records<Box<dyn Read + '_>>> {
+    let options = options.unwrap_or_default();
+
+    let (reader, default_name): (Box<dyn Read + '_>, String) = match input {
+        Either::A(buffer) => (Box::new(&buffer[..]), String::new()),
+        Either::B(path) => {
+            let file = File::open(path).map_err(|e| Error::from_reason(format!("open {} failed: {}", path, e)))?;
+            let name = Path::new(path)
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            (Box::new(file), name)
+        }
+    };
+
+    let mut mapping = MappingConfig::new(
+        options.integration_id.as_deref().unwrap_or(""),
+        options.integration_name.as_deref().unwrap_or(""),
+    );
+    if let Some(ms) = options.imported_at {
+        mapping.imported_at_millis = imported_at_millis(ms)?;
+    }
+    let file_name = options.file_name.unwrap_or(default_name);
+
+    Records::new(reader, &file_name, &mapping).map_err(Error::from_reason)
+}
+
+/// importedAt from JS; `as u64` would quietly turn NaN, ±Infinity and
+/// negative values into 0 (1970) or u64::MAX
+fn imported_at_millis(ms: f64) -> Result<u64> {
+    if ms.is_finite() && ms >= 0.0 {
+        Ok(ms as u64)
+    } else {
+        Err(Error::from_reason(format!(
+            "importedAt must be a non-negative number of milliseconds, got {}",
+            ms
+        )))
+    }
+}
+
+/// Parse a CSV into normalised part records
+#[napi]
+pub fn parse_records(env: Env, input: Input, options: Option<ParseOptions>) -> Result<JsUnknown> {
+    let parts: Vec<Part> = open(&input, options)?.collect();
+    env.to_js_value(&parts)
+}
+
+/// Parse a CSV straight to NDJSON, one document per line
+#[napi]
+pub fn to_ndjson(input: Input, options: Option<ParseOptions>) -> Result<Buffer> {
+    let mut records = open(&input, options)?;
+    let mut out = Vec::new();
+    while let Some(record) = records.next_record() {
+        record
+            .write_ndjson(&mut out)
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+    }
+    Ok(out.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imported_at_must_be_finite_and_not_negative() {
+        assert_eq!(imported_at_millis(1_714_521_600_000.0).unwrap(), 1_714_521_600_000);
+        assert_eq!(imported_at_millis(0.0).unwrap(), 0);
+        for bad in [-1.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let error = imported_at_millis(bad).unwrap_err();
+            assert!(error.reason.starts_with("importedAt must be"), "{}: {}", bad, error.reason);
+        }
+    }
+}
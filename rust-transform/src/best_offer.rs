@@ -0,0 +1,410 @@
+// =============================================================================
+// Cross-file best-offer selection — one row per part across all stock codes
+// =============================================================================
+// The same partNumber+brand shows up in several stock-code files (AB4, DS1,
+// ...) at different prices and lead times. With --best-offer each file thread
+// folds its records into a local group map, merges it into the run-wide map
+// when the file is done, and after the run parts_best_offer.ndjson gets the
+// winning offer per group plus:
+//
+//   offersCount, minPrice, maxPrice
+//
+// Ranking: cheapest = lowest price, then fastest; fastest = lowest delivery
+// days, then cheapest. Price 0 (unparsed) and unknown lead times rank last
+// on their criterion and are left out of minPrice/maxPrice, which are null
+// for a group with no priced offer. Ties go to the lowest supplier, then
+// fileName, then stockCode, then the whole document, so the winner never
+// depends on which file happened to finish first.
+// The per-offer .ndjson/.bulk outputs are untouched.
+//
+// Memory: the run-wide map holds every group until the run ends — its key
+// and the winning offer's NDJSON, roughly half a kilobyte per group, so
+// about 0.5 GB per million distinct parts (plus one file's worth per busy
+// thread). With max_groups (--best-offer-max-groups) a run that goes past
+// the limit drops the map and reports an error instead of the output.
+// =============================================================================
+
+use crate::bulk_action::DocField;
+use crate::parse::delivery_range;
+use crate::{PartRecord, PartRecordES};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub const OUTPUT_NAME: &str = "parts_best_offer.ndjson";
+
+const DEFAULT_KEY: [DocField; 2] = [DocField::PartNumber, DocField::Brand];
+
+#[derive(Clone, Copy)]
+pub enum OfferRank {
+    Cheapest,
+    Fastest,
+}
+
+impl OfferRank {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "cheapest" => Ok(OfferRank::Cheapest),
+            "fastest" => Ok(OfferRank::Fastest),
+            _ => Err(format!("unknown best-offer rank '{}' (expected cheapest or fastest)", s)),
+        }
+    }
+}
+
+pub struct BestOfferConfig {
+    pub rank: OfferRank,
+    /// Empty = partNumber,brand
+    pub key_fields: Vec<DocField>,
+    /// Most groups kept before the output is given up; 0 = no limit
+    pub max_groups: usize,
+}
+
+impl BestOfferConfig {
+    fn key_fields(&self) -> &[DocField] {
+        if self.key_fields.is_empty() {
+            &DEFAULT_KEY
+        } else {
+            &self.key_fields
+        }
+    }
+}
+
+struct Group {
+    offers: u64,
+    /// Over offers with a parsed (> 0) price; None when there is none
+    min_price: Option<f64>,
+    max_price: Option<f64>,
+    best_price: f64,
+    best_days: Option<i32>,
+    best_supplier: Box<str>,
+    best_file_name: Box<str>,
+    best_stock_code: Box<str>,
+    /// Serialised NDJSON of the winning offer (no trailing newline)
+    best_doc: Box<[u8]>,
+}
+
+/// `a` and `b` folded with `f`, either one if the other is missing
+fn fold(a: Option<f64>, b: Option<f64>, f: fn(f64, f64) -> f64) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(f(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// What an offer is ranked by
+struct Offer<'a> {
+    price: f64,
+    days: Option<i32>,
+    supplier: &'a str,
+    file_name: &'a str,
+    stock_code: &'a str,
+}
+
+impl Group {
+    fn best(&self) -> Offer<'_> {
+        Offer {
+            price: self.best_price,
+            days: self.best_days,
+            supplier: &self.best_supplier,
+            file_name: &self.best_file_name,
+            stock_code: &self.best_stock_code,
+        }
+    }
+
+    fn set_best(&mut self, offer: &Offer, best_doc: Box<[u8]>) {
+        self.best_price = offer.price;
+        self.best_days = offer.days;
+        self.best_supplier = offer.supplier.into();
+        self.best_file_name = offer.file_name.into();
+        self.best_stock_code = offer.stock_code.into();
+        self.best_doc = best_doc;
+    }
+}
+
+/// Lower is better; missing values sort last. Equal only when the tiebreak
+/// fields match too, in which case the caller compares the documents.
+fn compare(rank: OfferRank, a: &Offer, b: &Offer) -> Ordering {
+    let price = |p: f64| if p > 0.0 { p } else { f64::INFINITY };
+    let days = |d: Option<i32>| d.unwrap_or(i32::MAX);
+    let by_price = price(a.price).total_cmp(&price(b.price));
+    let by_days = days(a.days).cmp(&days(b.days));
+    match rank {
+        OfferRank::Cheapest => by_price.then(by_days),
+        OfferRank::Fastest => by_days.then(by_price),
+    }
+    .then_with(|| a.supplier.cmp(b.supplier))
+    .then_with(|| a.file_name.cmp(b.file_name))
+    .then_with(|| a.stock_code.cmp(b.stock_code))
+}
+
+// =============================================================================
+// Group map — one per file thread, merged into one per run
+// =============================================================================
+#[derive(Default)]
+pub struct OfferGroups {
+    groups: HashMap<String, Group>,
+    key_buf: String,
+    /// Why no output can be written; the groups are dropped when it is set
+    error: Option<String>,
+}
+
+impl OfferGroups {
+    pub fn add(&mut self, config: &BestOfferConfig, doc: &PartRecord, es_doc: &PartRecordES) {
+        if self.error.is_some() {
+            return;
+        }
+        // Case-insensitive key, built in a reusable buffer
+        self.key_buf.clear();
+        for (i, f) in config.key_fields().iter().enumerate() {
+            if i > 0 {
+                self.key_buf.push('\u{1f}');
+            }
+            for c in f.get(es_doc).chars() {
+                self.key_buf.push(c.to_ascii_uppercase());
+            }
+        }
+
+        let offer = Offer {
+            price: doc.price,
+            days: delivery_range(doc.delivery_days).map(|(min, _)| min),
+            supplier: doc.supplier,
+            file_name: doc.file_name,
+            stock_code: doc.stock_code,
+        };
+        let priced = (offer.price > 0.0).then_some(offer.price);
+
+        match self.groups.get_mut(self.key_buf.as_str()) {
+            Some(g) => {
+                g.offers += 1;
+                g.min_price = fold(g.min_price, priced, f64::min);
+                g.max_price = fold(g.max_price, priced, f64::max);
+                let order = compare(config.rank, &offer, &g.best());
+                if order == Ordering::Greater {
+                    return;
+                }
+                let best_doc = match serialize(doc) {
+                    Ok(best_doc) => best_doc,
+                    Err(e) => return self.fail(format!("cannot serialise {}: {}", doc.part_number, e)),
+                };
+                if order == Ordering::Less || best_doc < g.best_doc {
+                    g.set_best(&offer, best_doc);
+                }
+            }
+            None => {
+                let best_doc = match serialize(doc) {
+                    Ok(best_doc) => best_doc,
+                    Err(e) => return self.fail(format!("cannot serialise {}: {}", doc.part_number, e)),
+                };
+                self.groups.insert(
+                    self.key_buf.clone(),
+                    Group {
+                        offers: 1,
+                        min_price: priced,
+                        max_price: priced,
+                        best_price: offer.price,
+                        best_days: offer.days,
+                        best_supplier: offer.supplier.into(),
+                        best_file_name: offer.file_name.into(),
+                        best_stock_code: offer.stock_code.into(),
+                        best_doc,
+                    },
+                );
+                self.check_size(config.max_groups);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, config: &BestOfferConfig, other: OfferGroups) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Some(e) = other.error {
+            return self.fail(e);
+        }
+        for (key, theirs) in other.groups {
+            match self.groups.get_mut(&key) {
+                Some(g) => {
+                    g.offers += theirs.offers;
+                    g.min_price = fold(g.min_price, theirs.min_price, f64::min);
+                    g.max_price = fold(g.max_price, theirs.max_price, f64::max);
+                    let better = compare(config.rank, &theirs.best(), &g.best())
+                        .then_with(|| theirs.best_doc.cmp(&g.best_doc))
+                        == Ordering::Less;
+                    if better {
+                        g.best_price = theirs.best_price;
+                        g.best_days = theirs.best_days;
+                        g.best_supplier = theirs.best_supplier;
+                        g.best_file_name = theirs.best_file_name;
+                        g.best_stock_code = theirs.best_stock_code;
+                        g.best_doc = theirs.best_doc;
+                    }
+                }
+                None => {
+                    self.groups.insert(key, theirs);
+                }
+            }
+        }
+        self.check_size(config.max_groups);
+    }
+
+    fn check_size(&mut self, max_groups: usize) {
+        if max_groups > 0 && self.groups.len() > max_groups {
+            self.fail(format!("more than {} best-offer groups", max_groups));
+        }
+    }
+
+    fn fail(&mut self, error: String) {
+        self.error = Some(error);
+        self.groups = HashMap::new();
+    }
+
+    /// Write <output_dir>/parts_best_offer.ndjson. Returns (groups, offers, bytes).
+    pub fn write(self, output_dir: &Path) -> std::io::Result<(u64, u64, u64)> {
+        if let Some(e) = self.error {
+            return Err(std::io::Error::other(e));
+        }
+        let path = output_dir.join(OUTPUT_NAME);
+        let mut w = BufWriter::with_capacity(1024 * 1024, File::create(path)?);
+
+        // Deterministic output order
+        let mut groups: Vec<(String, Group)> = self.groups.into_iter().collect();
+        groups.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        let mut offers = 0u64;
+        let mut bytes = 0u64;
+        let mut line = Vec::with_capacity(1024);
+        for (_, g) in &groups {
+            offers += g.offers;
+            line.clear();
+            // Reopen the serialised object and append the aggregate fields
+            line.extend_from_slice(&g.best_doc[..g.best_doc.len() - 1]);
+            line.extend_from_slice(format!(r#","offersCount":{},"minPrice":"#, g.offers).as_bytes());
+            serde_json::to_writer(&mut line, &g.min_price)?;
+            line.extend_from_slice(br#","maxPrice":"#);
+            serde_json::to_writer(&mut line, &g.max_price)?;
+            line.extend_from_slice(b"}\n");
+            w.write_all(&line)?;
+            bytes += line.len() as u64;
+        }
+        w.flush()?;
+        Ok((groups.len() as u64, offers, bytes))
+    }
+}
+
+fn serialize(doc: &PartRecord) -> serde_json::Result<Box<[u8]>> {
+    serde_json::to_vec(doc).map(Vec::into_boxed_slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use serde_json::Value;
+
+    fn record<'a>(part_number: &'a str, price: f64, delivery_days: &'a str) -> PartRecord<'a> {
+        PartRecord { part_number, price, delivery_time: delivery_days, delivery_days, ..PartRecord::sample() }
+    }
+
+    fn config(max_groups: usize) -> BestOfferConfig {
+        BestOfferConfig { rank: OfferRank::Cheapest, key_fields: Vec::new(), max_groups }
+    }
+
+    fn groups(config: &BestOfferConfig, offers: &[(&str, f64, &str)]) -> OfferGroups {
+        let mut groups = OfferGroups::default();
+        for &(part, price, days) in offers {
+            let doc = record(part, price, days);
+            groups.add(config, &doc, &doc.es());
+        }
+        groups
+    }
+
+    fn written(name: &str, groups: OfferGroups) -> std::io::Result<Vec<Value>> {
+        let dir = scratch(&format!("best-offer-{}", name));
+        let result = groups.write(&dir).map(|_| {
+            let text = std::fs::read_to_string(dir.join(OUTPUT_NAME)).unwrap();
+            text.lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+        result
+    }
+
+    #[test]
+    fn unpriced_groups_have_null_min_and_max() {
+        let config = config(0);
+        let mut all = groups(&config, &[("A", 0.0, "3"), ("A", 0.0, "1"), ("B", 12.5, "")]);
+        all.merge(&config, groups(&config, &[("B", 9.0, "2"), ("B", 0.0, "1")]));
+        let lines = written("null", all).unwrap();
+
+        assert_eq!(lines[0]["partNumber"], "A");
+        assert_eq!((&lines[0]["minPrice"], &lines[0]["maxPrice"]), (&Value::Null, &Value::Null));
+        assert_eq!(lines[0]["deliveryDays"], "1");
+        assert_eq!(lines[1]["offersCount"], 3);
+        assert_eq!((lines[1]["minPrice"].as_f64(), lines[1]["maxPrice"].as_f64()), (Some(9.0), Some(12.5)));
+        assert_eq!(lines[1]["price"], 9.0);
+    }
+
+    #[test]
+    fn too_many_groups_give_up_the_output() {
+        let config = config(2);
+        let within = groups(&config, &[("A", 1.0, ""), ("B", 1.0, ""), ("A", 2.0, "")]);
+        assert_eq!(written("within", within).unwrap().len(), 2);
+
+        let over = groups(&config, &[("A", 1.0, ""), ("B", 1.0, ""), ("C", 1.0, "")]);
+        let error = written("over", over).unwrap_err();
+        assert_eq!(error.to_string(), "more than 2 best-offer groups");
+
+        // Each file stays under the limit; together they do not
+        let mut merged = groups(&config, &[("A", 1.0, ""), ("B", 1.0, "")]);
+        merged.merge(&config, groups(&config, &[("C", 1.0, "")]));
+        assert!(written("merged", merged).is_err());
+    }
+
+    /// One file's offers of part A, all at the same price and lead time
+    fn tied_file(config: &BestOfferConfig, supplier: &str, file_name: &str, stock_code: &str) -> OfferGroups {
+        let mut groups = OfferGroups::default();
+        for description in ["second", "first"] {
+            let doc = PartRecord {
+                description,
+                supplier,
+                file_name,
+                stock_code,
+                ..record("A", 10.0, "3")
+            };
+            groups.add(config, &doc, &doc.es());
+        }
+        groups
+    }
+
+    fn merged_both_ways(name: &str, files: &[(&str, &str, &str)]) -> Value {
+        let config = config(0);
+        let merge = |order: Vec<&(&str, &str, &str)>| {
+            let mut all = OfferGroups::default();
+            for &&(supplier, file_name, stock_code) in &order {
+                all.merge(&config, tied_file(&config, supplier, file_name, stock_code));
+            }
+            written(name, all).unwrap()
+        };
+        let forward = merge(files.iter().collect());
+        let backward = merge(files.iter().rev().collect());
+        assert_eq!(forward, backward);
+        assert_eq!(forward[0]["offersCount"], 2 * files.len() as u64);
+        forward[0].clone()
+    }
+
+    #[test]
+    fn ties_do_not_depend_on_merge_order() {
+        // Same supplier: the lowest fileName, then its lowest document
+        let best = merged_both_ways("tie-file", &[("APMG", "b_AB4_part1.csv", "AB4"), ("APMG", "a_DS1_part1.csv", "DS1")]);
+        assert_eq!((&best["fileName"], &best["description"]), (&Value::from("a_DS1_part1.csv"), &Value::from("first")));
+
+        // Supplier before fileName
+        let best = merged_both_ways("tie-supplier", &[("Alpha", "b_AB4_part1.csv", "AB4"), ("Zeta", "a_DS1_part1.csv", "DS1")]);
+        assert_eq!(best["supplier"], "Alpha");
+
+        // Same file name in two folders: the stock code decides
+        let best = merged_both_ways("tie-code", &[("APMG", "parts.csv", "DS1"), ("APMG", "parts.csv", "AB4")]);
+        assert_eq!(best["stockCode"], "AB4");
+    }
+}
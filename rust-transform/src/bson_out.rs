@@ -0,0 +1,275 @@
+// =============================================================================
+// BSON output — mongorestore-compatible dump files instead of NDJSON
+// =============================================================================
+// mongoimport re-parses every JSON line we just wrote; with --bson each CSV
+// becomes <stem>.bson (concatenated BSON documents, the mongodump format)
+// next to <stem>.metadata.json, which names the parts collection and holds the
+// Part model's indexes — mongorestore pairs the two by name:
+//
+//   mongorestore --db partsform --collection parts \
+//       --numInsertionWorkersPerCollection 8 <stem>.bson
+//
+// Encoded by hand (the record is flat) so the hot path stays allocation-free:
+//   importedAt        → UTC datetime
+//   integration       → ObjectId when it is a 24-hex-digit id, else string
+//   quantity/minOrder → int32 when it fits (what mongoimport stores), else int64
+// =============================================================================
+
+use crate::PartRecord;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+const T_DOUBLE: u8 = 0x01;
+const T_STRING: u8 = 0x02;
+const T_OBJECT_ID: u8 = 0x07;
+const T_DATETIME: u8 = 0x09;
+const T_INT32: u8 = 0x10;
+const T_INT64: u8 = 0x12;
+
+/// Parse a 24-char hex MongoDB ObjectId
+pub fn parse_object_id(s: &str) -> Option<[u8; 12]> {
+    if s.len() != 24 {
+        return None;
+    }
+    let mut out = [0u8; 12];
+    for (i, chunk) in s.as_bytes().chunks(2).enumerate() {
+        let hi = (chunk[0] as char).to_digit(16)?;
+        let lo = (chunk[1] as char).to_digit(16)?;
+        out[i] = (hi * 16 + lo) as u8;
+    }
+    Some(out)
+}
+
+#[inline]
+fn key(buf: &mut Vec<u8>, ty: u8, name: &str) {
+    buf.push(ty);
+    buf.extend_from_slice(name.as_bytes());
+    buf.push(0);
+}
+
+#[inline]
+fn string(buf: &mut Vec<u8>, name: &str, value: &str) {
+    key(buf, T_STRING, name);
+    buf.extend_from_slice(&((value.len() + 1) as i32).to_le_bytes());
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(0);
+}
+
+#[inline]
+fn double(buf: &mut Vec<u8>, name: &str, value: f64) {
+    key(buf, T_DOUBLE, name);
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+#[inline]
+fn integer(buf: &mut Vec<u8>, name: &str, value: i64) {
+    match i32::try_from(value) {
+        Ok(v) => {
+            key(buf, T_INT32, name);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Err(_) => {
+            key(buf, T_INT64, name);
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// Append one PartRecord as a BSON document (field order matches the NDJSON)
+pub fn encode_part(buf: &mut Vec<u8>, r: &PartRecord, integration_oid: Option<&[u8; 12]>, imported_at_millis: u64) {
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]); // length placeholder
+
+    string(buf, "partNumber", r.part_number);
+    string(buf, "description", r.description);
+    string(buf, "brand", r.brand);
+    string(buf, "supplier", r.supplier);
+    double(buf, "price", r.price);
+    string(buf, "currency", r.currency);
+    integer(buf, "quantity", r.quantity);
+    integer(buf, "minOrderQty", r.min_order_qty);
+    string(buf, "stock", r.stock);
+    string(buf, "stockCode", r.stock_code);
+    double(buf, "weight", r.weight);
+    string(buf, "weightUnit", r.weight_unit);
+    double(buf, "volume", r.volume);
+    string(buf, "deliveryTime", r.delivery_time);
+    string(buf, "deliveryDays", r.delivery_days);
+    string(buf, "category", r.category);
+    string(buf, "subcategory", r.subcategory);
+    match integration_oid {
+        Some(oid) => {
+            key(buf, T_OBJECT_ID, "integration");
+            buf.extend_from_slice(oid);
+        }
+        None => string(buf, "integration", r.integration),
+    }
+    string(buf, "integrationName", r.integration_name);
+    string(buf, "fileName", r.file_name);
+    key(buf, T_DATETIME, "importedAt");
+    buf.extend_from_slice(&(imported_at_millis as i64).to_le_bytes());
+
+    buf.push(0);
+    let len = (buf.len() - start) as i32;
+    buf[start..start + 4].copy_from_slice(&len.to_le_bytes());
+}
+
+fn index(key: serde_json::Value, name: &str) -> serde_json::Value {
+    json!({ "v": 2, "key": key, "name": name })
+}
+
+/// Write `path` (<stem>.metadata.json, next to <stem>.bson) for `collection`
+/// with the indexes from models/Part.js. Only parts_text_index is kept of the
+/// two text indexes — MongoDB allows one.
+pub fn write_metadata(path: &Path, collection: &str) -> Result<(), String> {
+    let indexes = vec![
+        index(json!({ "_id": 1 }), "_id_"),
+        index(json!({ "source.type": 1 }), "source.type_1"),
+        index(json!({ "source.supplierId": 1 }), "source.supplierId_1"),
+        index(json!({ "integrationName": 1 }), "integrationName_1"),
+        index(json!({ "importedAt": 1 }), "importedAt_1"),
+        index(json!({ "partNumber": 1, "supplier": 1 }), "partNumber_1_supplier_1"),
+        index(json!({ "partNumber": 1, "fileName": 1 }), "partNumber_1_fileName_1"),
+        index(json!({ "partNumber": 1, "integration": 1 }), "partNumber_1_integration_1"),
+        index(json!({ "brand": 1, "supplier": 1 }), "brand_1_supplier_1"),
+        index(json!({ "price": 1, "quantity": 1 }), "price_1_quantity_1"),
+        index(json!({ "source.type": 1, "source.supplierId": 1 }), "source.type_1_source.supplierId_1"),
+        index(json!({ "source.supplierId": 1, "createdAt": -1 }), "source.supplierId_1_createdAt_-1"),
+        index(json!({ "integration": 1 }), "integration_1"),
+        json!({
+            "v": 2,
+            "key": { "_fts": "text", "_ftsx": 1 },
+            "name": "parts_text_index",
+            "weights": { "partNumber": 10, "brand": 5, "description": 3, "supplier": 2 },
+            "default_language": "english",
+            "language_override": "language",
+            "textIndexVersion": 3
+        }),
+    ];
+
+    let metadata = json!({
+        "options": {},
+        "indexes": indexes,
+        "collectionName": collection,
+        "type": "collection"
+    });
+
+    let body = serde_json::to_vec(&metadata).map_err(|e| e.to_string())?;
+    fs::write(path, body).map_err(|e| format!("write {} failed: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use crate::{MappingConfig, Transformer};
+    use bson::{Bson, Document};
+    use serde_json::{Map, Value};
+
+    const OID: &str = "507f1f77bcf86cd799439011";
+
+    fn record(quantity: i64) -> PartRecord<'static> {
+        PartRecord {
+            part_number: "0 986 479 \"A\"",
+            description: "Brake disc — front",
+            supplier: "APMG",
+            price: 12.75,
+            quantity,
+            min_order_qty: 2,
+            stock: "in stock",
+            weight: 0.5,
+            volume: 0.25,
+            delivery_time: "3/6",
+            delivery_days: "3/6",
+            category: "Brakes",
+            subcategory: "Discs",
+            integration: OID,
+            integration_name: "APMG",
+            file_name: "prices_AB4.csv",
+            imported_at: "2024-05-01T00:00:00.000Z",
+            ..PartRecord::sample()
+        }
+    }
+
+    fn decode(r: &PartRecord, oid: Option<&[u8; 12]>) -> Document {
+        let mut buf = Vec::new();
+        encode_part(&mut buf, r, oid, 1_714_521_600_000);
+        let doc = Document::from_reader(&mut buf.as_slice()).unwrap();
+        assert_eq!(bson::to_vec(&doc).unwrap(), buf, "re-encoding gives the same bytes");
+        doc
+    }
+
+    #[test]
+    fn encoded_part_decodes_to_the_record() {
+        let r = record(7);
+        let oid = parse_object_id(OID).unwrap();
+        let doc = decode(&r, Some(&oid));
+
+        // Same fields in the same order as the NDJSON line
+        let mut line = Vec::new();
+        r.write_ndjson(&mut line).unwrap();
+        let json: Map<String, Value> = serde_json::from_slice(&line).unwrap();
+        assert_eq!(doc.keys().collect::<Vec<_>>(), json.keys().collect::<Vec<_>>());
+
+        for (name, value) in &json {
+            match doc.get(name).unwrap() {
+                Bson::String(s) => assert_eq!(value.as_str(), Some(s.as_str()), "{}", name),
+                Bson::Double(d) => assert_eq!(value.as_f64(), Some(*d), "{}", name),
+                Bson::Int32(n) => assert_eq!(value.as_i64(), Some(*n as i64), "{}", name),
+                Bson::ObjectId(id) => {
+                    assert_eq!(name, "integration");
+                    assert_eq!(id.to_hex(), OID);
+                }
+                Bson::DateTime(t) => {
+                    assert_eq!(name, "importedAt");
+                    assert_eq!(t.timestamp_millis(), 1_714_521_600_000);
+                }
+                other => panic!("{}: unexpected {:?}", name, other),
+            }
+        }
+    }
+
+    #[test]
+    fn wide_integers_and_plain_integrations() {
+        let mut r = record(i32::MAX as i64 + 1);
+        r.min_order_qty = -1;
+        r.integration = "not-an-object-id";
+        let doc = decode(&r, None);
+        assert_eq!(doc.get("quantity"), Some(&Bson::Int64(i32::MAX as i64 + 1)));
+        assert_eq!(doc.get("minOrderQty"), Some(&Bson::Int32(-1)));
+        assert_eq!(doc.get("integration"), Some(&Bson::String("not-an-object-id".into())));
+        assert_eq!(parse_object_id("not-an-object-id"), None);
+        assert_eq!(parse_object_id("507f1f77bcf86cd79943901g"), None);
+    }
+
+    #[test]
+    fn every_data_file_gets_its_own_metadata() {
+        let dir = scratch("bson-metadata");
+        let (input, output) = (dir.join("in"), dir.join("out"));
+        fs::create_dir_all(input.join("north")).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        let files = vec![input.join("a_AB4.csv"), input.join("north/b_AB4.csv")];
+        for f in &files {
+            fs::write(f, "part_number;price\nP1;1,5\n").unwrap();
+        }
+
+        let summary = Transformer::builder(MappingConfig::new(OID, "APMG"), &output)
+            .input_root(&input)
+            .bson(true)
+            .build()
+            .unwrap()
+            .run(&files);
+
+        for (result, stem) in summary.files.iter().zip(["a_AB4", "north/b_AB4"]) {
+            assert_eq!(result.error, None);
+            let metadata = output.join(format!("{}.metadata.json", stem));
+            assert_eq!(result.outputs.bson.as_deref(), Some(&*output.join(format!("{}.bson", stem)).display().to_string()));
+            assert_eq!(result.outputs.bson_metadata.as_deref(), Some(&*metadata.display().to_string()));
+            let json: Value = serde_json::from_slice(&fs::read(&metadata).unwrap()).unwrap();
+            assert_eq!(json["collectionName"], "parts");
+        }
+        assert!(!output.join("parts.metadata.json").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,376 @@
+// =============================================================================
+// ES _bulk action line builder — op type, _id, routing, pipeline, version
+// =============================================================================
+// Default output is unchanged: {"index":{"_index":"<name>"}}
+//
+//   --es-op index|create|update   update wraps the doc as
+//                                 {"doc":{...},"doc_as_upsert":true}
+//   --es-id-fields a,b,c          _id = values joined with ':' (required by
+//                                 update and external versioning; defaults to
+//                                 integration,stockCode,brand,partNumber)
+//                                 A ':' or '\' in a value is escaped with '\',
+//                                 so ("a:b","c") and ("a","b:c") stay apart
+//                                 An _id over ES's 512-byte limit keeps its
+//                                 start plus '#' and a hash of all of it
+//   --es-routing <field>          per-document routing, e.g. brand
+//   --es-pipeline <name>          ingest pipeline (index/create only)
+//   --es-version-external         version = import timestamp (ms),
+//                                 version_type = external (index only)
+// =============================================================================
+
+use crate::hash::hash_bytes;
+use crate::PartRecordES;
+
+/// Longest _id ES accepts, in UTF-8 bytes
+pub const MAX_ID_BYTES: usize = 512;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BulkOp {
+    Index,
+    Create,
+    Update,
+}
+
+impl BulkOp {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "index" => Ok(BulkOp::Index),
+            "create" => Ok(BulkOp::Create),
+            "update" => Ok(BulkOp::Update),
+            _ => Err(format!("unknown ES bulk op '{}' (expected index, create or update)", s)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BulkOp::Index => "index",
+            BulkOp::Create => "create",
+            BulkOp::Update => "update",
+        }
+    }
+}
+
+/// Document fields usable for _id and routing (camelCase, as in the output)
+#[derive(Clone, Copy)]
+pub enum DocField {
+    PartNumber,
+    Brand,
+    Supplier,
+    Currency,
+    Stock,
+    StockCode,
+    Category,
+    Subcategory,
+    Integration,
+    FileName,
+}
+
+impl DocField {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Ok(match s {
+            "partNumber" => DocField::PartNumber,
+            "brand" => DocField::Brand,
+            "supplier" => DocField::Supplier,
+            "currency" => DocField::Currency,
+            "stock" => DocField::Stock,
+            "stockCode" => DocField::StockCode,
+            "category" => DocField::Category,
+            "subcategory" => DocField::Subcategory,
+            "integration" => DocField::Integration,
+            "fileName" => DocField::FileName,
+            _ => return Err(format!("unsupported document field '{}'", s)),
+        })
+    }
+
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(DocField::parse)
+            .collect()
+    }
+
+    pub fn get<'a>(self, doc: &PartRecordES<'a>) -> &'a str {
+        match self {
+            DocField::PartNumber => doc.part_number,
+            DocField::Brand => doc.brand,
+            DocField::Supplier => doc.supplier,
+            DocField::Currency => doc.currency,
+            DocField::Stock => doc.stock,
+            DocField::StockCode => doc.stock_code,
+            DocField::Category => doc.category,
+            DocField::Subcategory => doc.subcategory,
+            DocField::Integration => doc.integration,
+            DocField::FileName => doc.file_name,
+        }
+    }
+}
+
+const DEFAULT_ID_FIELDS: [DocField; 4] = [
+    DocField::Integration,
+    DocField::StockCode,
+    DocField::Brand,
+    DocField::PartNumber,
+];
+
+pub struct BulkActionConfig {
+    pub op: BulkOp,
+    pub id_fields: Vec<DocField>,
+    pub routing: Option<DocField>,
+    pub pipeline: Option<String>,
+    pub version_external: bool,
+}
+
+impl Default for BulkActionConfig {
+    fn default() -> Self {
+        BulkActionConfig {
+            op: BulkOp::Index,
+            id_fields: Vec::new(),
+            routing: None,
+            pipeline: None,
+            version_external: false,
+        }
+    }
+}
+
+impl BulkActionConfig {
+    /// Reject combinations ES would refuse item by item
+    pub fn validate(&self) -> Result<(), String> {
+        if self.op == BulkOp::Update && self.pipeline.is_some() {
+            return Err("--es-pipeline is not supported with --es-op update".into());
+        }
+        if self.version_external && self.op != BulkOp::Index {
+            return Err("--es-version-external requires --es-op index".into());
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Per-run action writer — static parts pre-rendered once
+// =============================================================================
+pub struct BulkAction {
+    op: BulkOp,
+    id_fields: Vec<DocField>,
+    routing: Option<DocField>,
+    /// {"<op>":{"_index":"<name>"
+    head: Vec<u8>,
+    /// ,"pipeline":..,"version":..,"version_type":"external"}}\n
+    tail: Vec<u8>,
+    /// Whole line when nothing varies per document
+    fixed: Option<Vec<u8>>,
+}
+
+impl BulkAction {
+    pub fn new(config: &BulkActionConfig, index_name: &str, import_version: u64) -> Self {
+        let mut head = format!(r#"{{"{}":{{"_index":"#, config.op.name()).into_bytes();
+        push_json_str(&mut head, index_name);
+
+        let mut tail = Vec::new();
+        if let Some(ref pipeline) = config.pipeline {
+            tail.extend_from_slice(br#","pipeline":"#);
+            push_json_str(&mut tail, pipeline);
+        }
+        if config.version_external {
+            tail.extend_from_slice(
+                format!(r#","version":{},"version_type":"external""#, import_version).as_bytes(),
+            );
+        }
+        tail.extend_from_slice(b"}}\n");
+
+        let id_fields = if config.id_fields.is_empty()
+            && (config.op == BulkOp::Update || config.version_external)
+        {
+            DEFAULT_ID_FIELDS.to_vec()
+        } else {
+            config.id_fields.clone()
+        };
+
+        let fixed = if id_fields.is_empty() && config.routing.is_none() {
+            let mut line = head.clone();
+            line.extend_from_slice(&tail);
+            Some(line)
+        } else {
+            None
+        };
+
+        BulkAction {
+            op: config.op,
+            id_fields,
+            routing: config.routing,
+            head,
+            tail,
+            fixed,
+        }
+    }
+
+    /// Render the action line for `doc` into `out` (cleared first); `id` is
+    /// scratch space for the _id, kept by the caller across documents
+    pub fn write_action(&self, out: &mut Vec<u8>, id: &mut String, doc: &PartRecordES) {
+        out.clear();
+        if let Some(ref line) = self.fixed {
+            out.extend_from_slice(line);
+            return;
+        }
+        out.extend_from_slice(&self.head);
+        if !self.id_fields.is_empty() {
+            out.extend_from_slice(br#","_id":"#);
+            join_id(id, &self.id_fields, doc);
+            if id.len() > MAX_ID_BYTES {
+                shorten_id(id);
+            }
+            push_json_str(out, id);
+        }
+        if let Some(field) = self.routing {
+            let value = field.get(doc);
+            // Empty routing would send every such doc to one shard — omit it
+            if !value.is_empty() {
+                out.extend_from_slice(br#","routing":"#);
+                push_json_str(out, value);
+            }
+        }
+        out.extend_from_slice(&self.tail);
+    }
+
+    /// Serialise the document line; update ops wrap it for doc_as_upsert
+    pub fn write_doc(&self, out: &mut Vec<u8>, doc: &PartRecordES) -> serde_json::Result<()> {
+        out.clear();
+        if self.op == BulkOp::Update {
+            out.extend_from_slice(br#"{"doc":"#);
+            serde_json::to_writer(&mut *out, doc)?;
+            out.extend_from_slice(br#","doc_as_upsert":true}"#);
+        } else {
+            serde_json::to_writer(&mut *out, doc)?;
+        }
+        out.push(b'\n');
+        Ok(())
+    }
+
+    /// write_doc for a document serialised already (see RecordJson)
+    pub fn write_doc_json(&self, out: &mut Vec<u8>, json: &[u8]) {
+        out.clear();
+        if self.op == BulkOp::Update {
+            out.extend_from_slice(br#"{"doc":"#);
+            out.extend_from_slice(json);
+            out.extend_from_slice(br#","doc_as_upsert":true}"#);
+        } else {
+            out.extend_from_slice(json);
+        }
+        out.push(b'\n');
+    }
+}
+
+/// `fields` of `doc` joined with ':' into `id` (cleared first). A ':' or '\'
+/// inside a value gets a '\' in front, so no two field tuples join the same;
+/// values without either — nearly all — are copied as they are.
+pub fn join_id(id: &mut String, fields: &[DocField], doc: &PartRecordES) {
+    id.clear();
+    for (i, f) in fields.iter().enumerate() {
+        if i > 0 {
+            id.push(':');
+        }
+        let value = f.get(doc);
+        if value.contains([':', '\\']) {
+            for c in value.chars() {
+                if c == ':' || c == '\\' {
+                    id.push('\\');
+                }
+                id.push(c);
+            }
+        } else {
+            id.push_str(value);
+        }
+    }
+}
+
+/// Cut an over-long _id to MAX_ID_BYTES: its first bytes, then '#' and a
+/// hash of the whole id — still unique, and the same on every import
+fn shorten_id(id: &mut String) {
+    let hash = format!("#{:016x}", hash_bytes(id.as_bytes()));
+    let mut keep = MAX_ID_BYTES - hash.len();
+    while !id.is_char_boundary(keep) {
+        keep -= 1;
+    }
+    id.truncate(keep);
+    id.push_str(&hash);
+}
+
+#[inline]
+fn push_json_str(out: &mut Vec<u8>, s: &str) {
+    // Writing a &str to a Vec cannot fail
+    let _ = serde_json::to_writer(out, s);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PartRecord;
+    use serde_json::Value;
+
+    fn record(part_number: &str) -> PartRecord<'_> {
+        PartRecord { part_number, integration: "507f1f77bcf86cd799439011", ..PartRecord::sample() }
+    }
+
+    fn id_of(action: &BulkAction, id: &mut String, part_number: &str) -> String {
+        let mut out = Vec::new();
+        action.write_action(&mut out, id, &record(part_number).es());
+        let line: Value = serde_json::from_slice(&out).unwrap();
+        line["update"]["_id"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn ids_up_to_the_limit_are_kept_and_longer_ones_hashed() {
+        let config = BulkActionConfig { op: BulkOp::Update, ..Default::default() };
+        let action = BulkAction::new(&config, "parts", 0);
+        let mut id = String::new();
+        let prefix = "507f1f77bcf86cd799439011:AB4:BOSCH:";
+
+        assert_eq!(id_of(&action, &mut id, "P-1 \"x\""), format!("{}P-1 \"x\"", prefix));
+        let fits = "x".repeat(MAX_ID_BYTES - prefix.len());
+        assert_eq!(id_of(&action, &mut id, &fits), format!("{}{}", prefix, fits));
+
+        // Multi-byte characters straddling the cut are not split
+        let long_a = format!("{}ä{}", "x".repeat(MAX_ID_BYTES - prefix.len() - 18), "a".repeat(100));
+        let long_b = format!("{}ä{}", "x".repeat(MAX_ID_BYTES - prefix.len() - 18), "b".repeat(100));
+        let a = id_of(&action, &mut id, &long_a);
+        let b = id_of(&action, &mut id, &long_b);
+        assert!(a.len() <= MAX_ID_BYTES && b.len() <= MAX_ID_BYTES, "{} {}", a.len(), b.len());
+        assert!(a.starts_with(prefix) && a.contains('#'));
+        assert_ne!(a, b);
+        assert_eq!(id_of(&action, &mut id, &long_a), a);
+    }
+
+    #[test]
+    fn values_with_separators_never_share_an_id() {
+        let config = BulkActionConfig {
+            op: BulkOp::Update,
+            id_fields: vec![DocField::PartNumber, DocField::Brand],
+            ..Default::default()
+        };
+        let action = BulkAction::new(&config, "parts", 0);
+        let mut id = String::new();
+        let mut id_for = |part_number: &str, brand: &str| {
+            let doc = PartRecord { brand, ..record(part_number) };
+            let mut out = Vec::new();
+            action.write_action(&mut out, &mut id, &doc.es());
+            let line: Value = serde_json::from_slice(&out).unwrap();
+            line["update"]["_id"].as_str().unwrap().to_string()
+        };
+
+        assert_eq!(id_for("P1", "BOSCH"), "P1:BOSCH");
+        let ids = [
+            id_for("a:b", "c"),
+            id_for("a", "b:c"),
+            id_for("a\\", ":c"),
+            id_for("a\\:", "c"),
+            id_for("a\\", "\\:c"),
+        ];
+        assert_eq!(ids[0], r"a\:b:c");
+        assert_eq!(ids[1], r"a:b\:c");
+        for (i, a) in ids.iter().enumerate() {
+            for b in &ids[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+// =============================================================================
+// .bulk output — one monolithic file, or size-capped numbered chunks
+// =============================================================================
+
+use crate::events::Event;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Default)]
+pub struct BulkLimits {
+    /// 0 = unlimited
+    pub max_bytes: u64,
+    /// 0 = unlimited
+    pub max_docs: u64,
+}
+
+impl BulkLimits {
+    pub fn is_chunked(&self) -> bool {
+        self.max_bytes > 0 || self.max_docs > 0
+    }
+}
+
+pub(crate) struct BulkFileWriter<'a> {
+    /// output_dir/<stem>.bulk — chunks append .000, .001, ...
+    base_path: PathBuf,
+    limits: BulkLimits,
+    file_name: &'a str,
+    emit: &'a dyn Fn(Event),
+    chunk_index: usize,
+    chunk_path: PathBuf,
+    writer: BufWriter<File>,
+    chunk_bytes: u64,
+    chunk_docs: u64,
+    error: Option<String>,
+}
+
+impl<'a> BulkFileWriter<'a> {
+    pub(crate) fn create(
+        base_path: PathBuf,
+        limits: BulkLimits,
+        file_name: &'a str,
+        emit: &'a dyn Fn(Event),
+    ) -> std::io::Result<Self> {
+        let chunk_path = if limits.is_chunked() {
+            chunk_path(&base_path, 0)
+        } else {
+            base_path.clone()
+        };
+        let writer = BufWriter::with_capacity(1024 * 1024, File::create(&chunk_path)?);
+        Ok(BulkFileWriter {
+            base_path,
+            limits,
+            file_name,
+            emit,
+            chunk_index: 0,
+            chunk_path,
+            writer,
+            chunk_bytes: 0,
+            chunk_docs: 0,
+            error: None,
+        })
+    }
+
+    /// Write one action+doc pair. Rolls to the next chunk first if this pair
+    /// would push the current one past a limit — pairs are never split.
+    pub(crate) fn write_pair(&mut self, action: &[u8], doc: &[u8]) -> bool {
+        if self.error.is_some() {
+            return false;
+        }
+        let pair_len = (action.len() + doc.len()) as u64;
+        if self.limits.is_chunked() && self.chunk_docs > 0 {
+            let over_docs = self.limits.max_docs > 0 && self.chunk_docs >= self.limits.max_docs;
+            let over_bytes =
+                self.limits.max_bytes > 0 && self.chunk_bytes + pair_len > self.limits.max_bytes;
+            if (over_docs || over_bytes) && !self.roll() {
+                return false;
+            }
+        }
+        if let Err(e) = self
+            .writer
+            .write_all(action)
+            .and_then(|_| self.writer.write_all(doc))
+        {
+            self.error = Some(format!("write {} failed: {}", self.chunk_path.display(), e));
+            return false;
+        }
+        self.chunk_bytes += pair_len;
+        self.chunk_docs += 1;
+        true
+    }
+
+    /// Close the current chunk, announce it, open the next one
+    fn roll(&mut self) -> bool {
+        if !self.close_chunk() {
+            return false;
+        }
+        self.chunk_index += 1;
+        self.chunk_path = chunk_path(&self.base_path, self.chunk_index);
+        match File::create(&self.chunk_path) {
+            Ok(f) => {
+                self.writer = BufWriter::with_capacity(1024 * 1024, f);
+                self.chunk_bytes = 0;
+                self.chunk_docs = 0;
+                true
+            }
+            Err(e) => {
+                self.error = Some(format!("create {} failed: {}", self.chunk_path.display(), e));
+                false
+            }
+        }
+    }
+
+    /// Flush the current chunk; in chunked mode emit a bulk_chunk event so
+    /// Node can start streaming it to ES before the whole CSV is done.
+    fn close_chunk(&mut self) -> bool {
+        if let Err(e) = self.writer.flush() {
+            self.error = Some(format!("flush {} failed: {}", self.chunk_path.display(), e));
+            return false;
+        }
+        if self.limits.is_chunked() && self.chunk_docs > 0 {
+            (self.emit)(Event::BulkChunk {
+                file: self.file_name.to_string(),
+                chunk: self.chunk_index,
+                path: self.chunk_path.display().to_string(),
+                docs: self.chunk_docs,
+                bytes: self.chunk_bytes,
+            });
+        }
+        true
+    }
+
+    /// Close the last chunk; returns every file written, in order
+    pub(crate) fn finish(mut self) -> Result<Vec<PathBuf>, String> {
+        if self.error.is_none() {
+            self.close_chunk();
+        }
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if !self.limits.is_chunked() {
+            return Ok(vec![self.base_path]);
+        }
+        // The last chunk stays empty only when the file had no documents;
+        // a .bulk.000 with nothing in it is not left behind
+        let mut last = self.chunk_index + 1;
+        if self.chunk_docs == 0 {
+            drop(self.writer);
+            fs::remove_file(&self.chunk_path)
+                .map_err(|e| format!("remove {} failed: {}", self.chunk_path.display(), e))?;
+            last -= 1;
+        }
+        Ok((0..last).map(|i| chunk_path(&self.base_path, i)).collect())
+    }
+
+    /// Remove what has not been announced: the chunk being written, or the
+    /// whole unchunked file. Chunks announced by bulk_chunk stay — Node may
+    /// already be sending them to ES.
+    pub(crate) fn discard(self) {
+        drop(self.writer);
+        let _ = fs::remove_file(&self.chunk_path);
+    }
+}
+
+fn chunk_path(base: &Path, index: usize) -> PathBuf {
+    let mut name = base.as_os_str().to_os_string();
+    name.push(format!(".{:03}", index));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use std::cell::RefCell;
+
+    /// Write `docs` pairs of a 10-byte action line and a source line of
+    /// `source_len` bytes; returns the files and the bulk_chunk events
+    fn write(dir: &Path, limits: BulkLimits, docs: usize, source_len: usize) -> (Vec<PathBuf>, Vec<Event>) {
+        let events = RefCell::new(Vec::new());
+        let emit = |e: Event| events.borrow_mut().push(e);
+        let mut w = BulkFileWriter::create(dir.join("parts.bulk"), limits, "parts.csv", &emit).unwrap();
+        for i in 0..docs {
+            let action = format!("{{\"i\":{:03}}}\n", i);
+            let source = format!("{}\n", "x".repeat(source_len - 1));
+            assert!(w.write_pair(action.as_bytes(), source.as_bytes()));
+        }
+        let paths = w.finish().unwrap();
+        (paths, events.into_inner())
+    }
+
+    /// Every chunk holds whole pairs: an action line, then its source line
+    fn assert_whole_pairs(paths: &[PathBuf]) -> Vec<usize> {
+        paths
+            .iter()
+            .map(|p| {
+                let text = fs::read_to_string(p).unwrap();
+                let lines: Vec<&str> = text.lines().collect();
+                assert_eq!(lines.len() % 2, 0, "{}", p.display());
+                for pair in lines.chunks(2) {
+                    assert!(pair[0].starts_with("{\"i\":"), "{}: {:?}", p.display(), pair);
+                    assert!(pair[1].starts_with('x'), "{}: {:?}", p.display(), pair);
+                }
+                lines.len() / 2
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rolls_over_at_the_size_limit() {
+        let dir = scratch("bulk-file-bytes");
+        // 10 + 10 = 20 bytes a pair: two pairs fill a 45-byte chunk, a third would not fit
+        let limits = BulkLimits { max_bytes: 45, max_docs: 0 };
+        let (paths, events) = write(&dir, limits, 5, 10);
+        assert_eq!(paths, [0, 1, 2].map(|i| chunk_path(&dir.join("parts.bulk"), i)));
+        assert_eq!(assert_whole_pairs(&paths), [2, 2, 1]);
+        let sizes: Vec<u64> = paths.iter().map(|p| fs::metadata(p).unwrap().len()).collect();
+        assert_eq!(sizes, [40, 40, 20]);
+
+        let announced: Vec<(usize, u64, u64)> = events
+            .iter()
+            .map(|e| match e {
+                Event::BulkChunk { chunk, docs, bytes, .. } => (*chunk, *docs, *bytes),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(announced, [(0, 2, 40), (1, 2, 40), (2, 1, 20)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pairs_are_never_split() {
+        let dir = scratch("bulk-file-split");
+        // Each pair is bigger than the limit on its own: one pair per chunk, whole
+        let limits = BulkLimits { max_bytes: 16, max_docs: 0 };
+        let (paths, _) = write(&dir, limits, 3, 30);
+        assert_eq!(assert_whole_pairs(&paths), [1, 1, 1]);
+
+        // A pair that lands exactly on the limit stays; the next one rolls
+        let limits = BulkLimits { max_bytes: 60, max_docs: 0 };
+        let (paths, _) = write(&dir, limits, 4, 20);
+        assert_eq!(assert_whole_pairs(&paths), [2, 2]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rolls_over_at_the_doc_limit() {
+        let dir = scratch("bulk-file-docs");
+        let limits = BulkLimits { max_bytes: 0, max_docs: 3 };
+        let (paths, events) = write(&dir, limits, 7, 9);
+        assert_eq!(assert_whole_pairs(&paths), [3, 3, 1]);
+        assert_eq!(events.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn no_documents_leave_no_empty_chunk() {
+        let dir = scratch("bulk-file-empty");
+        let (paths, events) = write(&dir, BulkLimits { max_bytes: 100, max_docs: 0 }, 0, 9);
+        assert!(paths.is_empty());
+        assert!(events.is_empty());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        // Unchunked, the one .bulk file is always there
+        let (paths, _) = write(&dir, BulkLimits::default(), 0, 9);
+        assert_eq!(paths, [dir.join("parts.bulk")]);
+        assert_eq!(fs::metadata(&paths[0]).unwrap().len(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn discard_keeps_announced_chunks() {
+        let dir = scratch("bulk-file-discard");
+        let emit = |_: Event| {};
+        let limits = BulkLimits { max_bytes: 0, max_docs: 1 };
+        let mut w = BulkFileWriter::create(dir.join("parts.bulk"), limits, "parts.csv", &emit).unwrap();
+        assert!(w.write_pair(b"{}\n", b"x\n"));
+        assert!(w.write_pair(b"{}\n", b"x\n"));
+        w.discard();
+        assert!(chunk_path(&dir.join("parts.bulk"), 0).exists());
+        assert!(!chunk_path(&dir.join("parts.bulk"), 1).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
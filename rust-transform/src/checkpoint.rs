@@ -0,0 +1,302 @@
+// =============================================================================
+// Run checkpoint — which input files are already fully transformed
+// =============================================================================
+// The Transformer rewrites the checkpoint (write to .tmp, then rename) every
+// time a file's outputs are complete, so it is valid even after a SIGKILL:
+//
+//   { "version": 2, "integration_id": "507f…",
+//     "target": { "output_dir": "out", "es_index": "automotive_parts",
+//                 "es_op": "index", "bson": false, ... },
+//     "files": { "in/a_AB4_part1.csv": { "size": 1234, "modified_ms": …,
+//                                        "records": 42, "outputs": {…} } } }
+//
+// A resumed run skips a file only when its size and mtime still match, so a
+// re-downloaded supplier file is always transformed again. It must also
+// write to the same target: a checkpoint taken with another output
+// directory, index, op type or output format would skip files whose outputs
+// never reached the new one, so resuming from it is refused.
+// =============================================================================
+
+use crate::events::FileOutputs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+pub const CHECKPOINT_VERSION: u32 = 2;
+
+/// Default file name, inside the output directory
+pub const DEFAULT_NAME: &str = "turbo-transform.checkpoint.json";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    pub integration_id: String,
+    /// Where the completed files' outputs went
+    pub target: CheckpointTarget,
+    /// Input path (as given to the run) → what it looked like when completed
+    pub files: BTreeMap<String, CheckpointEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointEntry {
+    pub size: u64,
+    pub modified_ms: u64,
+    pub records: u64,
+    /// Output path relative to the output directory, minus the extension;
+    /// a later run against the checkpoint keeps its other files off it
+    #[serde(default)]
+    pub stem: String,
+    /// What the file was written to, reported again when it is skipped
+    #[serde(default)]
+    pub outputs: FileOutputs,
+}
+
+/// The run options that decide where a file's outputs end up
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointTarget {
+    pub output_dir: String,
+    pub es_index: String,
+    pub es_op: String,
+    pub bson: bool,
+    /// Cluster URL of an ES sink, None when .bulk files are written
+    pub es_url: Option<String>,
+    pub parquet_dir: Option<String>,
+}
+
+impl CheckpointTarget {
+    /// The first option that differs from `other`, as "name: this vs other"
+    pub fn mismatch(&self, other: &CheckpointTarget) -> Option<String> {
+        let show = |v: &Option<String>| v.as_deref().unwrap_or("none").to_string();
+        [
+            ("output directory", self.output_dir.clone(), other.output_dir.clone()),
+            ("ES index", self.es_index.clone(), other.es_index.clone()),
+            ("ES op", self.es_op.clone(), other.es_op.clone()),
+            ("BSON output", self.bson.to_string(), other.bson.to_string()),
+            ("ES sink", show(&self.es_url), show(&other.es_url)),
+            ("parquet dir", show(&self.parquet_dir), show(&other.parquet_dir)),
+        ]
+        .into_iter()
+        .find(|(_, a, b)| a != b)
+        .map(|(name, a, b)| format!("{} {} vs {}", name, a, b))
+    }
+}
+
+/// Size and mtime (ms) of `path`, None when it cannot be stat'ed
+fn fingerprint(path: &Path) -> Option<(u64, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((meta.len(), modified.as_millis() as u64))
+}
+
+impl Checkpoint {
+    pub fn new(integration_id: &str, target: CheckpointTarget) -> Self {
+        Checkpoint {
+            version: CHECKPOINT_VERSION,
+            integration_id: integration_id.to_string(),
+            target,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Read a checkpoint; Ok(None) when there is none at `path`
+    pub fn load(path: &Path) -> Result<Option<Checkpoint>, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("read {} failed: {}", path.display(), e)),
+        };
+        // The version first: older checkpoints lack fields of this one
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let invalid = |e: serde_json::Error| format!("{}: invalid checkpoint: {}", path.display(), e);
+        let Version { version } = serde_json::from_str(&text).map_err(invalid)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(format!(
+                "{}: checkpoint version {} is not supported (expected {})",
+                path.display(),
+                version,
+                CHECKPOINT_VERSION
+            ));
+        }
+        serde_json::from_str(&text).map(Some).map_err(invalid)
+    }
+
+    /// The entry for `csv` if it was completed and has not changed since
+    pub fn completed(&self, csv: &Path) -> Option<&CheckpointEntry> {
+        let entry = self.files.get(&csv.display().to_string())?;
+        let (size, modified_ms) = fingerprint(csv)?;
+        (entry.size == size && entry.modified_ms == modified_ms).then_some(entry)
+    }
+
+    pub fn mark_completed(&mut self, csv: &Path, records: u64, stem: &str, outputs: FileOutputs) {
+        if let Some((size, modified_ms)) = fingerprint(csv) {
+            self.files.insert(
+                csv.display().to_string(),
+                CheckpointEntry {
+                    size,
+                    modified_ms,
+                    records,
+                    stem: stem.to_string(),
+                    outputs,
+                },
+            );
+        }
+    }
+
+    /// Atomically replace the checkpoint at `path`
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut tmp = path.as_os_str().to_os_string();
+        tmp.push(".tmp");
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&tmp, text)
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("write {} failed: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = scratch("checkpoint-round-trip");
+        let csv = dir.join("a_AB4_part1.csv");
+        fs::write(&csv, "part_number\n1\n").unwrap();
+        let mut checkpoint = Checkpoint::new("507f1f77bcf86cd799439011", CheckpointTarget::default());
+        checkpoint.mark_completed(&csv, 1, "a_AB4_part1", FileOutputs::default());
+
+        let path = dir.join(DEFAULT_NAME);
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap().expect("saved above");
+        assert_eq!(loaded.integration_id, "507f1f77bcf86cd799439011");
+        assert_eq!(loaded.files, checkpoint.files);
+        assert_eq!(loaded.completed(&csv).map(|e| e.records), Some(1));
+
+        assert!(Checkpoint::load(&dir.join("missing.json")).unwrap().is_none());
+        fs::write(&path, r#"{"version":99,"integration_id":"","files":{}}"#).unwrap();
+        assert!(Checkpoint::load(&path).unwrap_err().contains("version 99"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn changed_file_is_transformed_again() {
+        let dir = scratch("checkpoint-fingerprint");
+        let csv = dir.join("a_AB4_part1.csv");
+        fs::write(&csv, "part_number\n1\n").unwrap();
+        let mut checkpoint = Checkpoint::new("", CheckpointTarget::default());
+        checkpoint.mark_completed(&csv, 1, "a_AB4_part1", FileOutputs::default());
+        assert!(checkpoint.completed(&csv).is_some());
+
+        // Same size, new mtime: a re-download of identical length
+        let later = SystemTime::now() + Duration::from_secs(60);
+        fs::File::options().write(true).open(&csv).unwrap().set_modified(later).unwrap();
+        assert!(checkpoint.completed(&csv).is_none());
+
+        // Re-recorded, then a different size
+        checkpoint.mark_completed(&csv, 1, "a_AB4_part1", FileOutputs::default());
+        assert!(checkpoint.completed(&csv).is_some());
+        fs::write(&csv, "part_number\n1\n2\n").unwrap();
+        fs::File::options().write(true).open(&csv).unwrap().set_modified(later).unwrap();
+        assert!(checkpoint.completed(&csv).is_none());
+
+        // Never recorded, or gone since
+        assert!(checkpoint.completed(&dir.join("b_AB4_part1.csv")).is_none());
+        fs::remove_file(&csv).unwrap();
+        assert!(checkpoint.completed(&csv).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_replaces_through_a_tmp_file() {
+        let dir = scratch("checkpoint-atomic");
+        let path = dir.join(DEFAULT_NAME);
+        let tmp = dir.join(format!("{}.tmp", DEFAULT_NAME));
+        fs::write(&path, "previous run").unwrap();
+        // Left over from a run killed mid-write
+        fs::write(&tmp, "partial").unwrap();
+
+        Checkpoint::new("next", CheckpointTarget::default()).save(&path).unwrap();
+        assert!(!tmp.exists());
+        assert_eq!(Checkpoint::load(&path).unwrap().unwrap().integration_id, "next");
+
+        // A failed write leaves the previous checkpoint intact
+        fs::create_dir(&tmp).unwrap();
+        assert!(Checkpoint::new("lost", CheckpointTarget::default()).save(&path).is_err());
+        assert_eq!(Checkpoint::load(&path).unwrap().unwrap().integration_id, "next");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resumed_run_skips_only_unchanged_files() {
+        let dir = scratch("checkpoint-resume");
+        let (input, output) = (dir.join("in"), dir.join("out"));
+        fs::create_dir_all(&input).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        let files: Vec<PathBuf> = ["a_AB4_part1.csv", "b_AB4_part1.csv"].iter().map(|f| input.join(f)).collect();
+        for f in &files {
+            fs::write(f, "part_number;price\nP1;1,5\n").unwrap();
+        }
+        let path = output.join(DEFAULT_NAME);
+        let run = |resume: bool| {
+            crate::Transformer::builder(crate::MappingConfig::new("", ""), &output)
+                .checkpoint(&path, resume)
+                .build()
+                .unwrap()
+                .run(&files)
+        };
+        let skipped = |summary: crate::RunSummary| summary.files.iter().map(|f| f.skipped).collect::<Vec<_>>();
+
+        assert_eq!(skipped(run(false)), [false, false]);
+        assert_eq!(Checkpoint::load(&path).unwrap().unwrap().files.len(), 2);
+        let resumed = run(true);
+        assert_eq!(resumed.files[0].outputs.ndjson, Some(output.join("a_AB4_part1.ndjson").display().to_string()));
+        assert_eq!(skipped(resumed), [true, true]);
+
+        fs::write(&files[1], "part_number;price\nP1;1,5\nP2;2\n").unwrap();
+        let summary = run(true);
+        assert_eq!(summary.files[1].records, 2);
+        assert_eq!(skipped(summary), [true, false]);
+        // Without resume every file is read again
+        assert_eq!(skipped(run(false)), [false, false]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resume_into_another_target_is_refused() {
+        let dir = scratch("checkpoint-target");
+        let (input, output, other) = (dir.join("in"), dir.join("out"), dir.join("other"));
+        for d in [&input, &output, &other] {
+            fs::create_dir_all(d).unwrap();
+        }
+        let files = vec![input.join("a_AB4_part1.csv")];
+        fs::write(&files[0], "part_number;price\nP1;1,5\n").unwrap();
+        let path = dir.join(DEFAULT_NAME);
+        let builder = |out: &Path| crate::Transformer::builder(crate::MappingConfig::new("", ""), out).checkpoint(&path, true);
+        builder(&output).build().unwrap().run(&files);
+
+        let err = builder(&other).build().err().expect("other output directory");
+        assert!(err.contains("output directory"), "{}", err);
+        let err = builder(&output).es_index("parts_v2").build().err().expect("other index");
+        assert!(err.contains("ES index"), "{}", err);
+        let err = builder(&output).bson(true).build().err().expect("BSON instead of NDJSON");
+        assert!(err.contains("BSON"), "{}", err);
+        let update = crate::bulk_action::BulkActionConfig {
+            op: crate::bulk_action::BulkOp::Update,
+            ..Default::default()
+        };
+        let err = builder(&output).bulk_action(update).build().err().expect("other op");
+        assert!(err.contains("ES op"), "{}", err);
+
+        // The same target still resumes
+        assert!(builder(&output).build().unwrap().run(&files).files[0].skipped);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
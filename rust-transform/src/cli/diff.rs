@@ -0,0 +1,272 @@
+// =============================================================================
+// `turbo-transform diff <old.csv> <new.csv>` — what a new supplier file changes
+// =============================================================================
+// Both files go through the same mapping as `transform`; records are matched
+// on --key (default partNumber,brand,stockCode, joined as for an ES _id) and
+// compared by a hash of their fields, so only keys and hashes are held in
+// memory; the changed records listed as examples are read again to show
+// which fields differ.
+// fileName and importedAt always differ between runs and are ignored.
+// =============================================================================
+
+use super::{Command, Matches, Opt};
+use serde_json::{json, Map, Value};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+use turbo_transform::bulk_action::{self, DocField};
+use turbo_transform::{MappingConfig, Records};
+
+pub const COMMAND: Command = Command {
+    name: "diff",
+    args: "<old.csv> <new.csv>",
+    about: "Compare the records two versions of a supplier file produce",
+    opts: &[
+        Opt { name: "key", value: Some("a,b,.."), help: "Fields that identify a record (default partNumber,brand,stockCode)" },
+        Opt { name: "format", value: Some("fmt"), help: "json (default) or text" },
+        Opt { name: "limit", value: Some("n"), help: "Example keys listed per category (default 20)" },
+    ],
+    run,
+};
+
+const DEFAULT_KEY: [DocField; 3] = [DocField::PartNumber, DocField::Brand, DocField::StockCode];
+const DEFAULT_LIMIT: usize = 20;
+const IGNORED_FIELDS: [&str; 2] = ["fileName", "importedAt"];
+
+/// One file's records as key → hash of the compared fields, so neither file
+/// is held in memory; the fields of the listed examples are read again
+struct Side {
+    hashes: BTreeMap<String, u64>,
+    total: u64,
+    duplicate_keys: u64,
+}
+
+/// Call `f` with the key and the compared fields of every record of `path`
+fn each_record(
+    path: &Path,
+    key: &[DocField],
+    mapping: &MappingConfig,
+    mut f: impl FnMut(String, Map<String, Value>),
+) -> Result<(), String> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown.csv");
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut records = Records::new(file, file_name, mapping).map_err(|e| format!("{}: {}", file_name, e))?;
+    while let Some(r) = records.next_record() {
+        let mut id = String::new();
+        bulk_action::join_id(&mut id, key, &r.es());
+        let Value::Object(mut fields) = serde_json::to_value(&r).map_err(|e| e.to_string())? else {
+            unreachable!("PartRecord serialises to an object");
+        };
+        for name in IGNORED_FIELDS {
+            fields.remove(name);
+        }
+        f(id, fields);
+    }
+    Ok(())
+}
+
+fn load(path: &Path, key: &[DocField], mapping: &MappingConfig) -> Result<Side, String> {
+    let mut side = Side { hashes: BTreeMap::new(), total: 0, duplicate_keys: 0 };
+    each_record(path, key, mapping, |id, fields| {
+        side.total += 1;
+        match side.hashes.entry(id) {
+            Entry::Occupied(_) => side.duplicate_keys += 1, // first occurrence wins
+            Entry::Vacant(slot) => {
+                let mut h = DefaultHasher::new();
+                Value::Object(fields).to_string().hash(&mut h);
+                slot.insert(h.finish());
+            }
+        }
+    })?;
+    Ok(side)
+}
+
+/// The fields of the first record with each of `ids`
+fn fields_of(
+    path: &Path,
+    key: &[DocField],
+    mapping: &MappingConfig,
+    ids: &BTreeSet<&str>,
+) -> Result<BTreeMap<String, Map<String, Value>>, String> {
+    let mut out = BTreeMap::new();
+    each_record(path, key, mapping, |id, fields| {
+        if ids.contains(id.as_str()) {
+            out.entry(id).or_insert(fields);
+        }
+    })?;
+    Ok(out)
+}
+
+/// What changed from `old` to `new`, with up to `limit` examples of each
+struct Diff {
+    old: Side,
+    new: Side,
+    added: u64,
+    removed: u64,
+    changed: u64,
+    unchanged: u64,
+    added_examples: Vec<String>,
+    removed_examples: Vec<String>,
+    /// Key and the fields that differ
+    changed_examples: Vec<(String, Vec<Value>)>,
+}
+
+fn compare(old_path: &Path, new_path: &Path, key: &[DocField], limit: usize) -> Result<Diff, String> {
+    let mapping = MappingConfig::new("", "");
+    let old = load(old_path, key, &mapping)?;
+    let new = load(new_path, key, &mapping)?;
+
+    let only = |a: &Side, b: &Side| {
+        let mut keys = a.hashes.keys().filter(|k| !b.hashes.contains_key(*k));
+        let examples: Vec<String> = keys.by_ref().take(limit).cloned().collect();
+        (examples.len() as u64 + keys.count() as u64, examples)
+    };
+    let (removed, removed_examples) = only(&old, &new);
+    let (added, added_examples) = only(&new, &old);
+    let mut changed_ids = Vec::new();
+    let mut unchanged = 0u64;
+    for (id, before) in &old.hashes {
+        match new.hashes.get(id) {
+            Some(after) if after == before => unchanged += 1,
+            Some(_) => changed_ids.push(id.as_str()),
+            None => {}
+        }
+    }
+
+    let shown: BTreeSet<&str> = changed_ids.iter().take(limit).copied().collect();
+    let before = fields_of(old_path, key, &mapping, &shown)?;
+    let after = fields_of(new_path, key, &mapping, &shown)?;
+    let changed_examples = before
+        .into_iter()
+        .map(|(id, before)| {
+            let after = &after[&id];
+            let fields = before
+                .iter()
+                .filter(|(name, value)| after.get(*name) != Some(*value))
+                .map(|(name, value)| json!({ "field": name, "old": value, "new": after.get(name) }))
+                .collect();
+            (id, fields)
+        })
+        .collect();
+
+    Ok(Diff {
+        added,
+        removed,
+        changed: changed_ids.len() as u64,
+        unchanged,
+        added_examples,
+        removed_examples,
+        changed_examples,
+        old,
+        new,
+    })
+}
+
+fn run(m: &Matches) -> i32 {
+    match execute(m) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            1
+        }
+    }
+}
+
+fn execute(m: &Matches) -> Result<i32, String> {
+    m.positional_exact(2, "<old.csv> <new.csv>")?;
+    let key = match m.value("key") {
+        Some(k) => DocField::parse_list(k)?,
+        None => DEFAULT_KEY.to_vec(),
+    };
+    if key.is_empty() {
+        return Err("--key needs at least one field".into());
+    }
+    let text = match m.value("format").unwrap_or("json") {
+        "json" => false,
+        "text" => true,
+        other => return Err(format!("unknown --format '{}' (expected json or text)", other)),
+    };
+    let limit = m.parse("limit")?.unwrap_or(DEFAULT_LIMIT);
+
+    let diff = compare(Path::new(&m.positional[0]), Path::new(&m.positional[1]), &key, limit)?;
+    let (old, new) = (&diff.old, &diff.new);
+
+    if text {
+        println!("old: {} records ({} duplicate keys)", old.total, old.duplicate_keys);
+        println!("new: {} records ({} duplicate keys)", new.total, new.duplicate_keys);
+        println!(
+            "added {}, removed {}, changed {}, unchanged {}",
+            diff.added,
+            diff.removed,
+            diff.changed,
+            diff.unchanged
+        );
+        for id in &diff.added_examples {
+            println!("+ {}", id);
+        }
+        for id in &diff.removed_examples {
+            println!("- {}", id);
+        }
+        for (id, fields) in &diff.changed_examples {
+            println!("~ {}", id);
+            for f in fields {
+                println!("    {}: {} -> {}", f["field"].as_str().unwrap_or(""), f["old"], f["new"]);
+            }
+        }
+    } else {
+        let out = json!({
+            "key": m.value("key").unwrap_or("partNumber,brand,stockCode"),
+            "old": { "file": m.positional[0], "records": old.total, "duplicate_keys": old.duplicate_keys },
+            "new": { "file": m.positional[1], "records": new.total, "duplicate_keys": new.duplicate_keys },
+            "added": diff.added,
+            "removed": diff.removed,
+            "changed": diff.changed,
+            "unchanged": diff.unchanged,
+            "examples": {
+                "added": diff.added_examples,
+                "removed": diff.removed_examples,
+                "changed": diff
+                    .changed_examples
+                    .iter()
+                    .map(|(id, fields)| json!({ "key": id, "fields": fields }))
+                    .collect::<Vec<_>>(),
+            },
+        });
+        println!("{}", serde_json::to_string_pretty(&out).map_err(|e| e.to_string())?);
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use std::fs;
+
+    #[test]
+    fn counts_every_change_and_lists_examples() {
+        let dir = scratch("diff-compare");
+        let (old, new) = (dir.join("old_AB4_part1.csv"), dir.join("new_AB4_part1.csv"));
+        fs::write(&old, "part_number;brand;price\nP1;BOSCH;1\nP2;BOSCH;2\nP3;BOSCH;3\nP4;TRW;4\nP1;BOSCH;9\n").unwrap();
+        fs::write(&new, "part_number;brand;price\nP1;BOSCH;1\nP2;BOSCH;2.5\nP4;TRW;5\nP5;TRW;5\nP6;TRW;6\n").unwrap();
+
+        let diff = compare(&old, &new, &DEFAULT_KEY, 1).unwrap();
+        assert_eq!((diff.old.total, diff.old.duplicate_keys), (5, 1));
+        assert_eq!((diff.new.total, diff.new.duplicate_keys), (5, 0));
+        // fileName differs between the two files and is not a change
+        assert_eq!((diff.added, diff.removed, diff.changed, diff.unchanged), (2, 1, 2, 1));
+        assert_eq!(diff.added_examples, ["P5:TRW:AB4"]);
+        assert_eq!(diff.removed_examples, ["P3:BOSCH:AB4"]);
+        assert_eq!(diff.changed_examples.len(), 1);
+        let (id, fields) = &diff.changed_examples[0];
+        assert_eq!(id, "P2:BOSCH:AB4");
+        assert_eq!(fields, &[json!({ "field": "price", "old": 2.0, "new": 2.5 })]);
+
+        let diff = compare(&old, &new, &[DocField::Brand], 10).unwrap();
+        assert_eq!((diff.old.duplicate_keys, diff.new.duplicate_keys), (3, 3));
+        assert_eq!((diff.added, diff.removed, diff.changed, diff.unchanged), (0, 0, 1, 1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
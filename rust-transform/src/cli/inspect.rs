@@ -0,0 +1,289 @@
+// =============================================================================
+// `turbo-transform inspect <file>` — why a supplier file maps the way it does
+// =============================================================================
+// Reads the file exactly like `transform` would and reports the detected
+// encoding and delimiter, what every header resolved to (or why it didn't),
+// the first few records, and row-level warnings. --format json gives the
+// same report as one object for the admin UI.
+// =============================================================================
+
+use super::{Command, Matches, Opt};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use turbo_transform::parse::{extract_stock_code_from_filename, sniff_encoding};
+use turbo_transform::{ColumnMap, MappingConfig, Part, Records, RowStats};
+
+pub const COMMAND: Command = Command {
+    name: "inspect",
+    args: "<file.csv>",
+    about: "Show encoding, delimiter, header mapping, sample records and parse warnings for one CSV",
+    opts: &[
+        Opt { name: "records", value: Some("n"), help: "Transformed records to show (default 5)" },
+        Opt { name: "format", value: Some("fmt"), help: "text (default) or json" },
+    ],
+    run,
+};
+
+const DEFAULT_RECORDS: usize = 5;
+/// Bytes sampled for encoding detection
+const ENCODING_SAMPLE: u64 = 64 * 1024;
+
+/// One header cell and what became of it
+struct HeaderReport {
+    header: String,
+    field: Option<&'static str>,
+    /// Why `field` is None
+    reason: Option<String>,
+}
+
+fn explain(columns: &ColumnMap, headers: &csv::StringRecord, column: usize) -> HeaderReport {
+    let header = headers.get(column).unwrap_or("").to_string();
+    if let Some(field) = columns.field_of(column) {
+        return HeaderReport { header, field: Some(field), reason: None };
+    }
+    let candidates = ColumnMap::candidates(&header);
+    let reason = if header.trim().is_empty() {
+        "empty header".to_string()
+    } else if candidates.is_empty() {
+        "no mapping rule matches this header".to_string()
+    } else {
+        candidates
+            .iter()
+            .map(|&field| match columns.column_of(field) {
+                Some(k) => format!("{} already mapped from column {} (\"{}\")", field, k, headers.get(k).unwrap_or("")),
+                None => format!("{} not available", field),
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+    HeaderReport { header, field: None, reason: Some(reason) }
+}
+
+fn run(m: &Matches) -> i32 {
+    match execute(m) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            1
+        }
+    }
+}
+
+fn execute(m: &Matches) -> Result<i32, String> {
+    m.positional_exact(1, "exactly one <file.csv>")?;
+    let json_output = match m.value("format").unwrap_or("text") {
+        "text" => false,
+        "json" => true,
+        other => return Err(format!("unknown --format '{}' (expected text or json)", other)),
+    };
+    let sample_records = m.parse("records")?.unwrap_or(DEFAULT_RECORDS);
+
+    let report = inspect(Path::new(&m.positional[0]), sample_records)?;
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report.to_json()).map_err(|e| e.to_string())?);
+    } else {
+        report.print()?;
+    }
+    Ok(0)
+}
+
+/// Everything `inspect` reports about one file
+struct Report {
+    file_name: String,
+    encoding: &'static str,
+    delimiter: char,
+    headers: Vec<HeaderReport>,
+    unmapped: Vec<&'static str>,
+    samples: Vec<Part>,
+    stats: RowStats,
+    total_records: u64,
+    warnings: Vec<String>,
+}
+
+fn inspect(path: &Path, sample_records: usize) -> Result<Report, String> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown.csv");
+    let open = || File::open(path).map_err(|e| format!("{}: {}", path.display(), e));
+
+    let mut head = Vec::new();
+    open()?
+        .take(ENCODING_SAMPLE)
+        .read_to_end(&mut head)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let encoding = sniff_encoding(&head);
+
+    let mut records = Records::open(open()?, file_name, &MappingConfig::new("", ""))
+        .map_err(|e| format!("{}: {}", file_name, e))?;
+    let delimiter = records.delimiter() as char;
+    let columns = records.columns().clone();
+    let headers = records.headers().clone();
+    let header_reports: Vec<HeaderReport> = (0..headers.len()).map(|i| explain(&columns, &headers, i)).collect();
+    let unmapped: Vec<&'static str> = columns
+        .fields()
+        .into_iter()
+        .filter(|(_, c)| c.is_none())
+        .map(|(f, _)| f)
+        .collect();
+
+    let mut samples: Vec<Part> = Vec::new();
+    let mut total_records: u64 = 0;
+    while let Some(r) = records.next_record() {
+        if samples.len() < sample_records {
+            samples.push(Part::from(&r));
+        }
+        total_records += 1;
+    }
+    let stats = *records.stats();
+
+    // File-level warnings first, then the row-level ones the reader kept
+    let mut warnings: Vec<String> = Vec::new();
+    match encoding {
+        "utf-16le" | "utf-16be" => warnings.push(format!("{} is not supported — re-export the file as UTF-8", encoding)),
+        "non-utf-8" => warnings.push("file is not valid UTF-8 (Excel ANSI export?) — rows with invalid bytes are skipped".into()),
+        _ => {}
+    }
+    if headers.len() <= 1 {
+        warnings.push(format!("header has {} column(s) with delimiter '{}' — wrong delimiter?", headers.len(), delimiter));
+    }
+    if columns.part_number.is_none() {
+        warnings.push("no part number column detected — every row is skipped".into());
+    }
+    if columns.price.is_none() {
+        warnings.push("no price column — every record gets price 0".into());
+    }
+    if columns.stock_code.is_none() && extract_stock_code_from_filename(file_name).is_empty() {
+        warnings.push("no stock code column and none in the file name (…_<CODE>_partN.csv) — stockCode is empty".into());
+    }
+    if stats.malformed > 0 {
+        warnings.push(format!("{} malformed row(s) skipped", stats.malformed));
+    }
+    if stats.missing_part_number > 0 && columns.part_number.is_some() {
+        warnings.push(format!("{} row(s) without a part number skipped", stats.missing_part_number));
+    }
+    if stats.ragged > 0 {
+        warnings.push(format!("{} row(s) with a different field count than the header", stats.ragged));
+    }
+    warnings.extend(records.warnings().iter().cloned());
+
+    Ok(Report {
+        file_name: file_name.to_string(),
+        encoding,
+        delimiter,
+        headers: header_reports,
+        unmapped,
+        samples,
+        stats,
+        total_records,
+        warnings,
+    })
+}
+
+impl Report {
+    fn to_json(&self) -> Value {
+        let Report { file_name, encoding, delimiter, headers: header_reports, unmapped, samples, stats, total_records, warnings } =
+            self;
+        json!({
+            "file": file_name,
+            "encoding": encoding,
+            "delimiter": delimiter.to_string(),
+            "headers": header_reports
+                .iter()
+                .enumerate()
+                .map(|(i, h)| {
+                    let mut v = json!({ "column": i, "header": h.header, "field": h.field });
+                    if let Some(ref reason) = h.reason {
+                        v["reason"] = Value::from(reason.as_str());
+                    }
+                    v
+                })
+                .collect::<Vec<_>>(),
+            "unmapped_fields": unmapped,
+            "records": samples,
+            "rows": {
+                "rows": stats.rows,
+                "records": total_records,
+                "malformed": stats.malformed,
+                "missing_part_number": stats.missing_part_number,
+                "ragged": stats.ragged,
+            },
+            "warnings": warnings,
+        })
+    }
+
+    fn print(&self) -> Result<(), String> {
+        let Report { file_name, encoding, delimiter, headers: header_reports, unmapped, samples, stats, total_records, warnings } =
+            self;
+        println!("File:       {}", file_name);
+        println!("Encoding:   {}", encoding);
+        println!("Delimiter:  '{}'", delimiter);
+        println!();
+        println!("Headers:");
+        let width = header_reports.iter().map(|h| h.header.chars().count()).max().unwrap_or(0) + 2;
+        for (i, h) in header_reports.iter().enumerate() {
+            let quoted = format!("\"{}\"", h.header);
+            match (h.field, &h.reason) {
+                (Some(field), _) => println!("  {:>3}  {:<width$}  -> {}", i, quoted, field),
+                (None, Some(reason)) => println!("  {:>3}  {:<width$}  -- {}", i, quoted, reason),
+                (None, None) => println!("  {:>3}  {}", i, quoted),
+            }
+        }
+        if !unmapped.is_empty() {
+            println!("  unmapped fields: {}", unmapped.join(", "));
+        }
+        println!();
+        println!(
+            "Rows:       {} read, {} records, {} malformed, {} without part number, {} ragged",
+            stats.rows, total_records, stats.malformed, stats.missing_part_number, stats.ragged
+        );
+        println!();
+        println!("Records (first {} of {}):", samples.len(), total_records);
+        for part in samples {
+            println!("  {}", serde_json::to_string(part).map_err(|e| e.to_string())?);
+        }
+        if !warnings.is_empty() {
+            println!();
+            println!("Warnings:");
+            for w in warnings {
+                println!("  - {}", w);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use std::fs;
+
+    #[test]
+    fn reports_columns_samples_and_warnings() {
+        let dir = scratch("inspect");
+        let csv = dir.join("stock_AB4_part1.csv");
+        fs::write(&csv, "part_number;brand;price;colour;\nP1;BOSCH;1,50;red;\nP2;TRW;2;grün;\n;TRW;3;green;\n").unwrap();
+
+        let report = inspect(&csv, 1).unwrap().to_json();
+        assert_eq!(report["file"], "stock_AB4_part1.csv");
+        assert_eq!(report["encoding"], "utf-8");
+        assert_eq!(report["delimiter"], ";");
+        let headers = report["headers"].as_array().unwrap();
+        let fields: Vec<&Value> = headers.iter().map(|h| &h["field"]).collect();
+        assert_eq!(fields, [&json!("partNumber"), &json!("brand"), &json!("price"), &Value::Null, &Value::Null]);
+        assert_eq!(headers[3]["reason"], "no mapping rule matches this header");
+        assert_eq!(headers[4]["reason"], "empty header");
+        assert!(report["unmapped_fields"].as_array().unwrap().contains(&json!("description")));
+
+        // Only the first record is sampled, but every row is counted
+        let records = report["records"].as_array().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["partNumber"], "P1");
+        assert_eq!(records[0]["brand"], "BOSCH");
+        assert_eq!(records[0]["price"], 1.5);
+        assert_eq!(records[0]["stockCode"], "AB4");
+        assert_eq!(report["rows"], json!({ "rows": 3, "records": 2, "malformed": 0, "missing_part_number": 1, "ragged": 0 }));
+        assert_eq!(report["warnings"], json!(["1 row(s) without a part number skipped"]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,531 @@
+// =============================================================================
+// Command line — subcommands, named options, env vars, config file
+// =============================================================================
+//   turbo-transform <command> [options]
+//
+// Every option can be given three ways, highest precedence first:
+//   1. --name value | --name=value
+//   2. TURBO_<NAME> environment variable (--es-url → TURBO_ES_URL)
+//   3. --config <file.json> (or TURBO_CONFIG): one object per command,
+//        { "transform": { "es-index": "automotive_parts", "bson": true } }
+//
+// Unknown options, unknown config keys, repeated options and options that
+// contradict each other are errors — nothing falls back silently.
+// =============================================================================
+
+pub mod diff;
+pub mod inspect;
+pub mod schema;
+pub mod serve;
+pub mod stream;
+pub mod transform;
+pub mod validate;
+pub mod watch;
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::panic;
+use std::thread;
+use turbo_transform::Event;
+
+/// One named option of a command
+pub struct Opt {
+    pub name: &'static str,
+    /// Value placeholder for --help; None for on/off switches
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+pub struct Command {
+    pub name: &'static str,
+    /// Positional arguments, as shown in the usage line
+    pub args: &'static str,
+    pub about: &'static str,
+    pub opts: &'static [Opt],
+    pub run: fn(&Matches) -> i32,
+}
+
+pub const COMMANDS: [&Command; 8] = [
+    &transform::COMMAND,
+    &stream::COMMAND,
+    &serve::COMMAND,
+    &watch::COMMAND,
+    &inspect::COMMAND,
+    &validate::COMMAND,
+    &schema::COMMAND,
+    &diff::COMMAND,
+];
+
+const CONFIG_OPT: Opt = Opt {
+    name: "config",
+    value: Some("file"),
+    help: "JSON config file with per-command sections",
+};
+
+#[derive(Clone, Copy)]
+enum Source {
+    Arg,
+    Env,
+    Config,
+    /// `serve` request params
+    Params,
+}
+
+/// Options resolved for one command invocation
+pub struct Matches {
+    command: &'static str,
+    opts: &'static [Opt],
+    values: HashMap<&'static str, (String, Source)>,
+    pub positional: Vec<String>,
+    help: bool,
+}
+
+impl Matches {
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|(v, _)| v.as_str())
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.value(name) == Some("true")
+    }
+
+    /// Given, and for a switch also on: TURBO_RESUME=false or "resume": false
+    /// in the config file turn a switch off, they do not give it
+    pub fn has(&self, name: &str) -> bool {
+        let switch = self.opts.iter().any(|o| o.name == name && o.value.is_none());
+        match self.values.get(name) {
+            Some((v, _)) => !switch || v == "true",
+            None => false,
+        }
+    }
+
+    pub fn required(&self, name: &str) -> Result<&str, String> {
+        self.value(name)
+            .ok_or_else(|| format!("{} requires --{} (or {})", self.command, name, env_name(name)))
+    }
+
+    /// Parsed value, with where it came from in the error message
+    pub fn parse<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.values.get(name) {
+            None => Ok(None),
+            Some((v, source)) => v.parse::<T>().map(Some).map_err(|_| {
+                let from = match source {
+                    Source::Arg => String::new(),
+                    Source::Env => format!(" (from {})", env_name(name)),
+                    Source::Config => " (from config file)".to_string(),
+                    Source::Params => " (from request params)".to_string(),
+                };
+                format!("invalid value for --{}{}: {}", name, from, v)
+            }),
+        }
+    }
+
+    /// Exactly `n` positional arguments, named as in the usage line
+    pub fn positional_exact(&self, n: usize, usage: &str) -> Result<(), String> {
+        if self.positional.len() == n {
+            Ok(())
+        } else {
+            Err(format!("{} expects {}", self.command, usage))
+        }
+    }
+}
+
+/// --es-url → TURBO_ES_URL
+fn env_name(opt: &str) -> String {
+    format!("TURBO_{}", opt.to_ascii_uppercase().replace('-', "_"))
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" | "" => Some(false),
+        _ => None,
+    }
+}
+
+fn find_opt(cmd: &Command, name: &str) -> Option<&'static Opt> {
+    cmd.opts.iter().find(|o| o.name == name).or(if name == CONFIG_OPT.name {
+        Some(&CONFIG_OPT)
+    } else {
+        None
+    })
+}
+
+/// Resolve `args` (everything after the command name) against `cmd`
+pub fn parse(cmd: &Command, args: &[String]) -> Result<Matches, String> {
+    let mut m = Matches {
+        command: cmd.name,
+        opts: cmd.opts,
+        values: HashMap::new(),
+        positional: Vec::new(),
+        help: false,
+    };
+
+    // 1. Command line
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            m.positional.extend(iter.by_ref().cloned());
+            break;
+        }
+        if arg == "-h" || arg == "--help" {
+            m.help = true;
+            continue;
+        }
+        let Some(flag) = arg.strip_prefix("--") else {
+            if arg.len() > 1 && arg.starts_with('-') {
+                return Err(format!("unknown option {} for {}", arg, cmd.name));
+            }
+            m.positional.push(arg.clone());
+            continue;
+        };
+        let (name, inline) = match flag.split_once('=') {
+            Some((n, v)) => (n, Some(v.to_string())),
+            None => (flag, None),
+        };
+        let opt = find_opt(cmd, name).ok_or_else(|| format!("unknown option --{} for {}", name, cmd.name))?;
+        let value = match (opt.value, inline) {
+            (None, None) => "true".to_string(),
+            (None, Some(_)) => return Err(format!("--{} takes no value", name)),
+            (Some(_), Some(v)) => v,
+            (Some(_), None) => match iter.next() {
+                Some(v) if !v.starts_with("--") => v.clone(),
+                _ => return Err(format!("missing value for --{}", name)),
+            },
+        };
+        if m.values.insert(opt.name, (value, Source::Arg)).is_some() {
+            return Err(format!("--{} given more than once", name));
+        }
+    }
+
+    // 2. Environment
+    for opt in cmd.opts.iter().chain([&CONFIG_OPT]) {
+        if m.values.contains_key(opt.name) {
+            continue;
+        }
+        let var = env_name(opt.name);
+        let Ok(value) = env::var(&var) else { continue };
+        let value = match opt.value {
+            Some(_) if value.is_empty() => continue,
+            Some(_) => value,
+            None => match parse_switch(&value) {
+                Some(on) => on.to_string(),
+                None => return Err(format!("{} must be true or false, got '{}'", var, value)),
+            },
+        };
+        m.values.insert(opt.name, (value, Source::Env));
+    }
+
+    // 3. Config file
+    if let Some(path) = m.value(CONFIG_OPT.name).map(str::to_string) {
+        for (name, value) in load_config(&path, cmd)? {
+            m.values.entry(name).or_insert((value, Source::Config));
+        }
+    }
+
+    Ok(m)
+}
+
+/// Options given as one JSON object (a `serve` request's params) — nothing
+/// from the command line, environment or config file
+pub fn from_json(cmd: &Command, params: &Map<String, Value>) -> Result<Matches, String> {
+    let mut m = Matches {
+        command: cmd.name,
+        opts: cmd.opts,
+        values: HashMap::new(),
+        positional: Vec::new(),
+        help: false,
+    };
+    for (name, value) in option_values(cmd, params)? {
+        m.values.insert(name, (value, Source::Params));
+    }
+    Ok(m)
+}
+
+/// JSON option values as strings: switches are booleans, arrays are joined
+/// with commas
+fn option_values(cmd: &Command, entries: &Map<String, Value>) -> Result<Vec<(&'static str, String)>, String> {
+    let mut out = Vec::new();
+    for (key, value) in entries {
+        let opt = cmd
+            .opts
+            .iter()
+            .find(|o| o.name == key)
+            .ok_or_else(|| format!("unknown option \"{}\" for {}", key, cmd.name))?;
+        let value = match (opt.value, value) {
+            (None, Value::Bool(on)) => on.to_string(),
+            (Some(_), Value::String(s)) => s.clone(),
+            (Some(_), Value::Number(n)) => n.to_string(),
+            (Some(_), Value::Array(items)) if items.iter().all(Value::is_string) => items
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(","),
+            _ => return Err(format!("invalid value for \"{}\": {}", key, value)),
+        };
+        out.push((opt.name, value));
+    }
+    Ok(out)
+}
+
+/// The `cmd` section of a config file, as option name → value strings
+fn load_config(path: &str, cmd: &Command) -> Result<Vec<(&'static str, String)>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("config {}: {}", path, e))?;
+    let root: Value = serde_json::from_str(&text).map_err(|e| format!("config {}: {}", path, e))?;
+    let Value::Object(sections) = root else {
+        return Err(format!("config {}: expected a JSON object", path));
+    };
+
+    let mut out = Vec::new();
+    for (section, body) in &sections {
+        if !COMMANDS.iter().any(|c| c.name == section) {
+            return Err(format!("config {}: unknown command section \"{}\"", path, section));
+        }
+        if section != cmd.name {
+            continue;
+        }
+        let Value::Object(entries) = body else {
+            return Err(format!("config {}: \"{}\" must be an object", path, section));
+        };
+        out = option_values(cmd, entries).map_err(|e| format!("config {}: {}", path, e))?;
+    }
+    Ok(out)
+}
+
+// =============================================================================
+// Help
+// =============================================================================
+pub fn print_usage(program: &str) {
+    eprintln!("turbo-transform {}", env!("CARGO_PKG_VERSION"));
+    eprintln!();
+    eprintln!("Usage: {} <command> [options]", program);
+    eprintln!();
+    eprintln!("Commands:");
+    for cmd in COMMANDS {
+        eprintln!("  {:<11} {}", cmd.name, cmd.about);
+    }
+    eprintln!();
+    eprintln!("Run `{} <command> --help` for the options of a command.", program);
+    eprintln!("  -V, --version  Print version");
+}
+
+pub fn print_command_help(program: &str, cmd: &Command) {
+    eprintln!("{}", cmd.about);
+    eprintln!();
+    eprintln!("Usage: {} {} [options] {}", program, cmd.name, cmd.args);
+    eprintln!();
+    eprintln!("Options (each also settable as TURBO_<NAME> or in the config file):");
+    for opt in cmd.opts.iter().chain([&CONFIG_OPT]) {
+        let left = match opt.value {
+            Some(v) => format!("--{} <{}>", opt.name, v),
+            None => format!("--{}", opt.name),
+        };
+        eprintln!("  {:<30} {}", left, opt.help);
+    }
+}
+
+/// Entry point: dispatch `args[1]` to its command; returns the exit code
+pub fn main(args: &[String]) -> i32 {
+    // stderr carries JSON events, so panics are reported as one too — the
+    // default hook's "thread … panicked at" text would break consumers
+    panic::set_hook(Box::new(|info| {
+        let event = Event::Panic {
+            thread: thread::current().name().unwrap_or("<unnamed>").to_string(),
+            message: info.payload_as_str().unwrap_or("non-string panic payload").to_string(),
+            location: info.location().map(|l| l.to_string()),
+        };
+        eprintln!("{}", event.to_json());
+    }));
+    let program = args.first().map(|s| s.as_str()).unwrap_or("turbo-transform");
+
+    let Some(name) = args.get(1) else {
+        print_usage(program);
+        return 1;
+    };
+    match name.as_str() {
+        "-V" | "--version" => {
+            println!("turbo-transform {}", env!("CARGO_PKG_VERSION"));
+            return 0;
+        }
+        "-h" | "--help" | "help" => {
+            print_usage(program);
+            return 0;
+        }
+        _ => {}
+    }
+
+    let Some(cmd) = COMMANDS.iter().find(|c| c.name == name) else {
+        eprintln!("ERROR: unknown command '{}'", name);
+        if !name.starts_with('-') && args.len() >= 3 {
+            eprintln!("The positional form was replaced by: {} transform --input <dir> --output <dir> --integration-id <id> --es-index <name>", program);
+        }
+        print_usage(program);
+        return 1;
+    };
+
+    let matches = match parse(cmd, &args[2..]) {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            eprintln!("Run `{} {} --help` for usage.", program, cmd.name);
+            return 1;
+        }
+    };
+    if matches.help {
+        print_command_help(program, cmd);
+        return 0;
+    }
+    (cmd.run)(&matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const OPTS: &[Opt] = &[
+        Opt { name: "index", value: Some("name"), help: "" },
+        Opt { name: "limit", value: Some("n"), help: "" },
+        Opt { name: "fields", value: Some("a,b"), help: "" },
+        Opt { name: "strict", value: None, help: "" },
+    ];
+    const CMD: Command = Command { name: "transform", args: "", about: "", opts: OPTS, run: |_| 0 };
+
+    /// Options no other test reads, so setting their env vars cannot race
+    const ENV_OPTS: &[Opt] = &[
+        Opt { name: "cli-test-index", value: Some("name"), help: "" },
+        Opt { name: "cli-test-limit", value: Some("n"), help: "" },
+        Opt { name: "cli-test-empty", value: Some("v"), help: "" },
+        Opt { name: "cli-test-on", value: None, help: "" },
+        Opt { name: "cli-test-off", value: None, help: "" },
+        Opt { name: "cli-test-zero", value: None, help: "" },
+        Opt { name: "cli-test-bad", value: None, help: "" },
+    ];
+    const ENV_CMD: Command = Command { name: "transform", args: "", about: "", opts: ENV_OPTS, run: |_| 0 };
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn scratch_config(name: &str, body: Value) -> String {
+        let path = env::temp_dir().join(format!("turbo-cli-{}-{}.json", name, std::process::id()));
+        fs::write(&path, body.to_string()).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn values_switches_and_positionals() {
+        let m = parse(&CMD, &args(&["a", "--index", "parts", "--limit=5", "--strict", "b"])).unwrap();
+        assert_eq!(m.value("index"), Some("parts"));
+        assert_eq!(m.parse::<u32>("limit").unwrap(), Some(5));
+        assert!(m.flag("strict") && m.has("strict"));
+        assert_eq!(m.positional, ["a", "b"]);
+        assert!(!m.has("fields"));
+        assert_eq!(m.required("fields").unwrap_err(), "transform requires --fields (or TURBO_FIELDS)");
+
+        let m = parse(&CMD, &args(&["--limit", "x"])).unwrap();
+        assert_eq!(m.parse::<u32>("limit").unwrap_err(), "invalid value for --limit: x");
+    }
+
+    #[test]
+    fn double_dash_ends_the_options() {
+        let m = parse(&CMD, &args(&["--strict", "--", "--index", "x", "-h"])).unwrap();
+        assert_eq!(m.positional, ["--index", "x", "-h"]);
+        assert!(!m.has("index"));
+        assert!(!m.help);
+        assert!(parse(&CMD, &args(&["-h"])).unwrap().help);
+        // A lone "-" is a positional (stdin), not an option
+        assert_eq!(parse(&CMD, &args(&["-"])).unwrap().positional, ["-"]);
+    }
+
+    #[test]
+    fn mistakes_are_errors() {
+        let err = |list: &[&str]| parse(&CMD, &args(list)).err().unwrap();
+        assert_eq!(err(&["--nope"]), "unknown option --nope for transform");
+        assert_eq!(err(&["-x"]), "unknown option -x for transform");
+        assert_eq!(err(&["--strict=true"]), "--strict takes no value");
+        assert_eq!(err(&["--index"]), "missing value for --index");
+        assert_eq!(err(&["--index", "--strict"]), "missing value for --index");
+        assert_eq!(err(&["--index", "a", "--index=b"]), "--index given more than once");
+    }
+
+    #[test]
+    fn environment_then_config_file() {
+        let config = scratch_config(
+            "precedence",
+            json!({ "transform": { "cli-test-index": "from-config", "cli-test-limit": 7, "cli-test-off": true } }),
+        );
+        env::set_var("TURBO_CLI_TEST_INDEX", "from-env");
+        env::set_var("TURBO_CLI_TEST_EMPTY", "");
+        env::set_var("TURBO_CLI_TEST_ON", "YES");
+        env::set_var("TURBO_CLI_TEST_OFF", "false");
+        env::set_var("TURBO_CLI_TEST_ZERO", "0");
+
+        let m = parse(&ENV_CMD, &args(&["--config", &config])).unwrap();
+        assert_eq!(m.value("cli-test-index"), Some("from-env"));
+        assert_eq!(m.value("cli-test-limit"), Some("7"));
+        assert!(!m.has("cli-test-empty"));
+        assert!(m.flag("cli-test-on") && m.has("cli-test-on"));
+        // Off in the environment: not given, and the config file's true loses
+        assert!(!m.flag("cli-test-off") && !m.has("cli-test-off"));
+        assert!(!m.flag("cli-test-zero") && !m.has("cli-test-zero"));
+
+        let m = parse(&ENV_CMD, &args(&["--cli-test-index", "from-arg", "--config", &config])).unwrap();
+        assert_eq!(m.value("cli-test-index"), Some("from-arg"));
+
+        env::set_var("TURBO_CLI_TEST_LIMIT", "x");
+        let m = parse(&ENV_CMD, &args(&[])).unwrap();
+        assert_eq!(m.parse::<u32>("cli-test-limit").unwrap_err(), "invalid value for --cli-test-limit (from TURBO_CLI_TEST_LIMIT): x");
+
+        env::set_var("TURBO_CLI_TEST_BAD", "maybe");
+        assert_eq!(
+            parse(&ENV_CMD, &args(&[])).err().unwrap(),
+            "TURBO_CLI_TEST_BAD must be true or false, got 'maybe'"
+        );
+        for var in ["INDEX", "LIMIT", "EMPTY", "ON", "OFF", "ZERO", "BAD"] {
+            env::remove_var(format!("TURBO_CLI_TEST_{}", var));
+        }
+        fs::remove_file(&config).unwrap();
+    }
+
+    #[test]
+    fn config_file_mistakes_are_errors() {
+        let err = |name: &str, body: Value| {
+            let path = scratch_config(name, body);
+            let e = parse(&CMD, &args(&["--config", &path])).err().unwrap();
+            fs::remove_file(&path).unwrap();
+            e.replace(&path, "<config>")
+        };
+        assert_eq!(err("unknown-section", json!({ "nope": {} })), "config <config>: unknown command section \"nope\"");
+        assert_eq!(err("unknown-key", json!({ "transform": { "nope": 1 } })), "config <config>: unknown option \"nope\" for transform");
+        assert_eq!(err("bad-switch", json!({ "transform": { "strict": "yes" } })), "config <config>: invalid value for \"strict\": \"yes\"");
+        assert_eq!(err("not-object", json!([])), "config <config>: expected a JSON object");
+
+        // Other commands' sections are checked by name only
+        let path = scratch_config("other-section", json!({ "stream": { "anything": 1 }, "transform": { "strict": false } }));
+        let m = parse(&CMD, &args(&["--config", &path])).unwrap();
+        assert!(!m.has("strict"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn from_json_params() {
+        let params = json!({ "index": "parts", "limit": 5, "fields": ["a", "b"], "strict": false });
+        let m = from_json(&CMD, params.as_object().unwrap()).unwrap();
+        assert_eq!(m.value("index"), Some("parts"));
+        assert_eq!(m.value("limit"), Some("5"));
+        assert_eq!(m.value("fields"), Some("a,b"));
+        assert!(!m.has("strict"));
+
+        let m = from_json(&CMD, json!({ "limit": "x" }).as_object().unwrap()).unwrap();
+        assert_eq!(m.parse::<u32>("limit").unwrap_err(), "invalid value for --limit (from request params): x");
+        let err = |params: Value| from_json(&CMD, params.as_object().unwrap()).err().unwrap();
+        assert_eq!(err(json!({ "nope": 1 })), "unknown option \"nope\" for transform");
+        assert_eq!(err(json!({ "strict": 1 })), "invalid value for \"strict\": 1");
+        assert_eq!(err(json!({ "fields": [1] })), "invalid value for \"fields\": [1]");
+        // The config file is not read for request params
+        assert_eq!(err(json!({ "config": "x.json" })), "unknown option \"config\" for transform");
+    }
+}
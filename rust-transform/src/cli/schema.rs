@@ -0,0 +1,54 @@
+// =============================================================================
+// `turbo-transform schema` — print the ES index mapping or NDJSON JSON Schema
+// =============================================================================
+
+use super::{Command, Matches, Opt};
+use turbo_transform::schema::{self, DEFAULT_REPLICAS, DEFAULT_SHARDS};
+
+pub const COMMAND: Command = Command {
+    name: "schema",
+    args: "",
+    about: "Print the Elasticsearch index mapping (default) or the NDJSON JSON Schema",
+    opts: &[
+        Opt { name: "es", value: None, help: "Elasticsearch index settings + mappings (default)" },
+        Opt { name: "json-schema", value: None, help: "JSON Schema (draft 2020-12) for one NDJSON line" },
+        Opt { name: "shards", value: Some("n"), help: "number_of_shards for --es (default 5)" },
+        Opt { name: "replicas", value: Some("n"), help: "number_of_replicas for --es (default 0)" },
+    ],
+    run,
+};
+
+fn run(m: &Matches) -> i32 {
+    let out = match document(m) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return 1;
+        }
+    };
+    match serde_json::to_string_pretty(&out) {
+        Ok(s) => {
+            println!("{}", s);
+            0
+        }
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            1
+        }
+    }
+}
+
+fn document(m: &Matches) -> Result<serde_json::Value, String> {
+    m.positional_exact(0, "no positional arguments")?;
+    match (m.flag("es"), m.flag("json-schema")) {
+        (true, true) => Err("choose one of --es or --json-schema".into()),
+        (_, true) if m.has("shards") || m.has("replicas") => {
+            Err("--shards/--replicas only apply to --es".into())
+        }
+        (_, true) => Ok(schema::ndjson_json_schema()),
+        _ => Ok(schema::es_index_mapping(
+            m.parse("shards")?.unwrap_or(DEFAULT_SHARDS),
+            m.parse("replicas")?.unwrap_or(DEFAULT_REPLICAS),
+        )),
+    }
+}
@@ -0,0 +1,892 @@
+// =============================================================================
+// `turbo-transform serve` — one warm engine for many runs, over JSON-RPC 2.0
+// =============================================================================
+// Requests, responses and notifications are JSON-RPC 2.0 objects, one per
+// line, on stdin/stdout (default) or on every connection to --socket <path>.
+// The rayon pool stays up between jobs and concurrent jobs share it.
+//
+//   → {"jsonrpc":"2.0","id":1,"method":"transform","params":{"input":"/in",
+//        "output":"/out","integration-id":"507f…","es-index":"parts",
+//        "files":["prices_AB4_part1.csv"]}}
+//   ← {"jsonrpc":"2.0","id":1,"result":{"job":1}}
+//   ← {"jsonrpc":"2.0","method":"event","params":{"job":1,"event":{"event":"start",…}}}
+//   ← … file_done, progress, … complete
+//   ← {"jsonrpc":"2.0","method":"job_done","params":{"job":1,"state":"done",
+//        "exit_code":0,"errors":[]}}
+//
+// Methods:
+//   transform  params = the transform options, named as in the config file,
+//              plus optional "files" (relative to "input") instead of
+//              discovery → {"job": id}; refused while another job writes
+//              to the same output directory
+//   cancel     {"job": id} — stops the job gracefully, like SIGINT does
+//   status     {"job": id} → that job; no params → {"jobs": […]}. A finished
+//              job is dropped once status has reported it (and the oldest
+//              go when more than MAX_FINISHED pile up unread)
+//   shutdown   cancels every job, waits for them to wind down, then exits
+//
+// Events go to the connection that started the job; closing that connection
+// cancels it, and closing stdin shuts the server down. SIGINT/SIGTERM act
+// like shutdown; a second signal exits immediately. Metrics are per server
+// (--metrics-addr, --metrics-textfile), labelled by each job's integration.
+// The socket is made owner-only (0600): whoever can connect can run jobs.
+// =============================================================================
+
+use super::{from_json, transform, Command, Matches, Opt};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use turbo_transform::{panic_message, Event};
+
+pub const COMMAND: Command = Command {
+    name: "serve",
+    args: "",
+    about: "Keep the engine warm and run transforms on JSON-RPC requests (stdio or a Unix socket)",
+    opts: &[
+        Opt { name: "socket", value: Some("path"), help: "Listen on this Unix socket instead of stdin/stdout" },
+        Opt { name: "metrics-addr", value: Some("host:port"), help: "Serve Prometheus metrics for every job at http://<host:port>/metrics" },
+        Opt { name: "metrics-textfile", value: Some("file.prom"), help: "Rewrite this node_exporter textfile after every job" },
+    ],
+    run,
+};
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Server-defined: no job with that id
+const UNKNOWN_JOB: i64 = -32000;
+/// Server-defined: a running job writes to that output directory, or the
+/// server is shutting down
+const BUSY: i64 = -32001;
+
+/// Finished jobs kept for `status` when nobody asks about them
+const MAX_FINISHED: usize = 256;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into() }
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.code, "message": e.message } }),
+    }
+}
+
+/// One connection's output side — every message is one line
+#[derive(Clone)]
+struct Peer {
+    id: u64,
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl Peer {
+    fn send(&self, message: &Value) {
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // A peer that went away is noticed by its reader, which cancels its jobs
+        let _ = writeln!(out, "{}", message).and_then(|_| out.flush());
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum JobState {
+    Running,
+    Done,
+    /// Could not start, or ended with a non-zero exit code
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    fn name(self) -> &'static str {
+        match self {
+            JobState::Running => "running",
+            JobState::Done => "done",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+}
+
+struct Job {
+    peer: u64,
+    /// The output directory, absolute — one running job per directory
+    output: PathBuf,
+    cancel: Arc<AtomicBool>,
+    state: JobState,
+    files_total: usize,
+    /// Done, rejected or skipped
+    files_done: usize,
+    records: u64,
+    /// The last `progress` heartbeat
+    progress: Option<Value>,
+    exit_code: Option<i32>,
+    errors: Vec<String>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Job {
+    fn status(&self, id: u64) -> Value {
+        json!({
+            "job": id,
+            "state": self.state.name(),
+            "files_total": self.files_total,
+            "files_done": self.files_done,
+            "records": self.records,
+            "progress": self.progress,
+            "exit_code": self.exit_code,
+            "errors": self.errors,
+        })
+    }
+}
+
+/// A validated transform request, not in the job table yet
+struct PendingJob {
+    matches: Matches,
+    files: Option<Vec<String>>,
+    output: PathBuf,
+}
+
+struct Server {
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    next_job: AtomicU64,
+    next_peer: AtomicU64,
+    /// Set by `stop`; no job starts after it
+    stopping: AtomicBool,
+    /// Removed on exit
+    socket: Option<PathBuf>,
+    metrics: Option<transform::MetricsExport>,
+}
+
+impl Server {
+    fn peer(&self, out: Box<dyn Write + Send>) -> Peer {
+        Peer {
+            id: self.next_peer.fetch_add(1, Ordering::Relaxed),
+            out: Arc::new(Mutex::new(out)),
+        }
+    }
+
+    /// Answer every request read from `input` until it closes
+    fn serve_peer(self: &Arc<Self>, input: impl BufRead, peer: &Peer) {
+        for line in input.lines() {
+            let Ok(line) = line else { break };
+            if !line.trim().is_empty() {
+                self.handle_line(peer, &line);
+            }
+        }
+    }
+
+    fn handle_line(self: &Arc<Self>, peer: &Peer, line: &str) {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(e) => {
+                peer.send(&response(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))));
+                return;
+            }
+        };
+        let Value::Object(mut request) = request else {
+            let message = "expected a request object (batches are not supported)";
+            peer.send(&response(Value::Null, Err(RpcError::new(INVALID_REQUEST, message))));
+            return;
+        };
+        // No id: a notification, which gets no response
+        let id = request.remove("id");
+        let method = match (request.remove("jsonrpc"), request.remove("method")) {
+            (Some(v), Some(Value::String(m))) if v == "2.0" => m,
+            _ => {
+                let message = "expected \"jsonrpc\": \"2.0\" and a method name";
+                peer.send(&response(id.unwrap_or(Value::Null), Err(RpcError::new(INVALID_REQUEST, message))));
+                return;
+            }
+        };
+        let params = match request.remove("params") {
+            None | Some(Value::Null) => Map::new(),
+            Some(Value::Object(p)) => p,
+            Some(_) => {
+                let message = "params must be an object";
+                if let Some(id) = id {
+                    peer.send(&response(id, Err(RpcError::new(INVALID_PARAMS, message))));
+                }
+                return;
+            }
+        };
+
+        let result = match method.as_str() {
+            "transform" => match self.register(params).and_then(|job| self.start(peer, job)) {
+                // Answer before the job's first event
+                Ok((job, go)) => {
+                    if let Some(id) = id {
+                        peer.send(&response(id, Ok(json!({ "job": job }))));
+                    }
+                    let _ = go.send(());
+                    return;
+                }
+                Err(e) => Err(e),
+            },
+            "cancel" => self.cancel(&params),
+            "status" => self.status(&params),
+            "shutdown" => {
+                self.stop();
+                if let Some(id) = id {
+                    peer.send(&response(id, Ok(json!({}))));
+                }
+                self.exit(0);
+            }
+            other => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method '{}'", other))),
+        };
+        if let Some(id) = id {
+            peer.send(&response(id, result));
+        }
+    }
+
+    /// Validate a transform request
+    fn register(&self, mut params: Map<String, Value>) -> Result<PendingJob, RpcError> {
+        let invalid = |e: String| RpcError::new(INVALID_PARAMS, e);
+        let files = match params.remove("files") {
+            None => None,
+            Some(Value::Array(items)) => Some(
+                items
+                    .into_iter()
+                    .map(|v| match v {
+                        Value::String(s) => Ok(s),
+                        other => Err(invalid(format!("files must be strings, got {}", other))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            Some(other) => return Err(invalid(format!("files must be an array, got {}", other))),
+        };
+        let matches = from_json(&transform::COMMAND, &params).map_err(invalid)?;
+        if let Some(opt) = ["metrics-addr", "metrics-textfile"].iter().find(|o| matches.has(o)) {
+            return Err(invalid(format!("{} is set for the whole server (serve --{})", opt, opt)));
+        }
+        if matches.value("files-from") == Some("-") {
+            // stdin may be this server's request channel
+            return Err(invalid("files-from cannot be \"-\" here; pass \"files\" instead".into()));
+        }
+        transform::check(&matches, files.is_some()).map_err(invalid)?;
+        let output = matches.required("output").map_err(invalid)?;
+        let output = output_key(Path::new(output)).map_err(|e| invalid(format!("output {}: {}", output, e)))?;
+        Ok(PendingJob { matches, files, output })
+    }
+
+    /// Add `job` to the job table with its thread, which waits for the
+    /// returned sender so the caller can answer first
+    fn start(self: &Arc<Self>, peer: &Peer, job: PendingJob) -> Result<(u64, mpsc::Sender<()>), RpcError> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if self.stopping.load(Ordering::Relaxed) {
+            return Err(RpcError::new(BUSY, "the server is shutting down"));
+        }
+        if let Some((other, _)) = jobs.iter().find(|(_, j)| j.state == JobState::Running && j.output == job.output) {
+            let message = format!("job {} is writing to {}", other, job.output.display());
+            return Err(RpcError::new(BUSY, message));
+        }
+        let id = self.next_job.fetch_add(1, Ordering::Relaxed);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (go, started) = mpsc::channel();
+        let output = job.output.clone();
+        let thread = self.spawn(id, job, Arc::clone(&cancel), peer.clone(), started);
+        jobs.insert(
+            id,
+            Job {
+                peer: peer.id,
+                output,
+                cancel,
+                state: JobState::Running,
+                files_total: 0,
+                files_done: 0,
+                records: 0,
+                progress: None,
+                exit_code: None,
+                errors: Vec::new(),
+                thread: Some(thread),
+            },
+        );
+        Ok((id, go))
+    }
+
+    fn spawn(
+        self: &Arc<Self>,
+        id: u64,
+        job: PendingJob,
+        cancel: Arc<AtomicBool>,
+        peer: Peer,
+        started: mpsc::Receiver<()>,
+    ) -> JoinHandle<()> {
+        let server = Arc::clone(self);
+        thread::spawn(move || {
+            // Dropped without a send only if answering failed; run anyway
+            let _ = started.recv();
+            let events = Arc::clone(&server);
+            let on_peer = peer.clone();
+            let integration = job.matches.value("integration-id").unwrap_or("").to_string();
+            let on_event = move |event: &Event| {
+                let value = event.to_value();
+                if let Some(ref x) = events.metrics {
+                    x.observe(&integration, id, event);
+                }
+                events.record(id, event, &value);
+                on_peer.notify("event", json!({ "job": id, "event": value }));
+            };
+            // A panic outside the file threads (which isolate their own)
+            // must still finish the job, or it blocks its output directory
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                transform::execute(&job.matches, job.files, cancel, on_event)
+            }))
+            .unwrap_or_else(|payload| Err(format!("panicked: {}", panic_message(&*payload))));
+            if let Some(ref x) = server.metrics {
+                let integration = job.matches.value("integration-id").unwrap_or("");
+                match outcome {
+                    Ok(ref outcome) => x.observe(integration, id, &outcome.complete),
+                    Err(_) => x.abandon(integration, id),
+                }
+                if let Err(e) = x.write() {
+                    eprintln!("ERROR: {}", e);
+                }
+            }
+            server.finish(id, &peer, outcome);
+        })
+    }
+
+    /// Keep what `status` reports up to date
+    fn record(&self, id: u64, event: &Event, value: &Value) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(job) = jobs.get_mut(&id) else { return };
+        match event {
+            Event::Start { files, .. } => job.files_total = *files,
+            Event::FileDone { records, .. } => {
+                job.files_done += 1;
+                job.records += records;
+            }
+            Event::FileRejected { .. } | Event::FileSkipped { .. } => job.files_done += 1,
+            Event::Progress { .. } => job.progress = Some(value.clone()),
+            _ => {}
+        }
+    }
+
+    fn finish(&self, id: u64, peer: &Peer, outcome: Result<transform::Outcome, String>) {
+        let (state, exit_code, errors) = match outcome {
+            Ok(outcome) => {
+                peer.notify("event", json!({ "job": id, "event": outcome.complete.to_value() }));
+                let state = match outcome.exit_code {
+                    0 => JobState::Done,
+                    transform::EXIT_CANCELLED => JobState::Cancelled,
+                    _ => JobState::Failed,
+                };
+                (state, outcome.exit_code, outcome.errors)
+            }
+            Err(e) => (JobState::Failed, 1, vec![e]),
+        };
+        {
+            let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(job) = jobs.get_mut(&id) {
+                job.state = state;
+                job.exit_code = Some(exit_code);
+                job.errors = errors.clone();
+            }
+            // Ids grow, so the first finished ones are the oldest
+            let finished: Vec<u64> = jobs.iter().filter(|(_, j)| j.state != JobState::Running).map(|(id, _)| *id).collect();
+            for id in &finished[..finished.len().saturating_sub(MAX_FINISHED)] {
+                jobs.remove(id);
+            }
+        }
+        peer.notify(
+            "job_done",
+            json!({ "job": id, "state": state.name(), "exit_code": exit_code, "errors": errors }),
+        );
+    }
+
+    fn cancel(&self, params: &Map<String, Value>) -> Result<Value, RpcError> {
+        let id = job_param(params)?.ok_or_else(|| RpcError::new(INVALID_PARAMS, "cancel requires \"job\""))?;
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let job = jobs.get(&id).ok_or_else(|| unknown_job(id))?;
+        job.cancel.store(true, Ordering::Relaxed);
+        Ok(job.status(id))
+    }
+
+    /// Report jobs, dropping the finished ones reported
+    fn status(&self, params: &Map<String, Value>) -> Result<Value, RpcError> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        match job_param(params)? {
+            Some(id) => {
+                let job = jobs.get(&id).ok_or_else(|| unknown_job(id))?;
+                let status = job.status(id);
+                if job.state != JobState::Running {
+                    jobs.remove(&id);
+                }
+                Ok(status)
+            }
+            None => {
+                let all: Vec<Value> = jobs.iter().map(|(id, job)| job.status(*id)).collect();
+                jobs.retain(|_, job| job.state == JobState::Running);
+                Ok(json!({ "jobs": all }))
+            }
+        }
+    }
+
+    /// A connection closed: nobody hears its jobs' events any more
+    fn cancel_peer(&self, peer: u64) {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        for job in jobs.values().filter(|j| j.peer == peer) {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Cancel every job and wait until each has sent its job_done
+    fn stop(&self) {
+        let threads: Vec<JoinHandle<()>> = {
+            let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+            self.stopping.store(true, Ordering::Relaxed);
+            jobs.values_mut()
+                .filter_map(|job| {
+                    job.cancel.store(true, Ordering::Relaxed);
+                    job.thread.take()
+                })
+                .collect()
+        };
+        for thread in threads {
+            let _ = thread.join();
+        }
+    }
+
+    fn exit(&self, code: i32) -> ! {
+        if let Some(ref path) = self.socket {
+            let _ = fs::remove_file(path);
+        }
+        process::exit(code)
+    }
+}
+
+fn job_param(params: &Map<String, Value>) -> Result<Option<u64>, RpcError> {
+    match params.get("job") {
+        None => Ok(None),
+        Some(v) => v
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("job must be a job id, got {}", v))),
+    }
+}
+
+fn unknown_job(id: u64) -> RpcError {
+    RpcError::new(UNKNOWN_JOB, format!("unknown job {}", id))
+}
+
+/// `output` as an absolute path, resolved through its deepest existing
+/// ancestor, so two spellings of one directory compare equal even before
+/// it is created
+fn output_key(output: &Path) -> io::Result<PathBuf> {
+    let output = std::path::absolute(output)?;
+    let mut missing = Vec::new();
+    for base in output.ancestors() {
+        if let Ok(mut key) = fs::canonicalize(base) {
+            for part in missing.iter().rev() {
+                match part {
+                    Component::ParentDir => {
+                        key.pop();
+                    }
+                    Component::Normal(name) => key.push(name),
+                    _ => {}
+                }
+            }
+            return Ok(key);
+        }
+        missing.extend(base.components().next_back());
+    }
+    Ok(output)
+}
+
+/// Bind `path`, replacing a stale socket file no server is listening on, and
+/// make it owner-only
+fn listen(path: &Path) -> io::Result<UnixListener> {
+    let listener = match UnixListener::bind(path) {
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "another server is listening there"));
+            }
+            fs::remove_file(path)?;
+            UnixListener::bind(path)?
+        }
+        result => result?,
+    };
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+fn run(m: &Matches) -> i32 {
+    if let Err(e) = m.positional_exact(0, "no positional arguments") {
+        eprintln!("ERROR: {}", e);
+        return 1;
+    }
+    let metrics = match transform::MetricsExport::start(m) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return 1;
+        }
+    };
+    let socket = m.value("socket").map(PathBuf::from);
+    let listener = match socket.as_deref().map(listen).transpose() {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("ERROR: --socket {}: {}", m.value("socket").unwrap_or_default(), e);
+            return 1;
+        }
+    };
+    let server = Arc::new(Server {
+        jobs: Mutex::new(BTreeMap::new()),
+        next_job: AtomicU64::new(1),
+        next_peer: AtomicU64::new(0),
+        stopping: AtomicBool::new(false),
+        socket,
+        metrics,
+    });
+
+    // First signal: shut down like the shutdown method. The conditional
+    // shutdown fires only once `signalled` is set, i.e. on the second one.
+    let signalled = Arc::new(AtomicBool::new(false));
+    let signals = [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM];
+    for signal in signals {
+        if let Err(e) = signal_hook::flag::register_conditional_shutdown(signal, transform::EXIT_CANCELLED, Arc::clone(&signalled)) {
+            eprintln!("ERROR: cannot install signal handler: {}", e);
+            return 1;
+        }
+    }
+    match signal_hook::iterator::Signals::new(signals) {
+        Ok(mut iter) => {
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                if iter.forever().next().is_some() {
+                    signalled.store(true, Ordering::Relaxed);
+                    server.stop();
+                    server.exit(transform::EXIT_CANCELLED);
+                }
+            });
+        }
+        Err(e) => {
+            eprintln!("ERROR: cannot install signal handler: {}", e);
+            return 1;
+        }
+    }
+
+    let Some(listener) = listener else {
+        let peer = server.peer(Box::new(io::stdout()));
+        server.serve_peer(io::stdin().lock(), &peer);
+        server.stop();
+        server.exit(0);
+    };
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("ERROR: accept failed: {}", e);
+                continue;
+            }
+        };
+        let reader = match stream.try_clone() {
+            Ok(r) => BufReader::new(r),
+            Err(e) => {
+                eprintln!("ERROR: accept failed: {}", e);
+                continue;
+            }
+        };
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            let peer = server.peer(Box::new(stream));
+            server.serve_peer(reader, &peer);
+            server.cancel_peer(peer.id);
+        });
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+
+    /// A peer's output, kept in memory
+    #[derive(Clone, Default)]
+    struct Lines(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Lines {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Lines {
+        /// Every message sent since the last call
+        fn take(&self) -> Vec<Value> {
+            let bytes = std::mem::take(&mut *self.0.lock().unwrap());
+            String::from_utf8(bytes)
+                .unwrap()
+                .lines()
+                .map(|l| serde_json::from_str(l).unwrap())
+                .collect()
+        }
+    }
+
+    fn server() -> Arc<Server> {
+        Arc::new(Server {
+            jobs: Mutex::new(BTreeMap::new()),
+            next_job: AtomicU64::new(1),
+            next_peer: AtomicU64::new(0),
+            stopping: AtomicBool::new(false),
+            socket: None,
+            metrics: None,
+        })
+    }
+
+    fn connect(server: &Server) -> (Peer, Lines) {
+        let lines = Lines::default();
+        (server.peer(Box::new(lines.clone())), lines)
+    }
+
+    /// A job that is not running a thread, as `status` and `cancel` see it
+    fn insert_job(server: &Server, peer: u64, output: &Path, state: JobState) -> (u64, Arc<AtomicBool>) {
+        let id = server.next_job.fetch_add(1, Ordering::Relaxed);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let job = Job {
+            peer,
+            output: output.to_path_buf(),
+            cancel: Arc::clone(&cancel),
+            state,
+            files_total: 0,
+            files_done: 0,
+            records: 0,
+            progress: None,
+            exit_code: None,
+            errors: Vec::new(),
+            thread: None,
+        };
+        server.jobs.lock().unwrap().insert(id, job);
+        (id, cancel)
+    }
+
+    fn error_code(response: &Value) -> i64 {
+        response["error"]["code"].as_i64().unwrap_or_else(|| panic!("not an error: {}", response))
+    }
+
+    #[test]
+    fn malformed_requests_get_json_rpc_errors() {
+        let server = server();
+        let (peer, lines) = connect(&server);
+        let cases = [
+            ("{not json", PARSE_ERROR, Value::Null),
+            ("[1, 2]", INVALID_REQUEST, Value::Null),
+            (r#"{"id":1,"method":"status"}"#, INVALID_REQUEST, json!(1)),
+            (r#"{"jsonrpc":"1.0","id":2,"method":"status"}"#, INVALID_REQUEST, json!(2)),
+            (r#"{"jsonrpc":"2.0","id":3,"method":"status","params":[1]}"#, INVALID_PARAMS, json!(3)),
+            (r#"{"jsonrpc":"2.0","id":"a","method":"frobnicate"}"#, METHOD_NOT_FOUND, json!("a")),
+            (r#"{"jsonrpc":"2.0","id":5,"method":"status","params":{"job":"x"}}"#, INVALID_PARAMS, json!(5)),
+            (r#"{"jsonrpc":"2.0","id":6,"method":"status","params":{"job":99}}"#, UNKNOWN_JOB, json!(6)),
+            (r#"{"jsonrpc":"2.0","id":7,"method":"cancel"}"#, INVALID_PARAMS, json!(7)),
+            (r#"{"jsonrpc":"2.0","id":8,"method":"cancel","params":{"job":99}}"#, UNKNOWN_JOB, json!(8)),
+            (r#"{"jsonrpc":"2.0","id":9,"method":"transform","params":{"bogus":1}}"#, INVALID_PARAMS, json!(9)),
+            (r#"{"jsonrpc":"2.0","id":10,"method":"transform","params":{"files":"a.csv"}}"#, INVALID_PARAMS, json!(10)),
+        ];
+        for (line, code, id) in cases {
+            server.handle_line(&peer, line);
+            let responses = lines.take();
+            assert_eq!(responses.len(), 1, "{}", line);
+            assert_eq!(error_code(&responses[0]), code, "{}", line);
+            assert_eq!(responses[0]["id"], id, "{}", line);
+            assert_eq!(responses[0]["jsonrpc"], "2.0");
+        }
+    }
+
+    #[test]
+    fn notifications_get_no_response() {
+        let server = server();
+        let (peer, lines) = connect(&server);
+        let (job, cancel) = insert_job(&server, peer.id, Path::new("/out"), JobState::Running);
+        for line in [
+            r#"{"jsonrpc":"2.0","method":"status"}"#.to_string(),
+            r#"{"jsonrpc":"2.0","method":"frobnicate"}"#.to_string(),
+            r#"{"jsonrpc":"2.0","method":"status","params":[1]}"#.to_string(),
+            format!(r#"{{"jsonrpc":"2.0","method":"cancel","params":{{"job":{}}}}}"#, job),
+        ] {
+            server.handle_line(&peer, &line);
+            assert!(lines.take().is_empty(), "{}", line);
+        }
+        // The notification still acted
+        assert!(cancel.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn second_job_on_an_output_dir_is_busy() {
+        let dir = scratch("serve-busy");
+        let server = server();
+        let (peer, lines) = connect(&server);
+        let output = dir.join("out");
+        insert_job(&server, 7, &output_key(&output).unwrap(), JobState::Running);
+        let request = |output: &Path| {
+            let params = json!({
+                "input": dir.join("in"),
+                "output": output,
+                "integration-id": "507f1f77bcf86cd799439011",
+                "es-index": "parts",
+            });
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "transform", "params": params }).to_string()
+        };
+
+        // Another spelling of the same directory
+        server.handle_line(&peer, &request(&dir.join("in/../out")));
+        let responses = lines.take();
+        assert_eq!(error_code(&responses[0]), BUSY);
+        assert!(responses[0]["error"]["message"].as_str().unwrap().starts_with("job 1 is writing to"));
+
+        // Once that job is over, the directory is free again
+        server.jobs.lock().unwrap().get_mut(&1).unwrap().state = JobState::Done;
+        server.stopping.store(true, Ordering::Relaxed);
+        server.handle_line(&peer, &request(&output));
+        let responses = lines.take();
+        assert_eq!(responses[0]["error"]["message"], "the server is shutting down");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn transform_job_reports_events_and_job_done() {
+        let dir = scratch("serve-run");
+        fs::create_dir_all(dir.join("in")).unwrap();
+        fs::write(dir.join("in/prices_AB4_part1.csv"), "part_number;price\nP1;1,5\nP2;2\n").unwrap();
+        let server = server();
+        let (peer, lines) = connect(&server);
+        let params = json!({
+            "input": dir.join("in"),
+            "output": dir.join("out"),
+            "integration-id": "507f1f77bcf86cd799439011",
+            "es-index": "parts",
+            "files": ["prices_AB4_part1.csv"],
+        });
+        server.handle_line(&peer, &json!({ "jsonrpc": "2.0", "id": 1, "method": "transform", "params": params }).to_string());
+        let thread = server.jobs.lock().unwrap().get_mut(&1).unwrap().thread.take().unwrap();
+        thread.join().unwrap();
+
+        let messages = lines.take();
+        assert_eq!(messages[0], json!({ "jsonrpc": "2.0", "id": 1, "result": { "job": 1 } }));
+        let events: Vec<&str> = messages[1..messages.len() - 1]
+            .iter()
+            .map(|m| {
+                assert_eq!((&m["method"], &m["params"]["job"]), (&json!("event"), &json!(1)));
+                m["params"]["event"]["event"].as_str().unwrap()
+            })
+            .collect();
+        assert_eq!(events.first(), Some(&"start"));
+        assert!(events.contains(&"file_done"));
+        assert_eq!(events.last(), Some(&"complete"));
+        let done = messages.last().unwrap();
+        assert_eq!(done["method"], "job_done");
+        assert_eq!(done["params"], json!({ "job": 1, "state": "done", "exit_code": 0, "errors": [] }));
+        assert!(dir.join("out/prices_AB4_part1.ndjson").exists());
+
+        // status reports it once, with what the events said, then forgets it
+        server.handle_line(&peer, r#"{"jsonrpc":"2.0","id":2,"method":"status","params":{"job":1}}"#);
+        let status = &lines.take()[0]["result"];
+        assert_eq!((&status["state"], &status["files_done"], &status["records"]), (&json!("done"), &json!(1), &json!(2)));
+        server.handle_line(&peer, r#"{"jsonrpc":"2.0","id":3,"method":"status","params":{"job":1}}"#);
+        assert_eq!(error_code(&lines.take()[0]), UNKNOWN_JOB);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn status_drops_finished_jobs_once_reported() {
+        let server = server();
+        let (peer, lines) = connect(&server);
+        let (running, _) = insert_job(&server, peer.id, Path::new("/a"), JobState::Running);
+        let (done, _) = insert_job(&server, peer.id, Path::new("/b"), JobState::Done);
+        let (failed, _) = insert_job(&server, peer.id, Path::new("/c"), JobState::Failed);
+
+        server.handle_line(&peer, r#"{"jsonrpc":"2.0","id":1,"method":"status"}"#);
+        let all = &lines.take()[0]["result"]["jobs"];
+        let states: Vec<(u64, &str)> = all
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|j| (j["job"].as_u64().unwrap(), j["state"].as_str().unwrap()))
+            .collect();
+        assert_eq!(states, [(running, "running"), (done, "done"), (failed, "failed")]);
+
+        let ids: Vec<u64> = server.jobs.lock().unwrap().keys().copied().collect();
+        assert_eq!(ids, [running]);
+        // A running job is reported as often as asked
+        for _ in 0..2 {
+            server.handle_line(&peer, &format!(r#"{{"jsonrpc":"2.0","id":2,"method":"status","params":{{"job":{}}}}}"#, running));
+            assert_eq!(lines.take()[0]["result"]["state"], "running");
+        }
+    }
+
+    #[test]
+    fn unread_finished_jobs_are_capped() {
+        let server = server();
+        let (peer, lines) = connect(&server);
+        let (running, _) = insert_job(&server, peer.id, Path::new("/run"), JobState::Running);
+        for n in 0..MAX_FINISHED + 2 {
+            let (id, _) = insert_job(&server, peer.id, Path::new(&format!("/out{}", n)), JobState::Running);
+            server.finish(id, &peer, Err("could not start".into()));
+        }
+        let jobs = server.jobs.lock().unwrap();
+        assert_eq!(jobs.len(), MAX_FINISHED + 1);
+        assert!(jobs.contains_key(&running));
+        // The two oldest finished jobs went
+        assert!(!jobs.contains_key(&(running + 1)) && !jobs.contains_key(&(running + 2)));
+        let failed = &jobs[&(running + 3)];
+        assert!(failed.state == JobState::Failed && failed.errors == ["could not start"]);
+        let done = lines.take();
+        assert_eq!(done.len(), MAX_FINISHED + 2);
+        assert_eq!(done[0]["params"]["state"], "failed");
+    }
+
+    #[test]
+    fn closing_a_connection_cancels_only_its_jobs() {
+        let server = server();
+        let (peer, _) = connect(&server);
+        let (other, _) = connect(&server);
+        let (_, mine) = insert_job(&server, peer.id, Path::new("/a"), JobState::Running);
+        let (_, theirs) = insert_job(&server, other.id, Path::new("/b"), JobState::Running);
+
+        server.serve_peer(io::Cursor::new(b"\n   \n".to_vec()), &peer);
+        server.cancel_peer(peer.id);
+        assert!(mine.load(Ordering::Relaxed));
+        assert!(!theirs.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn output_key_matches_spellings_of_a_missing_directory() {
+        let dir = scratch("serve-output-key");
+        let name = dir.file_name().unwrap();
+        let plain = output_key(&dir.join("out")).unwrap();
+
+        assert_eq!(output_key(&dir.join("../").join(name).join("out")).unwrap(), plain);
+        assert_eq!(output_key(&dir.join("new/../out")).unwrap(), plain);
+        assert_eq!(output_key(&dir.join("./out/")).unwrap(), plain);
+        assert_ne!(output_key(&dir.join("out2")).unwrap(), plain);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
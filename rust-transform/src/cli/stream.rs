@@ -0,0 +1,98 @@
+// =============================================================================
+// `turbo-transform stream` — one CSV on stdin → NDJSON, BSON or _bulk on stdout
+// =============================================================================
+//   curl -s ftp://…/prices_AB4_part1.csv \
+//     | turbo-transform stream --file-name prices_AB4_part1.csv \
+//         --integration-id 507f… \
+//     | mongoimport --db partsform --collection parts
+//
+// stdout carries only data; the stream_done summary and errors go to stderr.
+// A CSV without records is not an error: stream_done reports records: 0.
+// =============================================================================
+
+use super::{transform, Command, Matches, Opt};
+use std::io;
+use std::time::Instant;
+use turbo_transform::stream::{self, StreamConfig, StreamFormat};
+use turbo_transform::{Event, MappingConfig};
+
+pub const COMMAND: Command = Command {
+    name: "stream",
+    args: "",
+    about: "Transform one CSV from stdin into NDJSON, BSON or an ES _bulk body on stdout",
+    opts: &[
+        Opt { name: "file-name", value: Some("name"), help: "Name of the streamed file, stamped as fileName (required)" },
+        Opt { name: "stock-code", value: Some("code"), help: "Stock code for rows without one (default: from the file name, …_<CODE>_partN.csv)" },
+        Opt { name: "integration-id", value: Some("id"), help: "MongoDB ObjectId stamped on every record (required)" },
+        Opt { name: "integration-name", value: Some("name"), help: "Human-readable integration name" },
+        Opt { name: "format", value: Some("fmt"), help: "ndjson (default), bson, or bulk" },
+        Opt { name: "es-index", value: Some("name"), help: "Index for the _bulk action lines (required with --format bulk)" },
+        Opt { name: "es-op", value: Some("op"), help: "index (default), create, or update (doc_as_upsert)" },
+        Opt { name: "es-id-fields", value: Some("a,b,.."), help: "Build _id from these fields joined by ':'" },
+        Opt { name: "es-routing", value: Some("field"), help: "Route each document by this field, e.g. brand" },
+        Opt { name: "es-pipeline", value: Some("name"), help: "Ingest pipeline for index/create" },
+        Opt { name: "es-version-external", value: None, help: "version = import timestamp (ms), version_type = external" },
+    ],
+    run,
+};
+
+/// Options that only shape _bulk action lines
+const BULK_OPTS: [&str; 6] = ["es-index", "es-op", "es-id-fields", "es-routing", "es-pipeline", "es-version-external"];
+
+fn config(m: &Matches) -> Result<StreamConfig, String> {
+    m.positional_exact(0, "no positional arguments (the CSV is read from stdin)")?;
+    let format = match m.value("format").unwrap_or("ndjson") {
+        "ndjson" => StreamFormat::Ndjson,
+        "bson" => StreamFormat::Bson,
+        "bulk" => StreamFormat::Bulk {
+            es_index: m.required("es-index")?.to_string(),
+            action: transform::bulk_action(m)?,
+        },
+        other => return Err(format!("unknown --format '{}' (expected ndjson, bson or bulk)", other)),
+    };
+    if !matches!(format, StreamFormat::Bulk { .. }) {
+        if let Some(opt) = BULK_OPTS.iter().find(|o| m.has(o)) {
+            return Err(format!("--{} requires --format bulk", opt));
+        }
+    }
+    Ok(StreamConfig {
+        mapping: MappingConfig::new(m.required("integration-id")?, m.value("integration-name").unwrap_or("")),
+        file_name: m.required("file-name")?.to_string(),
+        stock_code: m.value("stock-code").map(str::to_string),
+        format,
+    })
+}
+
+fn run(m: &Matches) -> i32 {
+    let config = match config(m) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return 1;
+        }
+    };
+
+    let start = Instant::now();
+    let summary = match stream::run(&config, io::stdin().lock(), io::stdout().lock()) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("ERROR: {}: {}", config.file_name, e);
+            return 1;
+        }
+    };
+
+    let elapsed = start.elapsed();
+    let secs = elapsed.as_secs_f64();
+    let done = Event::StreamDone {
+        file: config.file_name,
+        format: config.format.name().to_string(),
+        records: summary.records,
+        rows: summary.rows.rows,
+        rejected: summary.rows.rejected(),
+        bytes_written: summary.bytes_written,
+        duration_ms: elapsed.as_millis() as u64,
+        rate_per_sec: if secs > 0.0 { (summary.records as f64 / secs) as u64 } else { summary.records },
+    };
+    eprintln!("{}", done.to_json());
+    0
+}
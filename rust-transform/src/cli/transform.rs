@@ -0,0 +1,627 @@
+// =============================================================================
+// `turbo-transform transform` — CSV directory → NDJSON/BSON + ES _bulk
+// =============================================================================
+// SIGINT/SIGTERM cancel the run gracefully: no new files are started, files
+// being read are rolled back and the process exits with EXIT_CANCELLED. A
+// second signal exits immediately. With --resume or --checkpoint, completed
+// files are recorded in the checkpoint, and a --resume run skips them.
+// =============================================================================
+
+use super::{validate, Command, Matches, Opt};
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use turbo_transform::best_offer::{BestOfferConfig, OfferRank};
+use turbo_transform::bulk_action::{BulkActionConfig, BulkOp, DocField};
+use turbo_transform::checkpoint;
+use turbo_transform::discover::{self, Discovery, Glob, SymlinkPolicy};
+use turbo_transform::es_sink::{self, EsSinkConfig};
+use turbo_transform::metrics::Metrics;
+use turbo_transform::quality::QualityThresholds;
+use turbo_transform::timestamp::epoch_millis_now;
+use turbo_transform::{BulkLimits, Event, MappingConfig, Transformer};
+
+pub const COMMAND: Command = Command {
+    name: "transform",
+    args: "",
+    about: "Transform every CSV in a directory into NDJSON (or BSON) + ES _bulk bodies",
+    opts: &[
+        Opt { name: "input", value: Some("dir"), help: "Directory containing CSV files (required)" },
+        Opt { name: "recursive", value: None, help: "Also take CSVs from subdirectories; outputs mirror the relative paths" },
+        Opt { name: "include", value: Some("glob,.."), help: "Take only files matching these globs (default *.csv)" },
+        Opt { name: "exclude", value: Some("glob,.."), help: "Skip files matching these globs, e.g. *_test.csv" },
+        Opt { name: "symlinks", value: Some("policy"), help: "skip | files (default, follow links to files) | follow (into directories too)" },
+        Opt { name: "files-from", value: Some("file"), help: "Process the files listed in <file> (- = stdin), one per line, relative to --input" },
+        Opt { name: "output", value: Some("dir"), help: "Directory for .ndjson/.bson + .bulk files (required)" },
+        Opt { name: "integration-id", value: Some("id"), help: "MongoDB ObjectId stamped on every record (required)" },
+        Opt { name: "integration-name", value: Some("name"), help: "Human-readable integration name" },
+        Opt { name: "es-index", value: Some("name"), help: "Elasticsearch index for the _bulk action lines (required)" },
+        Opt { name: "es-url", value: Some("url"), help: "Stream _bulk bodies to this ES/OpenSearch node instead of writing .bulk files" },
+        Opt { name: "es-batch-bytes", value: Some("n"), help: "Max bytes per _bulk request (needs --es-url, default 10MiB)" },
+        Opt { name: "es-concurrency", value: Some("n"), help: "Max in-flight _bulk requests (needs --es-url, default 8)" },
+        Opt { name: "es-max-retries", value: Some("n"), help: "Retries on 429/5xx before dead-lettering (needs --es-url, default 8)" },
+        Opt { name: "es-dead-letter-dir", value: Some("dir"), help: "Where <stem>.deadletter.ndjson files go (needs --es-url, default --output)" },
+        Opt { name: "bson", value: None, help: "Write mongorestore-ready <stem>.bson + <stem>.metadata.json instead of .ndjson" },
+        Opt { name: "best-offer", value: Some("rank"), help: "cheapest|fastest — also write parts_best_offer.ndjson with one offer per group" },
+        Opt { name: "best-offer-key", value: Some("a,b,.."), help: "Grouping fields for --best-offer (default partNumber,brand)" },
+        Opt { name: "best-offer-max-groups", value: Some("n"), help: "Give up parts_best_offer.ndjson past n groups (~0.5 KB of memory each; default no limit)" },
+        Opt { name: "parquet-dir", value: Some("dir"), help: "Also write Parquet to <dir>/integration=<id>/stockCode=<code>/<stem>.parquet" },
+        Opt { name: "bulk-max-bytes", value: Some("n"), help: "Roll .bulk output into .bulk.000, .bulk.001, ... chunks of at most n bytes" },
+        Opt { name: "bulk-max-docs", value: Some("n"), help: "Roll .bulk output into chunks of at most n documents" },
+        Opt { name: "es-op", value: Some("op"), help: "index (default), create, or update (doc_as_upsert)" },
+        Opt { name: "es-id-fields", value: Some("a,b,.."), help: "Build _id from these fields joined by ':'" },
+        Opt { name: "es-routing", value: Some("field"), help: "Route each document by this field, e.g. brand" },
+        Opt { name: "es-pipeline", value: Some("name"), help: "Ingest pipeline for index/create" },
+        Opt { name: "es-version-external", value: None, help: "version = import timestamp (ms), version_type = external" },
+        Opt { name: "progress-secs", value: Some("n"), help: "Seconds between progress heartbeat events, 0 = off (default 5)" },
+        Opt { name: "metrics-addr", value: Some("host:port"), help: "Serve Prometheus metrics at http://<host:port>/metrics during the run" },
+        Opt { name: "metrics-textfile", value: Some("file.prom"), help: "Write Prometheus metrics to this node_exporter textfile at the end" },
+        Opt { name: "profile", value: None, help: "Write a per-file, per-field data profile to <output>/<es-index>.profile.json" },
+        Opt { name: "checkpoint", value: Some("file"), help: "Record completed files in <file> (with --resume, default <output>/turbo-transform.checkpoint.json)" },
+        Opt { name: "resume", value: None, help: "Record completed files, skipping those the checkpoint lists as complete and unchanged" },
+        Opt { name: "strict", value: None, help: "Reject files that fail the quality thresholds below; exit 1 if any does" },
+        Opt { name: "max-reject-rate", value: Some("r"), help: "With --strict: max share of rows that produce no record (default 0.10)" },
+        Opt { name: "max-zero-price-rate", value: Some("r"), help: "With --strict: max share of records with price 0 (default 0.10)" },
+        Opt { name: "max-empty-brand-rate", value: Some("r"), help: "With --strict: max share of records without a brand (default 0.25)" },
+        Opt { name: "max-duplicate-rate", value: Some("r"), help: "With --strict: max share of repeated partNumber+brand+stockCode (default 0.05)" },
+        Opt { name: "min-records", value: Some("n"), help: "With --strict: min records per file (default 1)" },
+    ],
+    run,
+};
+
+const DEFAULT_PROGRESS_SECS: u64 = 5;
+
+/// Exit code of a run stopped by SIGINT/SIGTERM (128 + SIGINT, as shells report it)
+pub const EXIT_CANCELLED: i32 = 130;
+
+/// Validated transform settings
+struct Settings {
+    input_dir: PathBuf,
+    discovery: Discovery,
+    files_from: Option<String>,
+    output_dir: PathBuf,
+    integration_id: String,
+    integration_name: String,
+    es_index: String,
+    es_url: Option<String>,
+    es_batch_bytes: usize,
+    es_concurrency: usize,
+    es_max_retries: u32,
+    es_dead_letter_dir: Option<PathBuf>,
+    parquet_dir: Option<PathBuf>,
+    bson: bool,
+    best_offer: Option<BestOfferConfig>,
+    bulk_limits: BulkLimits,
+    bulk_action: BulkActionConfig,
+    strict: Option<QualityThresholds>,
+    profile: bool,
+    progress_interval: Duration,
+    checkpoint: Option<PathBuf>,
+    resume: bool,
+}
+
+/// Action-line settings from --es-op, --es-id-fields, --es-routing,
+/// --es-pipeline and --es-version-external (shared with `stream`)
+pub fn bulk_action(m: &Matches) -> Result<BulkActionConfig, String> {
+    let mut bulk_action = BulkActionConfig {
+        version_external: m.flag("es-version-external"),
+        ..BulkActionConfig::default()
+    };
+    if let Some(op) = m.value("es-op") {
+        bulk_action.op = BulkOp::parse(op)?;
+    }
+    if let Some(fields) = m.value("es-id-fields") {
+        bulk_action.id_fields = DocField::parse_list(fields)?;
+    }
+    if let Some(field) = m.value("es-routing") {
+        bulk_action.routing = Some(DocField::parse(field)?);
+    }
+    bulk_action.pipeline = m.value("es-pipeline").map(str::to_string);
+    bulk_action.validate()?;
+    Ok(bulk_action)
+}
+
+fn settings(m: &Matches) -> Result<Settings, String> {
+    m.positional_exact(0, "no positional arguments")?;
+
+    let es_url = m.value("es-url").map(str::to_string);
+    if es_url.is_none() {
+        for opt in ["es-batch-bytes", "es-concurrency", "es-max-retries", "es-dead-letter-dir"] {
+            if m.has(opt) {
+                return Err(format!("--{} requires --es-url", opt));
+            }
+        }
+    }
+
+    let bulk_limits = BulkLimits {
+        max_bytes: m.parse("bulk-max-bytes")?.unwrap_or(0),
+        max_docs: m.parse("bulk-max-docs")?.unwrap_or(0),
+    };
+    if es_url.is_some() && bulk_limits.is_chunked() {
+        return Err("--bulk-max-bytes/--bulk-max-docs apply to .bulk files and cannot be combined with --es-url".into());
+    }
+
+    let bulk_action = bulk_action(m)?;
+
+    let best_offer = match m.value("best-offer") {
+        Some(rank) => Some(BestOfferConfig {
+            rank: OfferRank::parse(rank)?,
+            key_fields: match m.value("best-offer-key") {
+                Some(key) => DocField::parse_list(key)?,
+                None => Vec::new(),
+            },
+            max_groups: m.parse("best-offer-max-groups")?.unwrap_or(0),
+        }),
+        None if m.has("best-offer-key") => return Err("--best-offer-key requires --best-offer".into()),
+        None if m.has("best-offer-max-groups") => return Err("--best-offer-max-groups requires --best-offer".into()),
+        None => None,
+    };
+
+    let strict = if m.flag("strict") {
+        if es_url.is_some() || bulk_limits.is_chunked() {
+            return Err("--strict cannot be combined with --es-url or --bulk-max-bytes/--bulk-max-docs (rejected files must be deletable)".into());
+        }
+        Some(validate::thresholds(m)?)
+    } else {
+        if let Some(opt) = validate::THRESHOLD_OPTS.iter().find(|o| m.has(o)) {
+            return Err(format!("--{} requires --strict", opt));
+        }
+        None
+    };
+
+    let files_from = m.value("files-from").map(str::to_string);
+    if files_from.is_some() {
+        if let Some(opt) = ["recursive", "include", "exclude", "symlinks"].iter().find(|o| m.has(o)) {
+            return Err(format!("--{} cannot be combined with --files-from (the list is taken as is)", opt));
+        }
+    }
+    let discovery = Discovery {
+        recursive: m.flag("recursive"),
+        include: m.value("include").map(Glob::parse_list).transpose()?.unwrap_or_default(),
+        exclude: m.value("exclude").map(Glob::parse_list).transpose()?.unwrap_or_default(),
+        symlinks: m.value("symlinks").map(SymlinkPolicy::parse).transpose()?.unwrap_or_default(),
+    };
+
+    Ok(Settings {
+        input_dir: PathBuf::from(m.required("input")?),
+        discovery,
+        files_from,
+        output_dir: PathBuf::from(m.required("output")?),
+        integration_id: m.required("integration-id")?.to_string(),
+        integration_name: m.value("integration-name").unwrap_or("").to_string(),
+        es_index: m.required("es-index")?.to_string(),
+        es_url,
+        es_batch_bytes: m.parse("es-batch-bytes")?.unwrap_or(es_sink::DEFAULT_BATCH_BYTES),
+        es_concurrency: m.parse("es-concurrency")?.unwrap_or(es_sink::DEFAULT_CONCURRENCY),
+        es_max_retries: m.parse("es-max-retries")?.unwrap_or(es_sink::DEFAULT_MAX_RETRIES),
+        es_dead_letter_dir: m.value("es-dead-letter-dir").map(PathBuf::from),
+        parquet_dir: m.value("parquet-dir").map(PathBuf::from),
+        bson: m.flag("bson"),
+        best_offer,
+        bulk_limits,
+        bulk_action,
+        strict,
+        profile: m.flag("profile"),
+        progress_interval: Duration::from_secs(m.parse("progress-secs")?.unwrap_or(DEFAULT_PROGRESS_SECS)),
+        checkpoint: m.value("checkpoint").map(PathBuf::from),
+        resume: m.flag("resume"),
+    })
+}
+
+/// Contents of a --files-from list; "-" is stdin
+fn read_list(list: &str) -> Result<String, String> {
+    if list == "-" {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| format!("--files-from -: {}", e))?;
+        Ok(text)
+    } else {
+        fs::read_to_string(list).map_err(|e| format!("--files-from {}: {}", list, e))
+    }
+}
+
+/// --metrics-addr and --metrics-textfile (shared with `watch` and `serve`)
+pub struct MetricsExport {
+    metrics: Arc<Metrics>,
+    textfile: Option<PathBuf>,
+}
+
+impl MetricsExport {
+    /// None when neither option is given; otherwise /metrics is served from now on
+    pub fn start(m: &Matches) -> Result<Option<MetricsExport>, String> {
+        let textfile = m.value("metrics-textfile").map(PathBuf::from);
+        if let Some(ref path) = textfile {
+            if path.extension().and_then(|e| e.to_str()) != Some("prom") {
+                return Err(format!("--metrics-textfile {}: node_exporter only reads *.prom files", path.display()));
+            }
+        }
+        let metrics = Arc::new(Metrics::new());
+        if let Some(addr) = m.value("metrics-addr") {
+            metrics.serve(addr).map_err(|e| format!("--metrics-addr: {}", e))?;
+        } else if textfile.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(MetricsExport { metrics, textfile }))
+    }
+
+    /// `run` as for Metrics::observe
+    pub fn observe(&self, integration: &str, run: u64, event: &Event) {
+        self.metrics.observe(integration, run, event);
+    }
+
+    /// A run that ended without a Complete event
+    pub fn abandon(&self, integration: &str, run: u64) {
+        self.metrics.abandon(integration, run);
+    }
+
+    /// Rewrite the textfile, if there is one
+    pub fn write(&self) -> Result<(), String> {
+        match self.textfile {
+            Some(ref path) => self.metrics.write_textfile(path),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A flag the first SIGINT/SIGTERM sets; the second one exits immediately
+/// with EXIT_CANCELLED (shared with `watch`)
+pub fn cancel_on_signals() -> Result<Arc<AtomicBool>, String> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        // The conditional shutdown goes first: it only fires once the flag
+        // is already set, i.e. on the second signal
+        signal_hook::flag::register_conditional_shutdown(signal, EXIT_CANCELLED, Arc::clone(&cancel))
+            .and_then(|_| signal_hook::flag::register(signal, Arc::clone(&cancel)))
+            .map_err(|e| format!("cannot install signal handler: {}", e))?;
+    }
+    Ok(cancel)
+}
+
+fn run(m: &Matches) -> i32 {
+    let cancel = match cancel_on_signals() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return 1;
+        }
+    };
+
+    let export = match MetricsExport::start(m) {
+        Ok(x) => x.map(Arc::new),
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return 1;
+        }
+    };
+    let integration = m.value("integration-id").unwrap_or("").to_string();
+    let on_event = {
+        let (export, integration) = (export.clone(), integration.clone());
+        move |event: &Event| {
+            if let Some(ref x) = export {
+                x.observe(&integration, 0, event);
+            }
+            eprintln!("{}", event.to_json());
+        }
+    };
+
+    match execute(m, None, cancel, on_event) {
+        Ok(outcome) => {
+            // Final summary on stdout — machine-readable JSON
+            println!("{}", outcome.complete.to_json());
+            for e in &outcome.errors {
+                eprintln!("ERROR: {}", e);
+            }
+            if let Some(ref x) = export {
+                x.observe(&integration, 0, &outcome.complete);
+                if let Err(e) = x.write() {
+                    eprintln!("ERROR: {}", e);
+                }
+            }
+            outcome.exit_code
+        }
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            1
+        }
+    }
+}
+
+/// Validate the options in `m` without running anything; `explicit_files`
+/// as for `execute`
+pub fn check(m: &Matches, explicit_files: bool) -> Result<(), String> {
+    checked_settings(m, explicit_files).map(|_| ())
+}
+
+fn checked_settings(m: &Matches, explicit_files: bool) -> Result<Settings, String> {
+    let cli = settings(m)?;
+    if explicit_files && (cli.files_from.is_some() || ["recursive", "include", "exclude", "symlinks"].iter().any(|o| m.has(o))) {
+        return Err("an explicit file list cannot be combined with --files-from or discovery options".into());
+    }
+    Ok(cli)
+}
+
+/// What a finished run reports
+pub struct Outcome {
+    /// The `complete` event
+    pub complete: Event,
+    pub errors: Vec<String>,
+    pub exit_code: i32,
+}
+
+/// Run a transform with the options in `m`. Events go to `on_event` (the
+/// `complete` one is returned instead); setting `cancel` stops the run
+/// gracefully. `files` replaces discovery, relative to --input. Err means
+/// the run could not start.
+pub fn execute(
+    m: &Matches,
+    files: Option<Vec<String>>,
+    cancel: Arc<AtomicBool>,
+    on_event: impl Fn(&Event) + Send + Sync + 'static,
+) -> Result<Outcome, String> {
+    let cli = checked_settings(m, files.is_some())?;
+    let input_dir = cli.input_dir;
+    let output_dir = cli.output_dir;
+
+    // Validate input directory
+    if !input_dir.is_dir() {
+        return Err(format!("input directory does not exist: {}", input_dir.display()));
+    }
+
+    // Create output directory
+    fs::create_dir_all(&output_dir).map_err(|e| format!("cannot create output directory: {}", e))?;
+
+    // Enumerate CSV files — from a list, or by walking the input directory
+    let mut csv_files = match (files, cli.files_from) {
+        (Some(files), _) => discover::resolve_files(&files, &input_dir)?,
+        (None, Some(ref list)) => discover::read_file_list(&read_list(list)?, &input_dir)
+            .map_err(|e| format!("--files-from {}: {}", list, e))?,
+        (None, None) => cli.discovery.find(&input_dir)?,
+    };
+
+    if csv_files.is_empty() {
+        return Err(format!("no CSV files found in {}", input_dir.display()));
+    }
+
+    // Sort for deterministic processing order (largest files first for better load balancing)
+    csv_files.sort_by(|a, b| {
+        let size_a = fs::metadata(a).map(|m| m.len()).unwrap_or(0);
+        let size_b = fs::metadata(b).map(|m| m.len()).unwrap_or(0);
+        size_b.cmp(&size_a) // Descending — largest first
+    });
+
+    let total_files = csv_files.len();
+    let total_input_bytes: u64 = csv_files
+        .iter()
+        .map(|p| fs::metadata(p).map(|m| m.len()).unwrap_or(0))
+        .sum();
+
+    // Timestamp for all records in this batch (also the external ES version)
+    let mapping = MappingConfig {
+        integration_id: cli.integration_id,
+        integration_name: cli.integration_name,
+        imported_at_millis: epoch_millis_now(),
+    };
+
+    let mut builder = Transformer::builder(mapping, &output_dir)
+        .input_root(&input_dir)
+        .es_index(&cli.es_index)
+        .bulk_action(cli.bulk_action)
+        .bulk_limits(cli.bulk_limits)
+        .bson(cli.bson)
+        .progress_interval(cli.progress_interval)
+        .on_event(on_event);
+    // Optional direct-to-ES sink (credentials from the same env vars Node uses)
+    if let Some(url) = cli.es_url {
+        builder = builder.es_sink(EsSinkConfig {
+            url,
+            max_batch_bytes: cli.es_batch_bytes,
+            concurrency: cli.es_concurrency,
+            max_retries: cli.es_max_retries,
+            dead_letter_dir: cli.es_dead_letter_dir.unwrap_or_else(|| output_dir.clone()),
+            username: env::var("ELASTICSEARCH_USERNAME").ok(),
+            password: env::var("ELASTICSEARCH_PASSWORD").ok(),
+        });
+    }
+    if let Some(dir) = cli.parquet_dir {
+        builder = builder.parquet_dir(dir);
+    }
+    if let Some(cfg) = cli.best_offer {
+        builder = builder.best_offer(cfg);
+    }
+    if cli.profile {
+        builder = builder.profile(&cli.es_index);
+    }
+    let strict = cli.strict.is_some();
+    if let Some(thresholds) = cli.strict {
+        builder = builder.strict(thresholds);
+    }
+    // Only runs that ask for one leave a checkpoint in the output directory
+    if cli.resume || cli.checkpoint.is_some() {
+        let path = cli.checkpoint.unwrap_or_else(|| output_dir.join(checkpoint::DEFAULT_NAME));
+        builder = builder.checkpoint(path, cli.resume);
+    }
+    let transformer = builder.cancel_flag(cancel).build()?;
+
+    let num_threads = rayon::current_num_threads();
+
+    let start = Event::Start {
+        files: total_files,
+        total_bytes: total_input_bytes,
+        threads: num_threads,
+        input_dir: input_dir.display().to_string(),
+        output_dir: output_dir.display().to_string(),
+    };
+    transformer.emit(start);
+
+    let overall_start = Instant::now();
+    let summary = transformer.run(&csv_files);
+    let results = summary.files;
+
+    let overall_duration = overall_start.elapsed();
+
+    // Aggregate results
+    let mut total_records: u64 = 0;
+    let mut total_ndjson_bytes: u64 = 0;
+    let mut total_bson_bytes: u64 = 0;
+    let mut total_bulk_bytes: u64 = 0;
+    let mut total_es_indexed: u64 = 0;
+    let mut total_es_failed: u64 = 0;
+    let mut total_es_unconfirmed: u64 = 0;
+    let mut total_parquet_bytes: u64 = 0;
+    let mut files_ok = 0;
+    let mut files_skipped = 0;
+    let mut files_cancelled = 0;
+    let mut errors: Vec<String> = Vec::new();
+
+    for r in &results {
+        total_records += r.records;
+        total_ndjson_bytes += r.ndjson_bytes;
+        total_bson_bytes += r.bson_bytes;
+        total_bulk_bytes += r.bulk_bytes;
+        total_es_indexed += r.es_indexed;
+        total_es_failed += r.es_failed;
+        total_es_unconfirmed += r.es_unconfirmed;
+        total_parquet_bytes += r.parquet_bytes;
+        if let Some(ref e) = r.error {
+            errors.push(format!("{}: {}", r.file_name, e));
+        } else if r.skipped {
+            files_skipped += 1;
+        } else if r.cancelled {
+            files_cancelled += 1;
+        } else {
+            files_ok += 1;
+        }
+    }
+    errors.extend(summary.best_offer_error);
+    errors.extend(summary.profile_error);
+    errors.extend(summary.checkpoint_error);
+
+    let duration_ms = overall_duration.as_millis() as u64;
+    let rate = if duration_ms > 0 {
+        (total_records as f64 / (duration_ms as f64 / 1000.0)) as u64
+    } else {
+        total_records
+    };
+
+    let complete = Event::Complete {
+        total_records,
+        total_ndjson_bytes,
+        total_bson_bytes,
+        total_bulk_bytes,
+        total_es_indexed,
+        total_es_failed,
+        total_es_unconfirmed,
+        total_parquet_bytes,
+        total_input_bytes,
+        duration_ms,
+        rate_per_sec: rate,
+        files_processed: files_ok,
+        files_total: total_files,
+        errors: errors.len(),
+        files_skipped,
+        files_cancelled,
+        cancelled: summary.cancelled,
+        threads: num_threads,
+        es_index: cli.es_index,
+    };
+    let exit_code = if summary.cancelled {
+        EXIT_CANCELLED
+    } else if !errors.is_empty() && ((total_records == 0 && files_skipped == 0) || strict) {
+        // Still exit 0 if some files succeeded — let Node.js decide,
+        // unless --strict asked for every file to pass
+        1
+    } else {
+        0
+    };
+    Ok(Outcome {
+        complete,
+        errors,
+        exit_code,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use serde_json::{json, Value};
+    use std::path::Path;
+
+    /// An input directory with `good_AB4.csv` and, if asked, `zero_AB4.csv`
+    /// whose prices are all 0
+    fn input(name: &str, with_zero_prices: bool) -> PathBuf {
+        let dir = scratch(&format!("strict-{}", name));
+        fs::create_dir_all(dir.join("in")).unwrap();
+        let rows = |price: &str| {
+            let mut csv = String::from("part_number;brand;price\n");
+            for i in 0..10 {
+                csv += &format!("P{};BOSCH;{}\n", i, price);
+            }
+            csv
+        };
+        fs::write(dir.join("in/good_AB4.csv"), rows("9.50")).unwrap();
+        if with_zero_prices {
+            fs::write(dir.join("in/zero_AB4.csv"), rows("0")).unwrap();
+        }
+        dir
+    }
+
+    fn run(dir: &Path, extra: Value) -> Result<Outcome, String> {
+        let mut params = json!({
+            "input": dir.join("in"),
+            "output": dir.join("out"),
+            "integration-id": "507f1f77bcf86cd799439011",
+            "es-index": "parts",
+        });
+        params.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        let m = super::super::from_json(&COMMAND, params.as_object().unwrap())?;
+        execute(&m, None, Arc::new(AtomicBool::new(false)), |_| {})
+    }
+
+    #[test]
+    fn strict_exits_1_when_any_file_is_rejected() {
+        let dir = input("rejected", true);
+        let outcome = run(&dir, json!({ "strict": true })).unwrap();
+
+        assert_eq!(outcome.exit_code, 1);
+        assert_eq!(outcome.errors.len(), 1);
+        assert!(outcome.errors[0].starts_with("zero_AB4.csv: "), "{:?}", outcome.errors);
+        assert!(outcome.errors[0].contains("zero_price_rate"), "{:?}", outcome.errors);
+        assert!(dir.join("out/good_AB4.ndjson").exists());
+        assert!(!dir.join("out/zero_AB4.ndjson").exists());
+        assert!(!dir.join("out/zero_AB4.bulk").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn strict_exits_0_when_every_file_passes() {
+        let dir = input("passed", false);
+        let outcome = run(&dir, json!({ "strict": true })).unwrap();
+        assert_eq!((outcome.exit_code, outcome.errors.len()), (0, 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn thresholds_are_options_of_strict() {
+        let dir = input("thresholds", true);
+        // Without --strict the zero prices are written like any other
+        let outcome = run(&dir, json!({})).unwrap();
+        assert_eq!((outcome.exit_code, outcome.errors.len()), (0, 0));
+        assert!(dir.join("out/zero_AB4.ndjson").exists());
+
+        // A looser threshold lets the file through
+        let outcome = run(&dir, json!({ "strict": true, "max-zero-price-rate": 1 })).unwrap();
+        assert_eq!(outcome.exit_code, 0);
+
+        let error = run(&dir, json!({ "max-zero-price-rate": 1 })).err().unwrap();
+        assert_eq!(error, "--max-zero-price-rate requires --strict");
+        let error = run(&dir, json!({ "strict": true, "max-reject-rate": 2 })).err().unwrap();
+        assert_eq!(error, "--max-reject-rate must be between 0 and 1, got 2");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+// =============================================================================
+// `turbo-transform validate <file|dir>...` — quality check without output
+// =============================================================================
+// Parses every file exactly like `transform` and checks it against the
+// quality thresholds (see quality.rs). Prints one JSON report on stdout and
+// exits 1 when any file fails; each failing file lists the rules it tripped.
+// =============================================================================
+
+use super::{Command, Matches, Opt};
+use rayon::prelude::*;
+use serde_json::json;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use turbo_transform::discover::Discovery;
+use turbo_transform::quality::{QualityReport, QualityStats, QualityThresholds};
+use turbo_transform::{MappingConfig, Records};
+
+pub const COMMAND: Command = Command {
+    name: "validate",
+    args: "<file.csv|dir>...",
+    about: "Check CSV files against data-quality thresholds without writing output",
+    opts: &[
+        Opt { name: "max-reject-rate", value: Some("r"), help: "Max share of rows that produce no record (default 0.10)" },
+        Opt { name: "max-zero-price-rate", value: Some("r"), help: "Max share of records with price 0 (default 0.10)" },
+        Opt { name: "max-empty-brand-rate", value: Some("r"), help: "Max share of records without a brand (default 0.25)" },
+        Opt { name: "max-duplicate-rate", value: Some("r"), help: "Max share of repeated partNumber+brand+stockCode (default 0.05)" },
+        Opt { name: "min-records", value: Some("n"), help: "Min records per file (default 1)" },
+    ],
+    run,
+};
+
+/// Option names of the thresholds, shared with `transform --strict`
+pub const THRESHOLD_OPTS: [&str; 5] = [
+    "max-reject-rate",
+    "max-zero-price-rate",
+    "max-empty-brand-rate",
+    "max-duplicate-rate",
+    "min-records",
+];
+
+/// Thresholds from the command line, defaults for the rest
+pub fn thresholds(m: &Matches) -> Result<QualityThresholds, String> {
+    let defaults = QualityThresholds::default();
+    let rate = |name: &str, default: f64| -> Result<f64, String> {
+        match m.parse::<f64>(name)? {
+            Some(r) if !(0.0..=1.0).contains(&r) => Err(format!("--{} must be between 0 and 1, got {}", name, r)),
+            Some(r) => Ok(r),
+            None => Ok(default),
+        }
+    };
+    Ok(QualityThresholds {
+        max_reject_rate: rate("max-reject-rate", defaults.max_reject_rate)?,
+        max_zero_price_rate: rate("max-zero-price-rate", defaults.max_zero_price_rate)?,
+        max_empty_brand_rate: rate("max-empty-brand-rate", defaults.max_empty_brand_rate)?,
+        max_duplicate_key_rate: rate("max-duplicate-rate", defaults.max_duplicate_key_rate)?,
+        min_records: m.parse("min-records")?.unwrap_or(defaults.min_records),
+    })
+}
+
+/// The CSVs named on the command line; a directory is searched the way
+/// `transform --input` searches it by default, so both see the same files
+fn csv_paths(args: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut paths = Vec::new();
+    for arg in args {
+        let path = PathBuf::from(arg);
+        if path.is_dir() {
+            paths.extend(Discovery::default().find(&path)?);
+        } else if path.is_file() {
+            paths.push(path);
+        } else {
+            return Err(format!("no such file or directory: {}", path.display()));
+        }
+    }
+    Ok(paths)
+}
+
+/// Quality report for `path`, read exactly as `transform` would
+fn check(path: &Path, mapping: &MappingConfig, thresholds: &QualityThresholds) -> Result<QualityReport, String> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown.csv");
+    let file = File::open(path).map_err(|e| format!("open failed: {}", e))?;
+    let mut records = Records::new(file, file_name, mapping)?;
+    let mut stats = QualityStats::default();
+    while let Some(r) = records.next_record() {
+        stats.add(&r);
+    }
+    Ok(stats.report(records.stats(), thresholds))
+}
+
+fn run(m: &Matches) -> i32 {
+    match execute(m) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            1
+        }
+    }
+}
+
+fn execute(m: &Matches) -> Result<i32, String> {
+    if m.positional.is_empty() {
+        return Err("validate expects at least one <file.csv|dir>".into());
+    }
+    let thresholds = thresholds(m)?;
+    let paths = csv_paths(&m.positional)?;
+    if paths.is_empty() {
+        return Err("no CSV files found".into());
+    }
+
+    let mapping = MappingConfig::new("", "");
+    let files: Vec<_> = paths
+        .par_iter()
+        .map(|path| {
+            let file = path.display().to_string();
+            match check(path, &mapping, &thresholds) {
+                Ok(q) => json!({ "file": file, "ok": q.passed(), "quality": q }),
+                Err(e) => json!({ "file": file, "ok": false, "error": e }),
+            }
+        })
+        .collect();
+    let failed = files.iter().filter(|f| f["ok"] == false).count();
+
+    let out = json!({
+        "ok": failed == 0,
+        "files_checked": files.len(),
+        "files_failed": failed,
+        "thresholds": thresholds,
+        "files": files,
+    });
+    println!("{}", serde_json::to_string_pretty(&out).map_err(|e| e.to_string())?);
+    Ok(if failed == 0 { 0 } else { 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use std::fs;
+
+    #[test]
+    fn directories_hold_the_files_transform_would_take() {
+        let dir = scratch("validate");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["b_AB4.CSV", "a_AB4.csv", "._a_AB4.csv", ".upload.csv", "notes.txt", "sub/c_AB4.csv"] {
+            fs::write(dir.join(name), "part_number;price\nP1;1\n").unwrap();
+        }
+        let named = dir.join("notes.txt").display().to_string();
+
+        let paths = csv_paths(&[dir.display().to_string(), named.clone()]).unwrap();
+        assert_eq!(paths, [dir.join("a_AB4.csv"), dir.join("b_AB4.CSV"), PathBuf::from(&named)]);
+        assert_eq!(paths[..2], Discovery::default().find(&dir).unwrap()[..]);
+        assert!(csv_paths(&[dir.join("missing").display().to_string()]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
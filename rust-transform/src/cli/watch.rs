@@ -0,0 +1,220 @@
+// =============================================================================
+// `turbo-transform watch <dir>` — transform files as they land in a directory
+// =============================================================================
+//   turbo-transform watch /data/incoming --until _batch.complete -- \
+//       --output /data/out --integration-id 507f… --es-index parts
+//
+// Everything after `--` is a `transform` option (--input is <dir>). Files
+// are picked up once they finished arriving (see watch.rs) and transformed
+// in batches: what becomes ready while a batch runs forms the next one. Each
+// batch emits the usual events on stderr and prints its `complete` line on
+// stdout. Every batch records its files in the checkpoint (the first one
+// starts it afresh unless --resume is given) and later batches resume from
+// it, so it lists every file done since the watch started and no batch
+// writes over the outputs of an earlier one. --metrics-addr serves /metrics for the whole
+// watch; --metrics-textfile is rewritten after every batch.
+//
+// --until ends the watch once that file exists and nothing is still
+// settling; otherwise it runs until SIGINT/SIGTERM, which roll back the
+// batch in progress (exit EXIT_CANCELLED).
+// =============================================================================
+
+use super::{parse, transform, Command, Matches, Opt};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use turbo_transform::checkpoint;
+use turbo_transform::discover::Glob;
+use turbo_transform::Event;
+use turbo_transform::watch::{Change, DropDir, Inotify, Readiness};
+
+pub const COMMAND: Command = Command {
+    name: "watch",
+    args: "<dir> -- <transform options>",
+    about: "Transform CSVs as soon as they finish arriving in a drop directory",
+    opts: &[
+        Opt { name: "settle-secs", value: Some("n"), help: "A file is complete once nothing was written to it for n seconds (default 5)" },
+        Opt { name: "done-marker", value: None, help: "A file is complete once <file>.done exists, instead of by settling" },
+        Opt { name: "include", value: Some("glob,.."), help: "Take only files matching these globs (default *.csv)" },
+        Opt { name: "exclude", value: Some("glob,.."), help: "Skip files matching these globs" },
+        Opt { name: "until", value: Some("name"), help: "Exit once <dir>/<name> exists and every complete file is transformed" },
+    ],
+    run,
+};
+
+const DEFAULT_SETTLE_SECS: u64 = 5;
+
+/// Longest wait for inotify before settling files are looked at again
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Transform options that do not fit a watch: the files come from <dir>,
+/// and the cross-file passes need the whole run up front
+const UNSUPPORTED: [&str; 7] = [
+    "recursive",
+    "include",
+    "exclude",
+    "symlinks",
+    "files-from",
+    "best-offer",
+    "profile",
+];
+
+/// Arguments for one batch's `transform`: `extra`, plus what keeps the
+/// checkpoint going when `extra` does not ask for one itself — a fresh
+/// checkpoint in the first batch, resuming it in the later ones
+fn transform_args(dir: &str, extra: &[String], first: bool, user: &Matches) -> Result<Vec<String>, String> {
+    let mut args = vec!["--input".to_string(), dir.to_string()];
+    args.extend(extra.iter().cloned());
+    if !user.flag("resume") {
+        if !first {
+            args.push("--resume".to_string());
+        } else if !user.has("checkpoint") {
+            let output = Path::new(user.required("output")?);
+            args.push("--checkpoint".to_string());
+            args.push(output.join(checkpoint::DEFAULT_NAME).display().to_string());
+        }
+    }
+    Ok(args)
+}
+
+fn run(m: &Matches) -> i32 {
+    match watch(m) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            1
+        }
+    }
+}
+
+fn watch(m: &Matches) -> Result<i32, String> {
+    let Some((dir, extra)) = m.positional.split_first() else {
+        return Err("watch expects <dir> -- <transform options>".into());
+    };
+    let readiness = if m.flag("done-marker") {
+        if m.has("settle-secs") {
+            return Err("--settle-secs cannot be combined with --done-marker".into());
+        }
+        Readiness::Marker
+    } else {
+        Readiness::Settled(Duration::from_secs(m.parse("settle-secs")?.unwrap_or(DEFAULT_SETTLE_SECS)))
+    };
+
+    let mut as_given = vec!["--input".to_string(), dir.to_string()];
+    as_given.extend(extra.iter().cloned());
+    let options = parse(&transform::COMMAND, &as_given).map_err(|e| format!("transform options: {}", e))?;
+    if let Some(opt) = UNSUPPORTED.iter().find(|o| options.has(o)) {
+        return Err(format!("--{} cannot be used with watch", opt));
+    }
+    transform::check(&options, true).map_err(|e| format!("transform options: {}", e))?;
+
+    let export = transform::MetricsExport::start(&options)?.map(Arc::new);
+    let integration = options.value("integration-id").unwrap_or("").to_string();
+    let cancel = transform::cancel_on_signals()?;
+    let root = Path::new(dir);
+    if !root.is_dir() {
+        return Err(format!("input directory does not exist: {}", root.display()));
+    }
+    // Watch before the first scan, so nothing lands unseen in between
+    let mut inotify = Inotify::watch(root).map_err(|e| format!("cannot watch {}: {}", root.display(), e))?;
+    let mut drop_dir = DropDir::new(
+        root,
+        readiness,
+        m.value("include").map(Glob::parse_list).transpose()?.unwrap_or_default(),
+        m.value("exclude").map(Glob::parse_list).transpose()?.unwrap_or_default(),
+    )?;
+    drop_dir.rescan()?;
+
+    let mut batches = 0;
+    let mut failed = false;
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(transform::EXIT_CANCELLED);
+        }
+
+        let ready = drop_dir.take_ready();
+        if !ready.is_empty() {
+            let files = ready
+                .iter()
+                .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+                .collect();
+            let batch = parse(&transform::COMMAND, &transform_args(dir, extra, batches == 0, &options)?)?;
+            batches += 1;
+            let on_event = {
+                let (export, integration) = (export.clone(), integration.clone());
+                move |event: &Event| {
+                    if let Some(ref x) = export {
+                        x.observe(&integration, 0, event);
+                    }
+                    eprintln!("{}", event.to_json());
+                }
+            };
+            match transform::execute(&batch, Some(files), Arc::clone(&cancel), on_event) {
+                Ok(outcome) => {
+                    println!("{}", outcome.complete.to_json());
+                    for e in &outcome.errors {
+                        eprintln!("ERROR: {}", e);
+                    }
+                    if let Some(ref x) = export {
+                        x.observe(&integration, 0, &outcome.complete);
+                        if let Err(e) = x.write() {
+                            eprintln!("ERROR: {}", e);
+                        }
+                    }
+                    if outcome.exit_code == transform::EXIT_CANCELLED {
+                        return Ok(transform::EXIT_CANCELLED);
+                    }
+                    failed |= outcome.exit_code != 0;
+                }
+                Err(e) => {
+                    eprintln!("ERROR: {}", e);
+                    failed = true;
+                }
+            }
+            // Whatever changed meanwhile is read below, or on the next pass
+            continue;
+        }
+
+        if let Some(name) = m.value("until") {
+            if root.join(name).exists() && drop_dir.settling() == 0 {
+                return Ok(if failed { 1 } else { 0 });
+            }
+        }
+
+        for change in inotify.wait(POLL_INTERVAL).map_err(|e| format!("watching {}: {}", root.display(), e))? {
+            match change {
+                Change::Entry(name) => drop_dir.changed(&name),
+                Change::Overflow => drop_dir.rescan()?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(extra: &[&str], first: bool) -> Vec<String> {
+        let extra: Vec<String> = extra.iter().map(|s| s.to_string()).collect();
+        let mut given = vec!["--input".to_string(), "/drop".to_string()];
+        given.extend(extra.iter().cloned());
+        let user = parse(&transform::COMMAND, &given).unwrap();
+        transform_args("/drop", &extra, first, &user).unwrap()[2 + extra.len()..].to_vec()
+    }
+
+    #[test]
+    fn batches_share_one_checkpoint() {
+        let base = ["--output", "/out", "--integration-id", "x"];
+        assert_eq!(args(&base, true), ["--checkpoint", "/out/turbo-transform.checkpoint.json"]);
+        assert_eq!(args(&base, false), ["--resume"]);
+
+        // The user's own checkpoint is kept; --resume already resumes it
+        let own = ["--output", "/out", "--integration-id", "x", "--checkpoint", "/cp.json"];
+        assert!(args(&own, true).is_empty());
+        assert_eq!(args(&own, false), ["--resume"]);
+        let resuming = ["--output", "/out", "--integration-id", "x", "--resume"];
+        assert!(args(&resuming, true).is_empty());
+        assert!(args(&resuming, false).is_empty());
+    }
+}
@@ -0,0 +1,472 @@
+// =============================================================================
+// Input discovery — which CSV files under the input directory make up a run
+// =============================================================================
+// Paths are matched relative to the input directory, with '/' separators and
+// ASCII case ignored (like the .csv extension check always was):
+//
+//   *.csv             a pattern without '/' matches the file name, at any depth
+//   2024-*/*.csv      a pattern with '/' matches the whole relative path
+//   *  ?  [a-z] [!x]  never cross a '/';  **  matches any number of folders
+//
+// None of them matches the leading '.' of a hidden file or folder (._parts.csv
+// from macOS, .parts.csv.part from an FTP server); a pattern that spells the
+// '.' does: .*.csv
+//
+// A file is taken when it matches an include pattern (default *.csv) and no
+// exclude pattern. Symlinks are skipped, followed only when they point at a
+// file (the default), or followed into directories too — each directory is
+// visited once, so link loops end.
+//
+// --files-from gives the list explicitly instead: one path per line,
+// relative to the input directory, '#' comments allowed. Outputs are named
+// after that relative path, so absolute paths, '..' and symlinks leading
+// out of the input directory are rejected.
+// =============================================================================
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// =============================================================================
+// Glob patterns
+// =============================================================================
+#[derive(Clone, Debug)]
+pub struct Glob {
+    pattern: Vec<char>,
+    /// Contains '/': matched against the relative path, not the file name
+    whole_path: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Glob, String> {
+        if pattern.is_empty() {
+            return Err("empty glob pattern".into());
+        }
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == '[' {
+                match class_end(&chars, i) {
+                    Some(end) => i = end,
+                    None => return Err(format!("unclosed '[' in glob pattern '{}'", pattern)),
+                }
+            }
+            i += 1;
+        }
+        Ok(Glob {
+            whole_path: pattern.contains('/'),
+            pattern: chars,
+        })
+    }
+
+    /// Comma-separated patterns, as given on the command line
+    pub fn parse_list(list: &str) -> Result<Vec<Glob>, String> {
+        list.split(',').map(str::trim).filter(|p| !p.is_empty()).map(Glob::new).collect()
+    }
+
+    /// `relative` uses '/' separators
+    pub fn matches(&self, relative: &str) -> bool {
+        let subject = if self.whole_path {
+            relative
+        } else {
+            relative.rsplit('/').next().unwrap_or(relative)
+        };
+        let text: Vec<char> = subject.chars().collect();
+        glob_match(&self.pattern, &text, true)
+    }
+}
+
+/// Index of the ']' closing the class that opens at `start`
+fn class_end(p: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    if p.get(i) == Some(&'!') {
+        i += 1;
+    }
+    // A ']' right after the opening bracket is a literal member
+    if p.get(i) == Some(&']') {
+        i += 1;
+    }
+    p[i.min(p.len())..].iter().position(|&c| c == ']').map(|n| i + n)
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let c = c.to_ascii_lowercase();
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        let lo = class[i].to_ascii_lowercase();
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            let hi = class[i + 2].to_ascii_lowercase();
+            found |= lo <= c && c <= hi;
+            i += 3;
+        } else {
+            found |= lo == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+/// `at_start`: `t` begins a file or folder name, whose leading '.' only a
+/// literal '.' matches
+fn glob_match(p: &[char], t: &[char], at_start: bool) -> bool {
+    let hidden = at_start && t.first() == Some(&'.');
+    match p.first() {
+        None => t.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => {
+            let rest = &p[2..];
+            // Spans that pass over no hidden name: stop at the first one
+            let visible = (0..t.len())
+                .find(|&i| t[i] == '.' && if i == 0 { at_start } else { t[i - 1] == '/' })
+                .unwrap_or(t.len());
+            match rest.first() {
+                // "**/" — zero or more whole folders
+                Some('/') => (0..=visible)
+                    .filter(|&i| i == 0 || t[i - 1] == '/')
+                    .any(|i| glob_match(&rest[1..], &t[i..], i > 0 || at_start)),
+                _ => (0..=visible).any(|i| glob_match(rest, &t[i..], if i == 0 { at_start } else { t[i - 1] == '/' })),
+            }
+        }
+        Some('*') => {
+            let end = if hidden { 0 } else { t.iter().position(|&c| c == '/').unwrap_or(t.len()) };
+            (0..=end).any(|i| glob_match(&p[1..], &t[i..], i == 0 && at_start))
+        }
+        Some('?') => !hidden && t.first().is_some_and(|&c| c != '/') && glob_match(&p[1..], &t[1..], false),
+        Some('[') => {
+            let end = class_end(p, 0).expect("classes are checked in Glob::new");
+            !hidden
+                && t.first().is_some_and(|&c| c != '/' && class_matches(&p[1..end], c))
+                && glob_match(&p[end + 1..], &t[1..], false)
+        }
+        Some(&c) => {
+            t.first().is_some_and(|&x| x.eq_ignore_ascii_case(&c)) && glob_match(&p[1..], &t[1..], c == '/')
+        }
+    }
+}
+
+// =============================================================================
+// Directory walk
+// =============================================================================
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SymlinkPolicy {
+    /// Ignore every symlink
+    Skip,
+    /// Follow links to files, ignore links to directories
+    #[default]
+    Files,
+    /// Follow links to files and directories
+    Follow,
+}
+
+impl SymlinkPolicy {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "skip" => Ok(SymlinkPolicy::Skip),
+            "files" => Ok(SymlinkPolicy::Files),
+            "follow" => Ok(SymlinkPolicy::Follow),
+            other => Err(format!("unknown symlink policy '{}' (expected skip, files or follow)", other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Discovery {
+    /// Descend into subdirectories
+    pub recursive: bool,
+    /// Empty = *.csv
+    pub include: Vec<Glob>,
+    pub exclude: Vec<Glob>,
+    pub symlinks: SymlinkPolicy,
+}
+
+impl Discovery {
+    /// Every matching file under `root`, sorted by path
+    pub fn find(&self, root: &Path) -> Result<Vec<PathBuf>, String> {
+        let default_include = [Glob::new("*.csv")?];
+        let include = if self.include.is_empty() { &default_include[..] } else { &self.include };
+
+        let mut found = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![(root.to_path_buf(), String::new())];
+        if let Ok(canonical) = fs::canonicalize(root) {
+            visited.insert(canonical);
+        }
+        while let Some((dir, prefix)) = pending.pop() {
+            let entries = fs::read_dir(&dir).map_err(|e| format!("cannot read {}: {}", dir.display(), e))?;
+            for entry in entries {
+                let entry = entry.map_err(|e| format!("cannot read {}: {}", dir.display(), e))?;
+                let path = entry.path();
+                let relative = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+                let Ok(mut file_type) = entry.file_type() else {
+                    continue;
+                };
+                if file_type.is_symlink() {
+                    if self.symlinks == SymlinkPolicy::Skip {
+                        continue;
+                    }
+                    // Dangling links are skipped like any unreadable entry
+                    let Ok(target) = fs::metadata(&path) else {
+                        continue;
+                    };
+                    if target.is_dir() && self.symlinks != SymlinkPolicy::Follow {
+                        continue;
+                    }
+                    file_type = target.file_type();
+                }
+                if file_type.is_dir() {
+                    let first_visit = fs::canonicalize(&path).map(|c| visited.insert(c)).unwrap_or(false);
+                    if self.recursive && first_visit {
+                        pending.push((path, format!("{}/", relative)));
+                    }
+                } else if file_type.is_file()
+                    && include.iter().any(|g| g.matches(&relative))
+                    && !self.exclude.iter().any(|g| g.matches(&relative))
+                {
+                    found.push(path);
+                }
+            }
+        }
+        found.sort();
+        Ok(found)
+    }
+}
+
+/// The files named in a --files-from list, relative to `root`
+pub fn read_file_list(list: &str, root: &Path) -> Result<Vec<PathBuf>, String> {
+    let base = canonical_root(root)?;
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    for (n, line) in list.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let path = listed_file(root, &base, line, &mut seen).map_err(|e| format!("line {}: {}", n + 1, e))?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// Explicitly named files, relative to `root`
+pub fn resolve_files(paths: &[String], root: &Path) -> Result<Vec<PathBuf>, String> {
+    let base = canonical_root(root)?;
+    let mut seen = HashSet::new();
+    paths.iter().map(|p| listed_file(root, &base, p, &mut seen)).collect()
+}
+
+fn canonical_root(root: &Path) -> Result<PathBuf, String> {
+    fs::canonicalize(root).map_err(|e| format!("cannot read {}: {}", root.display(), e))
+}
+
+/// `entry` under `root` (canonically `base`); it must stay inside it
+fn listed_file(root: &Path, base: &Path, entry: &str, seen: &mut HashSet<PathBuf>) -> Result<PathBuf, String> {
+    let relative = Path::new(entry);
+    let inside = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !inside {
+        return Err(format!("not a path inside the input directory: {}", entry));
+    }
+    let path = root.join(relative);
+    if !path.is_file() {
+        return Err(format!("not a file: {}", path.display()));
+    }
+    let canonical = fs::canonicalize(&path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    if !canonical.starts_with(base) {
+        return Err(format!("leads outside the input directory: {}", entry));
+    }
+    if !seen.insert(canonical) {
+        return Err(format!("listed twice: {}", path.display()));
+    }
+    Ok(path)
+}
+
+/// `path` relative to `root` with '/' separators — the file name when it is
+/// not under `root`. Names files in events and mirrors them in the outputs,
+/// so only plain folder names are kept: never '..', '.' or a root.
+pub fn relative_name(root: Option<&Path>, path: &Path) -> String {
+    let parts: Vec<_> = root
+        .and_then(|r| path.strip_prefix(r).ok())
+        .map(|rel| {
+            rel.components()
+                .filter_map(|c| match c {
+                    Component::Normal(part) => Some(part.to_string_lossy()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if parts.is_empty() {
+        path.file_name().unwrap_or_default().to_string_lossy().to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+
+    /// input/a.csv, input/sub/b.csv and other/parts.csv in a fresh directory
+    fn tree(name: &str) -> PathBuf {
+        let dir = scratch(&format!("discover-{}", name));
+        fs::create_dir_all(dir.join("input/sub")).unwrap();
+        fs::create_dir_all(dir.join("other")).unwrap();
+        for file in ["input/a.csv", "input/sub/b.csv", "other/parts.csv"] {
+            fs::write(dir.join(file), "part_number\n1\n").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn file_list_stays_inside_the_input_directory() {
+        let dir = tree("list");
+        let root = dir.join("input");
+        let outside = dir.join("other/parts.csv").display().to_string();
+
+        let files = read_file_list("a.csv\n./sub/b.csv\n# comment\n", &root).unwrap();
+        assert_eq!(files, vec![root.join("a.csv"), root.join("./sub/b.csv")]);
+
+        for entry in ["../other/parts.csv", "sub/../../other/parts.csv", outside.as_str()] {
+            let err = read_file_list(entry, &root).unwrap_err();
+            assert!(err.contains("not a path inside the input directory"), "{}: {}", entry, err);
+        }
+
+        std::os::unix::fs::symlink(dir.join("other/parts.csv"), root.join("link.csv")).unwrap();
+        let err = resolve_files(&["link.csv".to_string()], &root).unwrap_err();
+        assert!(err.contains("leads outside the input directory"), "{}", err);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn relative_name_keeps_only_folder_names() {
+        let root = Path::new("/data/in");
+        assert_eq!(relative_name(Some(root), Path::new("/data/in/sub/b.csv")), "sub/b.csv");
+        assert_eq!(relative_name(Some(root), Path::new("/data/in/./sub/b.csv")), "sub/b.csv");
+        assert_eq!(relative_name(Some(root), Path::new("/data/in/../other/parts.csv")), "other/parts.csv");
+        assert_eq!(relative_name(Some(root), Path::new("/elsewhere/c.csv")), "c.csv");
+        assert_eq!(relative_name(None, Path::new("/data/in/sub/b.csv")), "b.csv");
+    }
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).unwrap().matches(path)
+    }
+
+    #[test]
+    fn star_and_question_mark() {
+        assert!(matches("*.csv", "a.csv"));
+        assert!(matches("*.csv", "A.CSV"));
+        assert!(matches("*.csv", "deep/sub/a.csv"), "a name pattern matches at any depth");
+        assert!(!matches("*.csv", "a.csv.bak"));
+        assert!(matches("prices_*_part?.csv", "prices_AB4_part1.csv"));
+        assert!(!matches("prices_*_part?.csv", "prices_AB4_part12.csv"));
+        assert!(!matches("?.csv", ".csv"));
+
+        // With a '/', the whole path; * and ? never cross a folder
+        assert!(matches("2024-*/*.csv", "2024-05/a.csv"));
+        assert!(!matches("2024-*/*.csv", "2024-05/x/a.csv"));
+        assert!(!matches("2024-*/*.csv", "a.csv"));
+        assert!(!matches("a?b.csv", "a/b.csv"));
+        assert!(matches("**/a.csv", "a.csv"));
+        assert!(matches("**/a.csv", "x/y/a.csv"));
+        assert!(matches("in/**/*.csv", "in/a.csv"));
+        assert!(matches("in/**/*.csv", "in/x/y/a.csv"));
+        assert!(!matches("in/**/*.csv", "out/x/a.csv"));
+    }
+
+    #[test]
+    fn character_classes() {
+        assert!(matches("[a-c]*.csv", "b.csv"));
+        assert!(matches("[a-c]*.csv", "B.csv"));
+        assert!(!matches("[a-c]*.csv", "d.csv"));
+        assert!(matches("[!a-c]*.csv", "d.csv"));
+        assert!(!matches("[!a-c]*.csv", "a.csv"));
+        assert!(matches("part[0-9].csv", "part7.csv"));
+        assert!(matches("[]x].csv", "].csv"), "a ']' first is a member");
+        assert!(!matches("a[/]b", "a/b"));
+        assert_eq!(Glob::new("[a-c.csv").unwrap_err(), "unclosed '[' in glob pattern '[a-c.csv'");
+        assert_eq!(Glob::new("").unwrap_err(), "empty glob pattern");
+        assert_eq!(Glob::parse_list("*.csv, ,*.txt").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn dotfiles_need_an_explicit_dot() {
+        for hidden in [".a.csv", "._a.csv", "sub/.a.csv"] {
+            assert!(!matches("*.csv", hidden), "{}", hidden);
+            assert!(!matches("?a.csv", hidden), "{}", hidden);
+            assert!(!matches("[._]*.csv", hidden), "{}", hidden);
+        }
+        assert!(matches(".*.csv", ".a.csv"));
+        assert!(matches("sub/.*", "sub/.a.csv"));
+        assert!(matches("*.*.csv", "a.b.csv"), "only a leading '.' is special");
+        assert!(!matches("**/*.csv", ".git/a.csv"));
+        assert!(!matches("**/*.csv", "sub/.cache/a.csv"));
+        assert!(matches(".cache/*.csv", ".cache/a.csv"));
+        assert!(matches("**/.cache/*.csv", "sub/.cache/a.csv"));
+    }
+
+    fn found(discovery: &Discovery, root: &Path) -> Vec<String> {
+        discovery
+            .find(root)
+            .unwrap()
+            .iter()
+            .map(|p| relative_name(Some(root), p))
+            .collect()
+    }
+
+    #[test]
+    fn recursive_walk_with_include_and_exclude() {
+        let dir = tree("walk");
+        let root = dir.join("input");
+        fs::create_dir_all(root.join("sub/deeper")).unwrap();
+        for file in ["sub/deeper/c.CSV", "sub/notes.txt", "sub/skip_me.csv", ".d.csv"] {
+            fs::write(root.join(file), "x\n").unwrap();
+        }
+
+        assert_eq!(found(&Discovery::default(), &root), ["a.csv"]);
+        let recursive = Discovery { recursive: true, ..Discovery::default() };
+        assert_eq!(found(&recursive, &root), ["a.csv", "sub/b.csv", "sub/deeper/c.CSV", "sub/skip_me.csv"]);
+
+        let filtered = Discovery {
+            recursive: true,
+            include: Glob::parse_list("*.csv,*.txt").unwrap(),
+            exclude: Glob::parse_list("skip_*,deeper/*").unwrap(),
+            ..Discovery::default()
+        };
+        // "deeper/*" has a '/', so it is matched against the whole path and misses sub/deeper/c.CSV
+        assert_eq!(found(&filtered, &root), ["a.csv", "sub/b.csv", "sub/deeper/c.CSV", "sub/notes.txt"]);
+        let filtered = Discovery { exclude: Glob::parse_list("skip_*,**/deeper/*").unwrap(), ..filtered };
+        assert_eq!(found(&filtered, &root), ["a.csv", "sub/b.csv", "sub/notes.txt"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn symlink_policy() {
+        let dir = tree("symlinks");
+        let root = dir.join("input");
+        std::os::unix::fs::symlink(dir.join("other/parts.csv"), root.join("file_link.csv")).unwrap();
+        std::os::unix::fs::symlink(dir.join("other"), root.join("dir_link")).unwrap();
+        std::os::unix::fs::symlink(dir.join("missing.csv"), root.join("dangling.csv")).unwrap();
+        // A loop back to the root is walked once
+        std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+
+        let with = |symlinks| Discovery { recursive: true, symlinks, ..Discovery::default() };
+        assert_eq!(found(&with(SymlinkPolicy::Skip), &root), ["a.csv", "sub/b.csv"]);
+        assert_eq!(found(&with(SymlinkPolicy::Files), &root), ["a.csv", "file_link.csv", "sub/b.csv"]);
+        assert_eq!(
+            found(&with(SymlinkPolicy::Follow), &root),
+            ["a.csv", "dir_link/parts.csv", "file_link.csv", "sub/b.csv"]
+        );
+        assert_eq!(SymlinkPolicy::parse("follow").unwrap(), SymlinkPolicy::Follow);
+        assert!(SymlinkPolicy::parse("always").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
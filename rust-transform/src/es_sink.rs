@@ -0,0 +1,643 @@
+// =============================================================================
+// Elasticsearch _bulk sink — streams bulk bodies straight to ES/OpenSearch
+// =============================================================================
+// Skips the .bulk file round-trip (Rust writes → Node re-reads → POST).
+//
+//   - Byte-sized batches: a batch is POSTed once it reaches max_batch_bytes
+//   - Concurrency limit: one semaphore shared by every rayon file thread
+//   - 429 / 502 / 503 / 504, and transport errors before the request
+//     reached ES (DNS, connect): exponential backoff retry
+//   - Per-item errors parsed from the response; item-level 429s are retried,
+//     everything else goes to <stem>.deadletter.ndjson
+//   - A 2xx whose body cannot be read or parsed: ES applied the batch, but
+//     not which items succeeded. Those docs are counted as unconfirmed —
+//     never resent (that would duplicate auto-id docs) nor dead-lettered.
+//     So is a transport error once the request may have reached ES (a
+//     timeout or reset while waiting for the response)
+//
+// Plain http:// endpoints are supported, so a local mock server can stand in
+// for ES when testing.
+// =============================================================================
+
+use base64::Engine;
+use serde::Deserialize;
+use ureq::ErrorKind;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_BATCH_BYTES: usize = 10 * 1024 * 1024;
+pub const DEFAULT_CONCURRENCY: usize = 8;
+pub const DEFAULT_MAX_RETRIES: u32 = 8;
+
+const BACKOFF_BASE_MS: u64 = 200;
+const BACKOFF_MAX_MS: u64 = 30_000;
+
+pub struct EsSinkConfig {
+    /// Cluster base URL, e.g. http://localhost:9200
+    pub url: String,
+    pub max_batch_bytes: usize,
+    pub concurrency: usize,
+    pub max_retries: u32,
+    /// Directory for <stem>.deadletter.ndjson files
+    pub dead_letter_dir: PathBuf,
+    /// Basic auth credentials (same env vars Node uses)
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+// =============================================================================
+// Counting semaphore — caps in-flight POSTs across all file threads
+// =============================================================================
+struct Semaphore {
+    available: Mutex<usize>,
+    cond: Condvar,
+}
+
+impl Semaphore {
+    fn new(permits: usize) -> Self {
+        Semaphore {
+            available: Mutex::new(permits.max(1)),
+            cond: Condvar::new(),
+        }
+    }
+
+    fn acquire(&self) -> Permit<'_> {
+        let mut n = self.available.lock().unwrap_or_else(|e| e.into_inner());
+        while *n == 0 {
+            n = self.cond.wait(n).unwrap_or_else(|e| e.into_inner());
+        }
+        *n -= 1;
+        Permit(self)
+    }
+}
+
+struct Permit<'a>(&'a Semaphore);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut n = self.0.available.lock().unwrap_or_else(|e| e.into_inner());
+        *n += 1;
+        self.0.cond.notify_one();
+    }
+}
+
+// =============================================================================
+// _bulk response — only the parts we need
+// =============================================================================
+#[derive(Deserialize)]
+struct BulkResponse {
+    #[serde(default)]
+    errors: bool,
+    #[serde(default)]
+    items: Vec<HashMap<String, BulkItem>>,
+}
+
+#[derive(Deserialize)]
+struct BulkItem {
+    status: u16,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+/// Outcome of a single POST attempt
+enum Attempt {
+    /// 2xx — parsed response body
+    Done(BulkResponse),
+    /// 2xx, but the body could not be read or parsed; or the request may
+    /// have reached ES and no answer came back
+    Unconfirmed,
+    /// Worth retrying the whole batch (429, 5xx gateway, ES not reached)
+    Retry(u16, String),
+    /// Non-retryable whole-request failure
+    Fatal(u16, String),
+}
+
+// =============================================================================
+// Shared sink — one per run
+// =============================================================================
+pub struct EsSink {
+    agent: ureq::Agent,
+    bulk_url: String,
+    auth_header: Option<String>,
+    max_batch_bytes: usize,
+    max_retries: u32,
+    dead_letter_dir: PathBuf,
+    permits: Semaphore,
+}
+
+impl EsSink {
+    pub fn new(config: EsSinkConfig) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(10))
+            .timeout(Duration::from_secs(300))
+            .max_idle_connections(config.concurrency.max(1) * 2)
+            .build();
+
+        let auth_header = match (config.username, config.password) {
+            (Some(u), Some(p)) => Some(format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", u, p))
+            )),
+            _ => None,
+        };
+
+        EsSink {
+            agent,
+            bulk_url: format!("{}/_bulk", config.url.trim_end_matches('/')),
+            auth_header,
+            max_batch_bytes: config.max_batch_bytes.max(1),
+            max_retries: config.max_retries,
+            dead_letter_dir: config.dead_letter_dir,
+            permits: Semaphore::new(config.concurrency),
+        }
+    }
+
+    /// Start a per-file stream. Dead letters go to <stem>.deadletter.ndjson.
+    pub fn stream(&self, stem: &str) -> EsStream<'_> {
+        EsStream {
+            sink: self,
+            dead_letter_path: self.dead_letter_dir.join(format!("{}.deadletter.ndjson", stem)),
+            dead_letter: None,
+            body: Vec::with_capacity(self.max_batch_bytes + 64 * 1024),
+            items: Vec::new(),
+            counts: EsCounts::default(),
+            io_error: None,
+        }
+    }
+
+    fn post(&self, body: &[u8]) -> Attempt {
+        let _permit = self.permits.acquire();
+
+        let mut req = self
+            .agent
+            .post(&self.bulk_url)
+            .set("Content-Type", "application/x-ndjson");
+        if let Some(ref auth) = self.auth_header {
+            req = req.set("Authorization", auth);
+        }
+
+        match req.send_bytes(body) {
+            Ok(resp) => {
+                let mut text = String::new();
+                if resp.into_reader().read_to_string(&mut text).is_err() {
+                    return Attempt::Unconfirmed;
+                }
+                match serde_json::from_str::<BulkResponse>(&text) {
+                    Ok(parsed) => Attempt::Done(parsed),
+                    Err(_) => Attempt::Unconfirmed,
+                }
+            }
+            Err(ureq::Error::Status(code, resp)) => {
+                let text = resp.into_string().unwrap_or_default();
+                let snippet: String = text.chars().take(500).collect();
+                if matches!(code, 429 | 502 | 503 | 504) {
+                    Attempt::Retry(code, snippet)
+                } else {
+                    Attempt::Fatal(code, snippet)
+                }
+            }
+            Err(ureq::Error::Transport(t)) => match t.kind() {
+                ErrorKind::Dns | ErrorKind::ConnectionFailed | ErrorKind::ProxyConnect => {
+                    Attempt::Retry(0, format!("transport error: {}", t))
+                }
+                ErrorKind::InvalidUrl
+                | ErrorKind::UnknownScheme
+                | ErrorKind::InsecureRequestHttpsOnly
+                | ErrorKind::InvalidProxyUrl => Attempt::Fatal(0, format!("transport error: {}", t)),
+                // The body may have been applied; resending could index it twice
+                _ => Attempt::Unconfirmed,
+            },
+        }
+    }
+}
+
+fn backoff(attempt: u32) {
+    let ms = BACKOFF_BASE_MS
+        .saturating_mul(1u64 << attempt.min(16))
+        .min(BACKOFF_MAX_MS);
+    thread::sleep(Duration::from_millis(ms));
+}
+
+// =============================================================================
+// Per-file stream — owned by one rayon thread
+// =============================================================================
+/// What became of the documents of one stream
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EsCounts {
+    pub indexed: u64,
+    /// Dead-lettered
+    pub failed: u64,
+    /// Sent and either accepted with a 2xx whose body was unreadable, or
+    /// left without an answer: possibly indexed, not confirmed item by item
+    pub unconfirmed: u64,
+}
+
+pub struct EsStream<'a> {
+    sink: &'a EsSink,
+    dead_letter_path: PathBuf,
+    dead_letter: Option<BufWriter<File>>,
+    /// Pending _bulk body (action + doc pairs)
+    body: Vec<u8>,
+    /// Byte ranges of each action+doc pair within `body`, in request order
+    items: Vec<Range<usize>>,
+    counts: EsCounts,
+    io_error: Option<String>,
+}
+
+impl EsStream<'_> {
+    /// Queue one action+doc pair (both newline-terminated)
+    pub fn push(&mut self, action: &[u8], doc: &[u8]) {
+        let start = self.body.len();
+        self.body.extend_from_slice(action);
+        self.body.extend_from_slice(doc);
+        self.items.push(start..self.body.len());
+
+        if self.body.len() >= self.sink.max_batch_bytes {
+            self.flush();
+        }
+    }
+
+    /// Send whatever is pending; retries until every item is indexed or dead-lettered
+    pub fn flush(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        let mut body = std::mem::take(&mut self.body);
+        let mut items = std::mem::take(&mut self.items);
+        let mut attempt: u32 = 0;
+
+        while !items.is_empty() {
+            match self.sink.post(&body) {
+                Attempt::Done(resp) => {
+                    if !resp.errors {
+                        self.counts.indexed += items.len() as u64;
+                        break;
+                    }
+                    let mut retry: Vec<Range<usize>> = Vec::new();
+                    for (i, range) in items.iter().enumerate() {
+                        let item = resp.items.get(i).and_then(|m| m.values().next());
+                        match item {
+                            Some(it) if it.status < 300 => self.counts.indexed += 1,
+                            Some(it) if it.status == 429 && attempt < self.sink.max_retries => {
+                                retry.push(range.clone())
+                            }
+                            Some(it) => {
+                                let error = it.error.clone().unwrap_or(serde_json::Value::Null);
+                                self.dead_letter(&body[range.clone()], it.status, &error);
+                            }
+                            None => self.dead_letter(
+                                &body[range.clone()],
+                                0,
+                                &serde_json::Value::from("missing item in _bulk response"),
+                            ),
+                        }
+                    }
+                    if retry.is_empty() {
+                        break;
+                    }
+                    // Rebuild a body holding only the rejected pairs
+                    let mut next = Vec::with_capacity(retry.iter().map(|r| r.len()).sum());
+                    let mut next_items = Vec::with_capacity(retry.len());
+                    for r in retry {
+                        let start = next.len();
+                        next.extend_from_slice(&body[r]);
+                        next_items.push(start..next.len());
+                    }
+                    body = next;
+                    items = next_items;
+                }
+                Attempt::Unconfirmed => {
+                    self.counts.unconfirmed += items.len() as u64;
+                    break;
+                }
+                Attempt::Retry(status, reason) => {
+                    if attempt >= self.sink.max_retries {
+                        let error = serde_json::Value::from(reason);
+                        for range in &items {
+                            self.dead_letter(&body[range.clone()], status, &error);
+                        }
+                        break;
+                    }
+                }
+                Attempt::Fatal(status, reason) => {
+                    let error = serde_json::Value::from(reason);
+                    for range in &items {
+                        self.dead_letter(&body[range.clone()], status, &error);
+                    }
+                    break;
+                }
+            }
+            backoff(attempt);
+            attempt += 1;
+        }
+
+        // Hand the allocation back for the next batch
+        body.clear();
+        self.body = body;
+    }
+
+    /// Flush remaining docs and close the dead-letter file.
+    /// Returns an error only if the dead-letter file could not be written.
+    pub fn finish(mut self) -> Result<EsCounts, String> {
+        self.flush();
+        if let Some(mut w) = self.dead_letter.take() {
+            if let Err(e) = w.flush() {
+                self.io_error.get_or_insert(format!("dead-letter flush failed: {}", e));
+            }
+        }
+        match self.io_error {
+            Some(e) => Err(e),
+            None => Ok(self.counts),
+        }
+    }
+
+    /// Drop the pending batch unsent (a rolled-back file) and close the
+    /// dead-letter file. Batches already POSTed stay indexed.
+    pub fn discard(mut self) -> Result<EsCounts, String> {
+        self.body.clear();
+        self.items.clear();
+        self.finish()
+    }
+
+    /// Write one failed pair as {"status":..,"error":..,"action":..,"doc":..}
+    fn dead_letter(&mut self, pair: &[u8], status: u16, error: &serde_json::Value) {
+        self.counts.failed += 1;
+
+        if self.dead_letter.is_none() {
+            match File::create(&self.dead_letter_path) {
+                Ok(f) => self.dead_letter = Some(BufWriter::new(f)),
+                Err(e) => {
+                    self.io_error.get_or_insert(format!(
+                        "create dead-letter file {} failed: {}",
+                        self.dead_letter_path.display(),
+                        e
+                    ));
+                    return;
+                }
+            }
+        }
+
+        // Pair is "<action>\n<doc>\n" — both are already valid JSON
+        let mut lines = pair.split(|&b| b == b'\n').filter(|l| !l.is_empty());
+        let action = lines.next().unwrap_or(b"null");
+        let doc = lines.next().unwrap_or(b"null");
+
+        let mut line = Vec::with_capacity(pair.len() + 128);
+        line.extend_from_slice(format!(r#"{{"status":{},"error":"#, status).as_bytes());
+        let _ = serde_json::to_writer(&mut line, error);
+        line.extend_from_slice(br#","action":"#);
+        line.extend_from_slice(action);
+        line.extend_from_slice(br#","doc":"#);
+        line.extend_from_slice(doc);
+        line.extend_from_slice(b"}\n");
+
+        if let Some(ref mut w) = self.dead_letter {
+            if let Err(e) = w.write_all(&line) {
+                self.io_error.get_or_insert(format!("dead-letter write failed: {}", e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use std::io::{BufRead, BufReader};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A local HTTP stub standing in for ES: answers the n-th request with
+    /// the n-th scripted (status, body), repeating the last one
+    struct Stub {
+        url: String,
+        /// Request bodies, in arrival order
+        requests: Arc<Mutex<Vec<String>>>,
+        /// Most requests handled at the same time
+        peak: Arc<AtomicUsize>,
+    }
+
+    impl Stub {
+        fn start(script: Vec<(u16, &'static str)>, delay: Duration) -> Stub {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let peak = Arc::new(AtomicUsize::new(0));
+            let active = Arc::new(AtomicUsize::new(0));
+            let (log, max) = (Arc::clone(&requests), Arc::clone(&peak));
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let (log, max, active, script) = (Arc::clone(&log), Arc::clone(&max), Arc::clone(&active), script.clone());
+                    thread::spawn(move || {
+                        let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                        max.fetch_max(now, Ordering::SeqCst);
+                        let body = read_request(&stream);
+                        let n = {
+                            let mut log = log.lock().unwrap();
+                            log.push(body);
+                            log.len() - 1
+                        };
+                        thread::sleep(delay);
+                        let (status, reply) = script[n.min(script.len() - 1)];
+                        active.fetch_sub(1, Ordering::SeqCst);
+                        if reply == "hang up" {
+                            return;
+                        }
+                        let mut stream = stream;
+                        let _ = write!(
+                            stream,
+                            "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            status,
+                            // "truncated" promises more than it sends, so reading the body fails
+                            if reply == "truncated" { 1000 } else { reply.len() },
+                            reply
+                        );
+                    });
+                }
+            });
+            Stub { url, requests, peak }
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    fn read_request(stream: &TcpStream) -> String {
+        let mut reader = BufReader::new(stream);
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                break;
+            }
+            if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                length = v.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    fn sink(url: &str, name: &str, concurrency: usize, max_retries: u32) -> EsSink {
+        let dir = scratch(&format!("es-sink-{}", name));
+        EsSink::new(EsSinkConfig {
+            url: url.to_string(),
+            max_batch_bytes: DEFAULT_BATCH_BYTES,
+            concurrency,
+            max_retries,
+            dead_letter_dir: dir,
+            username: None,
+            password: None,
+        })
+    }
+
+    fn push_docs(stream: &mut EsStream, ids: &[&str]) {
+        for id in ids {
+            stream.push(format!("{{\"index\":{{\"_id\":\"{}\"}}}}\n", id).as_bytes(), b"{\"price\":1.0}\n");
+        }
+    }
+
+    fn dead_letters(sink: &EsSink, stem: &str) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(sink.dead_letter_dir.join(format!("{}.deadletter.ndjson", stem)))
+            .unwrap_or_default()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    const OK_2: &str = r#"{"errors":false,"items":[{"index":{"status":201}},{"index":{"status":201}}]}"#;
+
+    #[test]
+    fn too_many_requests_is_retried() {
+        let stub = Stub::start(vec![(429, "slow down"), (200, OK_2)], Duration::ZERO);
+        let sink = sink(&stub.url, "429", 1, 3);
+        let mut stream = sink.stream("a");
+        push_docs(&mut stream, &["1", "2"]);
+        let counts = stream.finish().unwrap();
+
+        assert_eq!(counts, EsCounts { indexed: 2, failed: 0, unconfirmed: 0 });
+        let requests = stub.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0], requests[1]);
+    }
+
+    #[test]
+    fn server_errors_are_retried_then_dead_lettered() {
+        let stub = Stub::start(vec![(503, "unavailable")], Duration::ZERO);
+        let sink = sink(&stub.url, "503", 1, 2);
+        let mut stream = sink.stream("a");
+        push_docs(&mut stream, &["1", "2"]);
+        let counts = stream.finish().unwrap();
+
+        assert_eq!(counts, EsCounts { indexed: 0, failed: 2, unconfirmed: 0 });
+        assert_eq!(stub.requests().len(), 3);
+        let letters = dead_letters(&sink, "a");
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0]["status"], 503);
+        assert_eq!(letters[1]["action"]["index"]["_id"], "2");
+    }
+
+    #[test]
+    fn item_errors_are_retried_or_dead_lettered_one_by_one() {
+        let stub = Stub::start(
+            vec![
+                (
+                    200,
+                    r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":400,"error":{"type":"mapper_parsing_exception"}}},{"index":{"status":429}}]}"#,
+                ),
+                (200, r#"{"errors":false,"items":[{"index":{"status":201}}]}"#),
+            ],
+            Duration::ZERO,
+        );
+        let sink = sink(&stub.url, "items", 1, 3);
+        let mut stream = sink.stream("a");
+        push_docs(&mut stream, &["1", "2", "3"]);
+        let counts = stream.finish().unwrap();
+
+        assert_eq!(counts, EsCounts { indexed: 2, failed: 1, unconfirmed: 0 });
+        let requests = stub.requests();
+        assert_eq!(requests.len(), 2);
+        // Only the item rejected with 429 is sent again
+        assert!(requests[1].contains(r#""_id":"3""#) && !requests[1].contains(r#""_id":"1""#));
+        let letters = dead_letters(&sink, "a");
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0]["action"]["index"]["_id"], "2");
+        assert_eq!(letters[0]["error"]["type"], "mapper_parsing_exception");
+    }
+
+    #[test]
+    fn unreadable_success_is_unconfirmed_not_resent() {
+        for reply in ["<html>proxy</html>", "truncated"] {
+            let stub = Stub::start(vec![(200, reply)], Duration::ZERO);
+            let sink = sink(&stub.url, "unconfirmed", 1, 3);
+            let mut stream = sink.stream("a");
+            push_docs(&mut stream, &["1", "2"]);
+            let counts = stream.finish().unwrap();
+
+            assert_eq!(counts, EsCounts { indexed: 0, failed: 0, unconfirmed: 2 }, "{}", reply);
+            assert_eq!(stub.requests().len(), 1, "{}", reply);
+            assert!(dead_letters(&sink, "a").is_empty(), "{}", reply);
+        }
+    }
+
+    #[test]
+    fn lost_answer_is_unconfirmed_not_resent() {
+        let stub = Stub::start(vec![(200, "hang up"), (200, OK_2)], Duration::ZERO);
+        let sink = sink(&stub.url, "hang-up", 1, 3);
+        let mut stream = sink.stream("a");
+        push_docs(&mut stream, &["1", "2"]);
+        let counts = stream.finish().unwrap();
+
+        assert_eq!(counts, EsCounts { indexed: 0, failed: 0, unconfirmed: 2 });
+        assert_eq!(stub.requests().len(), 1);
+        assert!(dead_letters(&sink, "a").is_empty());
+    }
+
+    #[test]
+    fn refused_connection_is_retried_then_dead_lettered() {
+        // Nothing listens on a port just released
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let sink = sink(&url, "refused", 1, 1);
+        let mut stream = sink.stream("a");
+        push_docs(&mut stream, &["1", "2"]);
+        let counts = stream.finish().unwrap();
+
+        assert_eq!(counts, EsCounts { indexed: 0, failed: 2, unconfirmed: 0 });
+        let letters = dead_letters(&sink, "a");
+        assert_eq!(letters.len(), 2);
+        assert!(letters[0]["error"].as_str().unwrap().starts_with("transport error"));
+    }
+
+    #[test]
+    fn concurrent_posts_are_capped_by_the_semaphore() {
+        let stub = Stub::start(
+            vec![(200, r#"{"errors":false,"items":[{"index":{"status":201}}]}"#)],
+            Duration::from_millis(100),
+        );
+        let sink = sink(&stub.url, "semaphore", 2, 0);
+        thread::scope(|scope| {
+            for t in 0..6 {
+                let sink = &sink;
+                scope.spawn(move || {
+                    let mut stream = sink.stream(&format!("f{}", t));
+                    push_docs(&mut stream, &["1"]);
+                    assert_eq!(stream.finish().unwrap().indexed, 1);
+                });
+            }
+        });
+        assert_eq!(stub.requests().len(), 6);
+        assert_eq!(stub.peak.load(Ordering::SeqCst), 2);
+    }
+}
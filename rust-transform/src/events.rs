@@ -0,0 +1,296 @@
+// =============================================================================
+// Machine-readable events — one JSON object per line, for turboSyncEngine.js
+// =============================================================================
+// Every event carries its name and the schema version first:
+//
+//   {"event":"file_done","v":1,"file":"a \"quoted\".csv","records":42,...}
+//
+// SCHEMA_VERSION only goes up for breaking changes (a field renamed, removed
+// or retyped); new events and new fields are added without a bump, so
+// consumers must ignore what they don't know. All strings go through serde,
+// so file names, paths and index names are always escaped correctly.
+// =============================================================================
+
+use crate::quality::QualityReport;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Before the first file is opened (stderr)
+    Start {
+        files: usize,
+        total_bytes: u64,
+        threads: usize,
+        input_dir: String,
+        output_dir: String,
+    },
+    /// A file's outputs are complete and can be imported (stderr)
+    FileDone {
+        file: String,
+        /// The CSV path as given to the run
+        input: String,
+        /// Boxed: the largest part of the largest event
+        outputs: Box<FileOutputs>,
+        records: u64,
+        /// Size of the CSV
+        input_bytes: u64,
+        /// Data rows read, and those that produced no record
+        rows: u64,
+        rejected: RowRejects,
+        ndjson_bytes: u64,
+        bson_bytes: u64,
+        bulk_bytes: u64,
+        es_indexed: u64,
+        es_failed: u64,
+        /// Sent to ES, but the response could not be read to confirm them
+        es_unconfirmed: u64,
+        parquet_bytes: u64,
+        duration_ms: u64,
+        rate_per_sec: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        quality: Option<QualityReport>,
+        /// "done/total"
+        progress: String,
+    },
+    /// Before the first file: inputs whose outputs would have shared a stem
+    /// (equal ignoring case, or once flattened for Parquet) and the stems
+    /// they were given instead (stderr)
+    StemCollision {
+        stem: String,
+        files: Vec<String>,
+        stems: Vec<String>,
+    },
+    /// Strict mode: a file failed its quality thresholds; outputs deleted (stderr)
+    FileRejected {
+        file: String,
+        records: u64,
+        duration_ms: u64,
+        quality: QualityReport,
+        rejected: RowRejects,
+        progress: String,
+    },
+    /// A file could not be read, or one of its outputs failed (stderr)
+    FileFailed {
+        file: String,
+        error: String,
+        duration_ms: u64,
+    },
+    /// A thread panicked; on a file's thread, that file's file_failed follows
+    /// (stderr, from the CLI's panic hook)
+    Panic {
+        thread: String,
+        message: String,
+        /// file:line:column of the panic
+        location: Option<String>,
+    },
+    /// Cancelled while being read; its outputs were deleted (stderr)
+    FileCancelled { file: String, records: u64 },
+    /// Resumed run: already complete in the checkpoint, not read again;
+    /// `outputs` are the files the earlier run wrote (stderr)
+    FileSkipped {
+        file: String,
+        records: u64,
+        outputs: FileOutputs,
+    },
+    /// A numbered .bulk.NNN chunk is closed and can be streamed (stderr)
+    BulkChunk {
+        file: String,
+        chunk: usize,
+        path: String,
+        docs: u64,
+        bytes: u64,
+    },
+    /// Heartbeat every progress interval while files are read (stderr)
+    Progress {
+        elapsed_ms: u64,
+        /// Over all files; finished files count as fully read
+        bytes_read: u64,
+        bytes_total: u64,
+        records: u64,
+        rate_per_sec: u64,
+        /// null until the first bytes are read
+        eta_ms: Option<u64>,
+        files_done: usize,
+        files_total: usize,
+        /// Files currently being read
+        files: Vec<FileProgressInfo>,
+    },
+    /// parts_best_offer.ndjson is written (stderr)
+    BestOffer {
+        path: String,
+        groups: u64,
+        offers: u64,
+        bytes: u64,
+    },
+    /// <run>.profile.json is written (stderr)
+    Profile { path: String, files: usize },
+    /// `stream` summary — stdout carries the data (stderr)
+    StreamDone {
+        file: String,
+        format: String,
+        records: u64,
+        rows: u64,
+        rejected: u64,
+        bytes_written: u64,
+        duration_ms: u64,
+        rate_per_sec: u64,
+    },
+    /// Run summary (stdout, last line)
+    Complete {
+        total_records: u64,
+        total_ndjson_bytes: u64,
+        total_bson_bytes: u64,
+        total_bulk_bytes: u64,
+        total_es_indexed: u64,
+        total_es_failed: u64,
+        total_es_unconfirmed: u64,
+        total_parquet_bytes: u64,
+        total_input_bytes: u64,
+        duration_ms: u64,
+        rate_per_sec: u64,
+        files_processed: usize,
+        files_total: usize,
+        errors: usize,
+        /// Complete in the checkpoint of a resumed run
+        files_skipped: usize,
+        /// Not started or rolled back because the run was cancelled
+        files_cancelled: usize,
+        cancelled: bool,
+        threads: usize,
+        es_index: String,
+    },
+}
+
+/// Files written for one input, in `file_done` (and in the checkpoint)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileOutputs {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ndjson: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bson: Option<String>,
+    /// <stem>.metadata.json, which mongorestore reads with <stem>.bson
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bson_metadata: Option<String>,
+    /// <stem>.bulk, or its .bulk.NNN chunks in order; empty with an ES sink
+    pub bulk: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parquet: Vec<String>,
+}
+
+/// Rows that produced no record, by reason
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct RowRejects {
+    /// The CSV parser rejected the row (invalid UTF-8, I/O errors)
+    pub malformed: u64,
+    /// The part number column was empty
+    pub missing_part_number: u64,
+}
+
+/// One open file in a `progress` event
+#[derive(Clone, Debug, Serialize)]
+pub struct FileProgressInfo {
+    pub file: String,
+    pub bytes_read: u64,
+    pub bytes_total: u64,
+    pub records: u64,
+    pub rate_per_sec: u64,
+    pub eta_ms: Option<u64>,
+}
+
+impl Event {
+    /// The event as one JSON line (no trailing newline), `event` and `v` first
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+
+    /// The event as a JSON object, `event` and `v` first
+    pub fn to_value(&self) -> Value {
+        let Ok(Value::Object(fields)) = serde_json::to_value(self) else {
+            unreachable!("events serialise to JSON objects");
+        };
+        let mut line = Map::with_capacity(fields.len() + 1);
+        let mut fields = fields.into_iter();
+        if let Some((tag, name)) = fields.next() {
+            line.insert(tag, name);
+        }
+        line.insert("v".into(), SCHEMA_VERSION.into());
+        line.extend(fields);
+        Value::Object(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use crate::{discover, MappingConfig, Transformer};
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    /// Quotes, a backslash, control characters and a non-UTF-8 byte
+    const AWKWARD: &[u8] = b"a \"quoted\" \\ name\n\t\x01\xff_AB4_part1.csv";
+
+    fn file_failed(file: &str) -> Event {
+        Event::FileFailed { file: file.to_string(), error: format!("open {} failed", file), duration_ms: 3 }
+    }
+
+    #[test]
+    fn names_are_escaped_and_v_comes_second() {
+        let name = String::from_utf8_lossy(AWKWARD).into_owned();
+        let line = file_failed(&name).to_json();
+        assert!(line.starts_with(r#"{"event":"file_failed","v":1,"file":"a \"quoted\" \\ name\n\t\u0001"#), "{}", line);
+        assert!(!line.contains('\n') && !line.contains('\t'), "one line: {}", line);
+
+        let back: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(back["file"], name.as_str());
+        assert_eq!(back["v"], SCHEMA_VERSION);
+        assert_eq!(SCHEMA_VERSION, 1);
+        // The non-UTF-8 byte became U+FFFD, so the line is valid UTF-8 JSON
+        assert!(name.contains('\u{fffd}'));
+    }
+
+    #[test]
+    fn every_event_of_a_run_over_an_awkward_name_parses() {
+        let dir = scratch("events");
+        let (input, output) = (dir.join("in"), dir.join("out"));
+        fs::create_dir_all(&input).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        let csv = input.join(OsStr::from_bytes(AWKWARD));
+        fs::write(&csv, "part_number;price\nP1;1,5\n").unwrap();
+
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let seen = lines.clone();
+        let summary = Transformer::builder(MappingConfig::new("", ""), &output)
+            .input_root(&input)
+            .on_event(move |e| seen.lock().unwrap().push(e.to_json()))
+            .build()
+            .unwrap()
+            .run(std::slice::from_ref(&csv));
+        assert_eq!(summary.files[0].error, None);
+
+        let expected = discover::relative_name(Some(&input), &csv);
+        assert_eq!(expected, String::from_utf8_lossy(AWKWARD));
+        let lines = lines.lock().unwrap();
+        assert!(!lines.is_empty());
+        for line in lines.iter() {
+            let event: Value = serde_json::from_str(line).unwrap();
+            let keys: Vec<&String> = event.as_object().unwrap().keys().take(2).collect();
+            assert_eq!(keys, ["event", "v"]);
+            assert_eq!(event["v"], 1);
+            if let Some(file) = event.get("file") {
+                assert_eq!(file, expected.as_str());
+            }
+        }
+        assert!(lines.iter().any(|l| l.starts_with(r#"{"event":"file_done","v":1,"#)));
+        assert!(Path::new(&summary.files[0].outputs.ndjson.clone().unwrap()).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
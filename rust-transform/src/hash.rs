@@ -0,0 +1,34 @@
+// =============================================================================
+// Byte hash — one stable 64-bit hash for everything that derives a name or id
+// =============================================================================
+// Used for the suffix of colliding output stems, the tail of over-long ES
+// _ids and the profile's HyperLogLog. Stems and _ids are persisted (output
+// file names, documents in ES), so the function must never change: the same
+// bytes hash the same in every version and on every platform.
+// =============================================================================
+
+/// FNV-1a with a splitmix64 finaliser — fast, and well spread for HLL
+pub(crate) fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h ^= h >> 30;
+    h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h ^= h >> 27;
+    h = h.wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output stems and ES _ids already written depend on these
+    #[test]
+    fn hashes_never_change() {
+        assert_eq!(hash_bytes(b""), 0xf52a_15e9_a9b5_e89b);
+        assert_eq!(hash_bytes(b"parts.csv"), 0xdc87_9550_9fde_5553);
+    }
+}
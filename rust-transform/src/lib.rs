@@ -0,0 +1,76 @@
+// =============================================================================
+// turbo_transform — supplier CSV → PartsForm records, as a library
+// =============================================================================
+// The turbo-transform binary is a thin CLI over this crate; other tools can
+// use the same column mapping, normalisation and outputs directly.
+//
+//   Records      CSV reader → PartRecord (borrowed) or Part (owned)
+//   ColumnMap    header heuristics + row normalisation
+//   parse        field-level helpers (prices, quantities, delivery days)
+//   Transformer  builder-configured parallel run: files → NDJSON/BSON,
+//                ES _bulk (file or live cluster), Parquet, best offers
+//   es_sink      _bulk bodies straight to ES/OpenSearch, retries, dead letters
+//   bulk_action  the _bulk action line: op, _id, routing, pipeline, version
+//   bson_out     mongorestore-ready .bson instead of NDJSON
+//   parquet_sink partitioned Parquet copy of every record
+//   best_offer   one winning offer per part across stock-code files
+//   schema       ES index mapping / JSON Schema derived from PartRecord
+//   quality      per-file reject/zero-price/empty-brand/duplicate rates
+//   profile      per-file, per-field fill rate / distinct / min-max / top values
+//   checkpoint   completed files of a run, for cancel + a manual --resume
+//   metrics      Prometheus counters/histograms from the events, /metrics or textfile
+//   discover     input files: recursive walk, include/exclude globs, file lists
+//   stream       one CSV reader → NDJSON/BSON/_bulk writer, no files involved
+//   watch        inotify on a drop directory: which files finished arriving
+// =============================================================================
+
+//! Supplier CSV → PartsForm records: the column mapping, normalisation and
+//! outputs behind the `turbo-transform` CLI.
+//!
+//! ```no_run
+//! use std::path::PathBuf;
+//! use turbo_transform::{MappingConfig, Transformer};
+//!
+//! # fn main() -> Result<(), String> {
+//! let mapping = MappingConfig::new("507f1f77bcf86cd799439011", "APMG");
+//! let transformer = Transformer::builder(mapping, "out/")
+//!     .es_index("automotive_parts")
+//!     .on_event(|e| eprintln!("{}", e.to_json()))
+//!     .build()?;
+//! let summary = transformer.run(&[PathBuf::from("in/prices_AB4_part1.csv")]);
+//! println!("{} files", summary.files.len());
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod best_offer;
+pub mod bson_out;
+pub mod bulk_action;
+mod bulk_file;
+pub mod checkpoint;
+pub mod discover;
+pub mod es_sink;
+pub mod events;
+mod hash;
+pub mod mapping;
+pub mod metrics;
+pub mod parquet_sink;
+pub mod parse;
+pub mod profile;
+pub mod quality;
+mod reader;
+mod record;
+pub mod schema;
+pub mod stream;
+#[cfg(test)]
+mod test_util;
+pub mod timestamp;
+mod transform;
+pub mod watch;
+
+pub use bulk_file::BulkLimits;
+pub use events::Event;
+pub use mapping::{ColumnMap, FileContext, MappingConfig};
+pub use reader::{Records, RowStats};
+pub use record::{Part, PartRecord, PartRecordES, RecordJson};
+pub use transform::{panic_message, EventHandler, FileResult, RunSummary, Transformer, TransformerBuilder};
@@ -0,0 +1,291 @@
+// =============================================================================
+// Column mapping + record normalisation — CSV row → PartRecord
+// =============================================================================
+// Header names are matched heuristically (supplier exports never agree on
+// "Vendor Code" vs "SKU" vs "Part #"), then every row is normalised the same
+// way for every output: empty currency → AED, weight unit → kg, stock →
+// unknown, minOrderQty at least 1, stock code from the filename when the
+// row has none.
+// =============================================================================
+
+use crate::parse::{extract_stock_code_from_filename, get_bytes, get_field, parse_delivery, parse_f64, parse_i64};
+use crate::record::PartRecord;
+use crate::timestamp::{epoch_millis_now, iso8601};
+
+/// Run-wide values stamped on every record
+#[derive(Clone, Debug)]
+pub struct MappingConfig {
+    /// MongoDB ObjectId of the integration (may be empty)
+    pub integration_id: String,
+    pub integration_name: String,
+    /// importedAt, in milliseconds since the epoch
+    pub imported_at_millis: u64,
+}
+
+impl MappingConfig {
+    /// Config stamped with the current time
+    pub fn new(integration_id: &str, integration_name: &str) -> Self {
+        MappingConfig {
+            integration_id: integration_id.to_string(),
+            integration_name: integration_name.to_string(),
+            imported_at_millis: epoch_millis_now(),
+        }
+    }
+
+    /// importedAt as written to NDJSON
+    pub fn imported_at(&self) -> String {
+        iso8601(self.imported_at_millis)
+    }
+}
+
+/// Per-file values stamped on every record of one CSV
+#[derive(Clone, Debug)]
+pub struct FileContext {
+    pub integration_id: String,
+    pub integration_name: String,
+    pub file_name: String,
+    /// Used when the row has no stock code ("" if the name has none either)
+    pub filename_stock_code: String,
+    pub imported_at: String,
+}
+
+impl FileContext {
+    pub fn new(config: &MappingConfig, file_name: &str) -> Self {
+        FileContext {
+            integration_id: config.integration_id.clone(),
+            integration_name: config.integration_name.clone(),
+            file_name: file_name.to_string(),
+            filename_stock_code: extract_stock_code_from_filename(file_name).to_string(),
+            imported_at: config.imported_at(),
+        }
+    }
+}
+
+// =============================================================================
+// Column mapping — resolved once per file from header row
+// =============================================================================
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ColumnMap {
+    pub part_number: Option<usize>,
+    pub description: Option<usize>,
+    pub brand: Option<usize>,
+    pub supplier: Option<usize>,
+    pub price: Option<usize>,
+    pub currency: Option<usize>,
+    pub quantity: Option<usize>,
+    pub min_order_qty: Option<usize>,
+    pub stock: Option<usize>,
+    pub stock_code: Option<usize>,
+    pub weight: Option<usize>,
+    pub weight_unit: Option<usize>,
+    pub volume: Option<usize>,
+    pub delivery_days: Option<usize>,
+    pub category: Option<usize>,
+    pub subcategory: Option<usize>,
+}
+
+/// Normalised header text the rules below are written against
+fn normalise_header(h: &str) -> String {
+    h.trim().to_ascii_lowercase().trim_matches(|c: char| c == '"' || c == '\'').to_string()
+}
+
+/// Whether a normalised header names `field`. Rules are checked in
+/// ColumnMap::fields() order and a header is taken by the first free field
+/// it matches, so "stock code" never lands on stock.
+fn header_matches(field: &str, h: &str) -> bool {
+    match field {
+        // Part number — highest priority match
+        "partNumber" => {
+            h.contains("vendor code")
+                || h.contains("vendor_code")
+                || h == "partnumber"
+                || h == "part number"
+                || h == "part_number"
+                || h == "sku"
+                || h == "code"
+                || h == "item number"
+                || h == "item #"
+                || h == "product code"
+                || h == "part #"
+        }
+        "description" => h.contains("title") || h.contains("desc") || h == "name" || h == "product name",
+        "brand" => h.contains("brand") || h == "manufacturer" || h == "make" || h == "mfr",
+        "supplier" => h.contains("supplier"),
+        "price" => h.contains("price") || h.contains("cost"),
+        "currency" => h.contains("currency") || h.contains("curr") || h == "aed" || h == "usd",
+        "quantity" => h == "quantity" || h == "qty",
+        "minOrderQty" => {
+            h.contains("min_lot")
+                || h.contains("min lot")
+                || h.contains("minorder")
+                || h.contains("min_order")
+                || h == "moq"
+                || h == "minimum order"
+        }
+        // stock vs stock_code disambiguation
+        "stock" => h == "stock",
+        "stockCode" => {
+            h.contains("stock code") || h.contains("stock_code") || h.contains("stockcode") || h == "warehouse"
+        }
+        "weight" => h == "weight",
+        "weightUnit" => h.contains("weight_unit") || h.contains("weightunit"),
+        "volume" => h.contains("volume") || h == "vol",
+        "deliveryDays" => h.contains("delivery") || h.contains("lead_time") || h.contains("leadtime"),
+        "category" => h == "category" || h == "cat",
+        "subcategory" => h.contains("subcategory") || h.contains("subcat") || h.contains("sub_category"),
+        _ => false,
+    }
+}
+
+impl ColumnMap {
+    /// Resolve columns from a header row by name heuristics (first match wins)
+    pub fn from_headers(headers: &csv::StringRecord) -> Self {
+        let mut map = ColumnMap::default();
+        for (i, h) in headers.iter().enumerate() {
+            let h = normalise_header(h);
+            for (field, column) in map.fields() {
+                if column.is_none() && header_matches(field, &h) {
+                    *map.slot_mut(field) = Some(i);
+                    break;
+                }
+            }
+        }
+        map
+    }
+
+    /// Every field a header would map to if it were still free, in priority order
+    pub fn candidates(header: &str) -> Vec<&'static str> {
+        let h = normalise_header(header);
+        ColumnMap::default()
+            .fields()
+            .into_iter()
+            .map(|(field, _)| field)
+            .filter(|field| header_matches(field, &h))
+            .collect()
+    }
+
+    fn slot_mut(&mut self, field: &str) -> &mut Option<usize> {
+        match field {
+            "partNumber" => &mut self.part_number,
+            "description" => &mut self.description,
+            "brand" => &mut self.brand,
+            "supplier" => &mut self.supplier,
+            "price" => &mut self.price,
+            "currency" => &mut self.currency,
+            "quantity" => &mut self.quantity,
+            "minOrderQty" => &mut self.min_order_qty,
+            "stock" => &mut self.stock,
+            "stockCode" => &mut self.stock_code,
+            "weight" => &mut self.weight,
+            "weightUnit" => &mut self.weight_unit,
+            "volume" => &mut self.volume,
+            "deliveryDays" => &mut self.delivery_days,
+            "category" => &mut self.category,
+            "subcategory" => &mut self.subcategory,
+            _ => unreachable!("unknown ColumnMap field {}", field),
+        }
+    }
+
+    /// The column a record field was resolved from
+    pub fn column_of(&self, field: &str) -> Option<usize> {
+        self.fields().into_iter().find(|&(f, _)| f == field).and_then(|(_, c)| c)
+    }
+}
+
+impl ColumnMap {
+    /// (PartRecord field name, resolved column) pairs, in record order
+    pub fn fields(&self) -> [(&'static str, Option<usize>); 16] {
+        [
+            ("partNumber", self.part_number),
+            ("description", self.description),
+            ("brand", self.brand),
+            ("supplier", self.supplier),
+            ("price", self.price),
+            ("currency", self.currency),
+            ("quantity", self.quantity),
+            ("minOrderQty", self.min_order_qty),
+            ("stock", self.stock),
+            ("stockCode", self.stock_code),
+            ("weight", self.weight),
+            ("weightUnit", self.weight_unit),
+            ("volume", self.volume),
+            ("deliveryDays", self.delivery_days),
+            ("category", self.category),
+            ("subcategory", self.subcategory),
+        ]
+    }
+
+    /// The record field a column feeds, if any
+    pub fn field_of(&self, column: usize) -> Option<&'static str> {
+        self.fields()
+            .into_iter()
+            .find(|&(_, c)| c == Some(column))
+            .map(|(name, _)| name)
+    }
+
+    /// Normalise one row. None when the row has no part number.
+    pub fn map_row<'r>(&self, row: &'r csv::ByteRecord, ctx: &'r FileContext) -> Option<PartRecord<'r>> {
+        let part_number = get_field(row, self.part_number);
+        if part_number.is_empty() {
+            return None;
+        }
+
+        // Resolve stock code: column value > filename extraction
+        let raw_stock_code = get_field(row, self.stock_code);
+        let stock_code = if raw_stock_code.is_empty() {
+            ctx.filename_stock_code.as_str()
+        } else {
+            raw_stock_code
+        };
+
+        let currency_raw = get_field(row, self.currency);
+        let currency = if currency_raw.is_empty() {
+            "AED"
+        } else {
+            currency_raw
+        };
+
+        let weight_unit_raw = get_field(row, self.weight_unit);
+        let weight_unit = if weight_unit_raw.is_empty() {
+            "kg"
+        } else {
+            weight_unit_raw
+        };
+
+        let stock_raw = get_field(row, self.stock);
+        let stock = if stock_raw.is_empty() {
+            "unknown"
+        } else {
+            stock_raw
+        };
+
+        let min_order_raw = parse_i64(get_bytes(row, self.min_order_qty));
+        let min_order_qty = if min_order_raw < 1 { 1 } else { min_order_raw };
+
+        let delivery_str = parse_delivery(get_field(row, self.delivery_days));
+
+        Some(PartRecord {
+            part_number,
+            description: get_field(row, self.description),
+            brand: get_field(row, self.brand),
+            supplier: get_field(row, self.supplier),
+            price: parse_f64(get_bytes(row, self.price)),
+            currency,
+            quantity: parse_i64(get_bytes(row, self.quantity)),
+            min_order_qty,
+            stock,
+            stock_code,
+            weight: parse_f64(get_bytes(row, self.weight)),
+            weight_unit,
+            volume: parse_f64(get_bytes(row, self.volume)),
+            delivery_time: delivery_str,
+            delivery_days: delivery_str,
+            category: get_field(row, self.category),
+            subcategory: get_field(row, self.subcategory),
+            integration: &ctx.integration_id,
+            integration_name: &ctx.integration_name,
+            file_name: &ctx.file_name,
+            imported_at: &ctx.imported_at,
+        })
+    }
+}
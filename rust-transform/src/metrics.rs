@@ -0,0 +1,524 @@
+// =============================================================================
+// Prometheus metrics — counters and histograms built from the run's events
+// =============================================================================
+// Metrics::observe takes every Event of a run (it is an on_event observer, so
+// the library itself counts nothing twice) and keeps one set of series per
+// integration. render() gives the text exposition format, for
+//
+//   serve(addr)            GET /metrics on a local port while runs are going
+//   write_textfile(path)   a node_exporter textfile collector file (*.prom)
+//
+// Counters add up over every run this process makes (`watch` batches,
+// `serve` jobs). Runs are told apart by an id the caller picks (the job id in
+// `serve`), so concurrent runs of one integration each count: the
+// turbo_transform_run_* gauges add up the runs in progress.
+// =============================================================================
+
+use crate::events::Event;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write as _};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Upper bounds (seconds) of the file duration histogram buckets
+const DURATION_BUCKETS: [f64; 11] = [0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0];
+
+/// How long a scrape may take to send its whole request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct Histogram {
+    /// Per bucket, not cumulative; the last one is +Inf
+    counts: [u64; DURATION_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        let bucket = DURATION_BUCKETS.iter().position(|&le| value <= le).unwrap_or(DURATION_BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Everything known about one integration
+#[derive(Default)]
+struct Series {
+    records: u64,
+    rows: u64,
+    /// By reason
+    rows_rejected: BTreeMap<&'static str, u64>,
+    input_bytes: u64,
+    /// By format
+    output_bytes: BTreeMap<&'static str, u64>,
+    /// By outcome
+    files: BTreeMap<&'static str, u64>,
+    /// By rule
+    quality_violations: BTreeMap<&'static str, u64>,
+    /// By result
+    es_docs: BTreeMap<&'static str, u64>,
+    file_duration: Histogram,
+    /// By outcome
+    runs: BTreeMap<&'static str, u64>,
+    /// Runs in progress, by the caller's run id
+    active: BTreeMap<u64, RunProgress>,
+    last_run_duration_seconds: f64,
+}
+
+/// Where a run in progress is
+#[derive(Default)]
+struct RunProgress {
+    bytes_read: u64,
+    bytes_total: u64,
+    files_done: usize,
+    files_total: usize,
+    records_per_second: u64,
+}
+
+fn add(counts: &mut BTreeMap<&'static str, u64>, key: &'static str, n: u64) {
+    *counts.entry(key).or_insert(0) += n;
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    /// By integration id
+    series: Mutex<BTreeMap<String, Series>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics::default()
+    }
+
+    /// Count one event of run `run` for `integration`; `run` only has to
+    /// differ between runs that overlap
+    pub fn observe(&self, integration: &str, run: u64, event: &Event) {
+        let mut all = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let s = all.entry(integration.to_string()).or_default();
+        match event {
+            Event::Start { files, total_bytes, .. } => {
+                let progress = RunProgress {
+                    bytes_total: *total_bytes,
+                    files_total: *files,
+                    ..RunProgress::default()
+                };
+                s.active.insert(run, progress);
+            }
+            Event::FileDone {
+                records,
+                input_bytes,
+                rows,
+                rejected,
+                ndjson_bytes,
+                bson_bytes,
+                bulk_bytes,
+                es_indexed,
+                es_failed,
+                es_unconfirmed,
+                parquet_bytes,
+                duration_ms,
+                ..
+            } => {
+                s.records += records;
+                s.rows += rows;
+                add(&mut s.rows_rejected, "malformed", rejected.malformed);
+                add(&mut s.rows_rejected, "missing_part_number", rejected.missing_part_number);
+                s.input_bytes += input_bytes;
+                add(&mut s.output_bytes, "ndjson", *ndjson_bytes);
+                add(&mut s.output_bytes, "bson", *bson_bytes);
+                add(&mut s.output_bytes, "bulk", *bulk_bytes);
+                add(&mut s.output_bytes, "parquet", *parquet_bytes);
+                add(&mut s.es_docs, "indexed", *es_indexed);
+                add(&mut s.es_docs, "failed", *es_failed);
+                add(&mut s.es_docs, "unconfirmed", *es_unconfirmed);
+                add(&mut s.files, "done", 1);
+                s.file_duration.observe(*duration_ms as f64 / 1000.0);
+            }
+            Event::FileRejected {
+                quality,
+                rejected,
+                duration_ms,
+                ..
+            } => {
+                s.rows += quality.rows;
+                add(&mut s.rows_rejected, "malformed", rejected.malformed);
+                add(&mut s.rows_rejected, "missing_part_number", rejected.missing_part_number);
+                for v in &quality.violations {
+                    add(&mut s.quality_violations, v.rule, 1);
+                }
+                add(&mut s.files, "rejected", 1);
+                s.file_duration.observe(*duration_ms as f64 / 1000.0);
+            }
+            Event::FileFailed { .. } => add(&mut s.files, "failed", 1),
+            Event::FileCancelled { .. } => add(&mut s.files, "cancelled", 1),
+            Event::FileSkipped { .. } => add(&mut s.files, "skipped", 1),
+            Event::Progress {
+                bytes_read,
+                files_done,
+                rate_per_sec,
+                ..
+            } => {
+                if let Some(p) = s.active.get_mut(&run) {
+                    p.bytes_read = *bytes_read;
+                    p.files_done = *files_done;
+                    p.records_per_second = *rate_per_sec;
+                }
+            }
+            Event::Complete { duration_ms, cancelled, .. } => {
+                s.active.remove(&run);
+                s.last_run_duration_seconds = *duration_ms as f64 / 1000.0;
+                add(&mut s.runs, if *cancelled { "cancelled" } else { "complete" }, 1);
+            }
+            _ => {}
+        }
+    }
+
+    /// End run `run` that stopped without a Complete event (it panicked)
+    pub fn abandon(&self, integration: &str, run: u64) {
+        let mut all = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let s = all.entry(integration.to_string()).or_default();
+        if s.active.remove(&run).is_some() {
+            add(&mut s.runs, "failed", 1);
+        }
+    }
+
+    /// Every series in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let all = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+
+        let mut counter = |name: &str, help: &str, value: &dyn Fn(&Series) -> u64| {
+            family(&mut out, name, "counter", help);
+            for (integration, s) in all.iter() {
+                sample(&mut out, name, &[("integration", integration)], value(s));
+            }
+        };
+        counter("turbo_transform_records_total", "Records written by completed files", &|s| s.records);
+        counter("turbo_transform_rows_total", "CSV data rows read by completed and rejected files", &|s| s.rows);
+        counter("turbo_transform_input_bytes_total", "CSV bytes of completed files", &|s| s.input_bytes);
+
+        let mut labelled = |name: &str, help: &str, label: &str, counts: &dyn Fn(&Series) -> &BTreeMap<&'static str, u64>| {
+            family(&mut out, name, "counter", help);
+            for (integration, s) in all.iter() {
+                for (key, n) in counts(s) {
+                    sample(&mut out, name, &[("integration", integration), (label, key)], n);
+                }
+            }
+        };
+        labelled("turbo_transform_rows_rejected_total", "Rows that produced no record", "reason", &|s| &s.rows_rejected);
+        labelled("turbo_transform_output_bytes_total", "Bytes written by completed files", "format", &|s| &s.output_bytes);
+        labelled("turbo_transform_files_total", "Files finished, by outcome", "outcome", &|s| &s.files);
+        labelled(
+            "turbo_transform_quality_violations_total",
+            "Quality thresholds failed by rejected files (strict mode)",
+            "rule",
+            &|s| &s.quality_violations,
+        );
+        labelled("turbo_transform_es_docs_total", "Documents sent to the ES sink, by result", "result", &|s| &s.es_docs);
+        labelled("turbo_transform_runs_total", "Runs finished, by outcome", "outcome", &|s| &s.runs);
+
+        let name = "turbo_transform_file_duration_seconds";
+        family(&mut out, name, "histogram", "Time to transform one file (completed and rejected files)");
+        for (integration, s) in all.iter() {
+            let h = &s.file_duration;
+            let mut cumulative = 0;
+            for (i, n) in h.counts.iter().enumerate() {
+                cumulative += n;
+                let le = DURATION_BUCKETS.get(i).map_or("+Inf".to_string(), |b| b.to_string());
+                sample(&mut out, &format!("{}_bucket", name), &[("integration", integration), ("le", &le)], cumulative);
+            }
+            sample(&mut out, &format!("{}_sum", name), &[("integration", integration)], h.sum);
+            sample(&mut out, &format!("{}_count", name), &[("integration", integration)], h.count);
+        }
+
+        let mut gauge = |name: &str, help: &str, value: &dyn Fn(&Series) -> f64| {
+            family(&mut out, name, "gauge", help);
+            for (integration, s) in all.iter() {
+                sample(&mut out, name, &[("integration", integration)], value(s));
+            }
+        };
+        let active = |s: &Series, value: &dyn Fn(&RunProgress) -> u64| s.active.values().map(value).sum::<u64>() as f64;
+        gauge("turbo_transform_runs_active", "Runs in progress", &|s| s.active.len() as f64);
+        gauge("turbo_transform_run_bytes_read", "CSV bytes read so far by the runs in progress", &|s| {
+            active(s, &|p| p.bytes_read)
+        });
+        gauge("turbo_transform_run_bytes_total", "CSV bytes in the runs in progress", &|s| active(s, &|p| p.bytes_total));
+        gauge("turbo_transform_run_files_done", "Files finished by the runs in progress", &|s| {
+            active(s, &|p| p.files_done as u64)
+        });
+        gauge("turbo_transform_run_files_total", "Files in the runs in progress", &|s| active(s, &|p| p.files_total as u64));
+        gauge("turbo_transform_run_records_per_second", "Throughput of the runs in progress", &|s| {
+            active(s, &|p| p.records_per_second)
+        });
+        gauge("turbo_transform_last_run_duration_seconds", "Duration of the last finished run", &|s| s.last_run_duration_seconds);
+        out
+    }
+
+    /// Atomically replace the textfile at `path` (node_exporter reads *.prom)
+    pub fn write_textfile(&self, path: &Path) -> Result<(), String> {
+        let mut tmp = path.as_os_str().to_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, self.render())
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("write {} failed: {}", path.display(), e))
+    }
+
+    /// Answer GET /metrics on `addr` from background threads (one per
+    /// connection), for as long as the process lives; returns the bound address
+    pub fn serve(self: &Arc<Self>, addr: &str) -> Result<SocketAddr, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+        let bound = listener.local_addr().map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+        let metrics = Arc::clone(self);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let metrics = Arc::clone(&metrics);
+                // A scraper that hangs up early is its own problem
+                thread::spawn(move || metrics.answer(stream));
+            }
+        });
+        Ok(bound)
+    }
+
+    fn answer(&self, mut stream: TcpStream) -> io::Result<()> {
+        // A deadline for the whole request, so a byte now and then cannot keep it open
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        let mut head = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < 8192 {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            stream.set_read_timeout(Some(left))?;
+            let n = stream.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            head.extend_from_slice(&buf[..n]);
+        }
+        let request_line = String::from_utf8_lossy(head.split(|&b| b == b'\n').next().unwrap_or_default()).to_string();
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let path = parts.next().unwrap_or("").split('?').next().unwrap_or("");
+        let (status, body) = match (method, path) {
+            ("GET", "/metrics") => ("200 OK", self.render()),
+            ("GET", _) => ("404 Not Found", "only /metrics is served here\n".to_string()),
+            _ => ("405 Method Not Allowed", "only GET is supported\n".to_string()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl Display) {
+    let labels: Vec<String> = labels.iter().map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v))).collect();
+    let _ = writeln!(out, "{}{{{}}} {}", name, labels.join(","), value);
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::RowRejects;
+
+    fn start(files: usize, total_bytes: u64) -> Event {
+        Event::Start {
+            files,
+            total_bytes,
+            threads: 1,
+            input_dir: "in".into(),
+            output_dir: "out".into(),
+        }
+    }
+
+    fn file_done(records: u64, duration_ms: u64) -> Event {
+        Event::FileDone {
+            file: "parts.csv".into(),
+            input: "in/parts.csv".into(),
+            outputs: Box::default(),
+            records,
+            input_bytes: 100,
+            rows: records + 1,
+            rejected: RowRejects { malformed: 0, missing_part_number: 1 },
+            ndjson_bytes: 50,
+            bson_bytes: 0,
+            bulk_bytes: 0,
+            es_indexed: 0,
+            es_failed: 0,
+            es_unconfirmed: 0,
+            parquet_bytes: 0,
+            duration_ms,
+            rate_per_sec: 0,
+            quality: None,
+            progress: "1/1".into(),
+        }
+    }
+
+    fn progress(bytes_read: u64, files_done: usize) -> Event {
+        Event::Progress {
+            elapsed_ms: 1000,
+            bytes_read,
+            bytes_total: 0,
+            records: 0,
+            rate_per_sec: 10,
+            eta_ms: None,
+            files_done,
+            files_total: 0,
+            files: Vec::new(),
+        }
+    }
+
+    fn complete(duration_ms: u64) -> Event {
+        Event::Complete {
+            total_records: 0,
+            total_ndjson_bytes: 0,
+            total_bson_bytes: 0,
+            total_bulk_bytes: 0,
+            total_es_indexed: 0,
+            total_es_failed: 0,
+            total_es_unconfirmed: 0,
+            total_parquet_bytes: 0,
+            total_input_bytes: 0,
+            duration_ms,
+            rate_per_sec: 0,
+            files_processed: 0,
+            files_total: 0,
+            errors: 0,
+            files_skipped: 0,
+            files_cancelled: 0,
+            cancelled: false,
+            threads: 1,
+            es_index: "parts".into(),
+        }
+    }
+
+    /// The samples of `name`, as "labels value"
+    fn samples(text: &str, name: &str) -> Vec<String> {
+        text.lines()
+            .filter_map(|l| l.strip_prefix(name)?.strip_prefix('{'))
+            .map(|l| l.replacen("} ", " ", 1))
+            .collect()
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative_up_to_inf() {
+        let metrics = Metrics::new();
+        for ms in [200, 500, 3000, 4000000] {
+            metrics.observe("a", 0, &file_done(1, ms));
+        }
+        let text = metrics.render();
+        let buckets = samples(&text, "turbo_transform_file_duration_seconds_bucket");
+        assert_eq!(buckets.len(), DURATION_BUCKETS.len() + 1);
+        assert_eq!(buckets[0], r#"integration="a",le="0.5" 2"#);
+        assert_eq!(buckets[1], r#"integration="a",le="1" 2"#);
+        assert_eq!(buckets[3], r#"integration="a",le="5" 3"#);
+        assert_eq!(buckets[10], r#"integration="a",le="1800" 3"#);
+        assert_eq!(buckets[11], r#"integration="a",le="+Inf" 4"#);
+        assert_eq!(samples(&text, "turbo_transform_file_duration_seconds_count"), [r#"integration="a" 4"#]);
+        assert_eq!(samples(&text, "turbo_transform_file_duration_seconds_sum"), [r#"integration="a" 4003.7"#]);
+        assert!(text.contains("# TYPE turbo_transform_file_duration_seconds histogram\n"));
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let metrics = Metrics::new();
+        metrics.observe("a\"b\\c\nd", 0, &file_done(3, 10));
+        let text = metrics.render();
+        assert_eq!(samples(&text, "turbo_transform_records_total"), [r#"integration="a\"b\\c\nd" 3"#]);
+        // Every sample stays on its own line
+        assert!(text.lines().all(|l| l.starts_with("turbo_transform_") || l.starts_with("# ")));
+    }
+
+    #[test]
+    fn each_integration_has_its_own_series() {
+        let metrics = Metrics::new();
+        metrics.observe("b", 0, &file_done(5, 10));
+        metrics.observe("a", 0, &file_done(2, 10));
+        metrics.observe("a", 0, &file_done(1, 10));
+        metrics.observe("b", 0, &Event::FileFailed { file: "x.csv".into(), error: "e".into(), duration_ms: 1 });
+        let text = metrics.render();
+        assert_eq!(samples(&text, "turbo_transform_records_total"), [r#"integration="a" 3"#, r#"integration="b" 5"#]);
+        assert_eq!(
+            samples(&text, "turbo_transform_files_total"),
+            [r#"integration="a",outcome="done" 2"#, r#"integration="b",outcome="done" 1"#, r#"integration="b",outcome="failed" 1"#]
+        );
+        assert_eq!(
+            samples(&text, "turbo_transform_rows_rejected_total"),
+            [
+                r#"integration="a",reason="malformed" 0"#,
+                r#"integration="a",reason="missing_part_number" 2"#,
+                r#"integration="b",reason="malformed" 0"#,
+                r#"integration="b",reason="missing_part_number" 1"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn overlapping_runs_each_count() {
+        let metrics = Metrics::new();
+        metrics.observe("a", 1, &start(2, 1000));
+        metrics.observe("a", 2, &start(3, 500));
+        metrics.observe("a", 1, &progress(400, 1));
+        metrics.observe("a", 2, &progress(100, 0));
+        let text = metrics.render();
+        assert_eq!(samples(&text, "turbo_transform_runs_active"), [r#"integration="a" 2"#]);
+        assert_eq!(samples(&text, "turbo_transform_run_bytes_read"), [r#"integration="a" 500"#]);
+        assert_eq!(samples(&text, "turbo_transform_run_bytes_total"), [r#"integration="a" 1500"#]);
+        assert_eq!(samples(&text, "turbo_transform_run_files_total"), [r#"integration="a" 5"#]);
+
+        // One finishing leaves the other in progress
+        metrics.observe("a", 1, &complete(2500));
+        let text = metrics.render();
+        assert_eq!(samples(&text, "turbo_transform_runs_active"), [r#"integration="a" 1"#]);
+        assert_eq!(samples(&text, "turbo_transform_run_bytes_read"), [r#"integration="a" 100"#]);
+        assert_eq!(samples(&text, "turbo_transform_last_run_duration_seconds"), [r#"integration="a" 2.5"#]);
+
+        // One that died without a Complete still ends
+        metrics.abandon("a", 2);
+        metrics.abandon("a", 2);
+        let text = metrics.render();
+        assert_eq!(samples(&text, "turbo_transform_runs_active"), [r#"integration="a" 0"#]);
+        assert_eq!(
+            samples(&text, "turbo_transform_runs_total"),
+            [r#"integration="a",outcome="complete" 1"#, r#"integration="a",outcome="failed" 1"#]
+        );
+    }
+
+    #[test]
+    fn a_slow_scrape_does_not_block_the_next() {
+        let metrics = Arc::new(Metrics::new());
+        metrics.observe("a", 0, &file_done(7, 10));
+        let addr = metrics.serve("127.0.0.1:0").unwrap();
+
+        // Connected, but never sends its request
+        let _stalled = TcpStream::connect(addr).unwrap();
+        let mut scrape = TcpStream::connect(addr).unwrap();
+        scrape.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        scrape.write_all(b"GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+        let mut response = String::new();
+        scrape.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("turbo_transform_records_total{integration=\"a\"} 7\n"));
+    }
+}
@@ -0,0 +1,472 @@
+// =============================================================================
+// Parquet sink — columnar, compressed copy of every record for analytics
+// =============================================================================
+// Written in the same pass as .ndjson/.bulk, so BI never has to scan Mongo.
+//
+// Layout (hive-style partitions):
+//   <parquet_dir>/integration=<id>/stockCode=<code>/<stem>.parquet
+//
+// Typed columns: price/weight/volume DOUBLE, quantity/minOrderQty INT64,
+// deliveryDaysMin/Max INT32 (parsed from "3/6", "10", ...), importedAt
+// TIMESTAMP(MILLIS). Strings stay UTF8. ZSTD compressed.
+// =============================================================================
+
+use crate::parse::delivery_range;
+use crate::PartRecord;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use parquet::schema::types::Type;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Rows buffered per partition before a row group is written
+const ROW_GROUP_ROWS: usize = 128 * 1024;
+
+const SCHEMA: &str = "
+message part_record {
+    REQUIRED BYTE_ARRAY partNumber (UTF8);
+    REQUIRED BYTE_ARRAY description (UTF8);
+    REQUIRED BYTE_ARRAY brand (UTF8);
+    REQUIRED BYTE_ARRAY supplier (UTF8);
+    REQUIRED DOUBLE price;
+    REQUIRED BYTE_ARRAY currency (UTF8);
+    REQUIRED INT64 quantity;
+    REQUIRED INT64 minOrderQty;
+    REQUIRED BYTE_ARRAY stock (UTF8);
+    REQUIRED BYTE_ARRAY stockCode (UTF8);
+    REQUIRED DOUBLE weight;
+    REQUIRED BYTE_ARRAY weightUnit (UTF8);
+    REQUIRED DOUBLE volume;
+    REQUIRED BYTE_ARRAY deliveryDays (UTF8);
+    OPTIONAL INT32 deliveryDaysMin;
+    OPTIONAL INT32 deliveryDaysMax;
+    REQUIRED BYTE_ARRAY category (UTF8);
+    REQUIRED BYTE_ARRAY subcategory (UTF8);
+    REQUIRED BYTE_ARRAY integration (UTF8);
+    REQUIRED BYTE_ARRAY integrationName (UTF8);
+    REQUIRED BYTE_ARRAY fileName (UTF8);
+    REQUIRED INT64 importedAt (TIMESTAMP(MILLIS,true));
+}
+";
+
+/// Parsed once per run, shared by every file thread
+pub struct ParquetConfig {
+    pub dir: PathBuf,
+    schema: Arc<Type>,
+    props: Arc<WriterProperties>,
+}
+
+impl ParquetConfig {
+    pub fn new(dir: PathBuf) -> Result<Self, String> {
+        let schema = parse_message_type(SCHEMA).map_err(|e| format!("parquet schema: {}", e))?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .set_max_row_group_size(ROW_GROUP_ROWS)
+            .set_created_by(format!("turbo-transform {}", env!("CARGO_PKG_VERSION")))
+            .build();
+        Ok(ParquetConfig {
+            dir,
+            schema: Arc::new(schema),
+            props: Arc::new(props),
+        })
+    }
+}
+
+/// Hive-style partition value: keep [A-Za-z0-9._-], %-escape the rest
+fn partition_value(s: &str) -> String {
+    if s.is_empty() {
+        return "__HIVE_DEFAULT_PARTITION__".into();
+    }
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b == b'.' || b == b'_' || b == b'-' {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+// =============================================================================
+// Column buffers for one partition
+// =============================================================================
+#[derive(Default)]
+struct Columns {
+    part_number: Vec<ByteArray>,
+    description: Vec<ByteArray>,
+    brand: Vec<ByteArray>,
+    supplier: Vec<ByteArray>,
+    price: Vec<f64>,
+    currency: Vec<ByteArray>,
+    quantity: Vec<i64>,
+    min_order_qty: Vec<i64>,
+    stock: Vec<ByteArray>,
+    stock_code: Vec<ByteArray>,
+    weight: Vec<f64>,
+    weight_unit: Vec<ByteArray>,
+    volume: Vec<f64>,
+    delivery_days: Vec<ByteArray>,
+    /// Non-null values plus one definition level per row
+    delivery_min: Vec<i32>,
+    delivery_max: Vec<i32>,
+    delivery_def: Vec<i16>,
+    category: Vec<ByteArray>,
+    subcategory: Vec<ByteArray>,
+    integration: Vec<ByteArray>,
+    integration_name: Vec<ByteArray>,
+    file_name: Vec<ByteArray>,
+    imported_at: Vec<i64>,
+}
+
+impl Columns {
+    fn len(&self) -> usize {
+        self.price.len()
+    }
+
+    fn push(&mut self, r: &PartRecord, imported_at_millis: i64) {
+        let s = |v: &str| ByteArray::from(v);
+        self.part_number.push(s(r.part_number));
+        self.description.push(s(r.description));
+        self.brand.push(s(r.brand));
+        self.supplier.push(s(r.supplier));
+        self.price.push(r.price);
+        self.currency.push(s(r.currency));
+        self.quantity.push(r.quantity);
+        self.min_order_qty.push(r.min_order_qty);
+        self.stock.push(s(r.stock));
+        self.stock_code.push(s(r.stock_code));
+        self.weight.push(r.weight);
+        self.weight_unit.push(s(r.weight_unit));
+        self.volume.push(r.volume);
+        self.delivery_days.push(s(r.delivery_days));
+        match delivery_range(r.delivery_days) {
+            Some((min, max)) => {
+                self.delivery_min.push(min);
+                self.delivery_max.push(max);
+                self.delivery_def.push(1);
+            }
+            None => self.delivery_def.push(0),
+        }
+        self.category.push(s(r.category));
+        self.subcategory.push(s(r.subcategory));
+        self.integration.push(s(r.integration));
+        self.integration_name.push(s(r.integration_name));
+        self.file_name.push(s(r.file_name));
+        self.imported_at.push(imported_at_millis);
+    }
+
+    /// Write everything buffered as one row group, then clear
+    fn write_row_group(&mut self, writer: &mut SerializedFileWriter<File>) -> parquet::errors::Result<()> {
+        let mut rg = writer.next_row_group()?;
+        let mut idx = 0;
+        while let Some(mut col) = rg.next_column()? {
+            match idx {
+                0 => bytes(&mut col, &self.part_number)?,
+                1 => bytes(&mut col, &self.description)?,
+                2 => bytes(&mut col, &self.brand)?,
+                3 => bytes(&mut col, &self.supplier)?,
+                4 => col.typed::<DoubleType>().write_batch(&self.price, None, None).map(drop)?,
+                5 => bytes(&mut col, &self.currency)?,
+                6 => col.typed::<Int64Type>().write_batch(&self.quantity, None, None).map(drop)?,
+                7 => col.typed::<Int64Type>().write_batch(&self.min_order_qty, None, None).map(drop)?,
+                8 => bytes(&mut col, &self.stock)?,
+                9 => bytes(&mut col, &self.stock_code)?,
+                10 => col.typed::<DoubleType>().write_batch(&self.weight, None, None).map(drop)?,
+                11 => bytes(&mut col, &self.weight_unit)?,
+                12 => col.typed::<DoubleType>().write_batch(&self.volume, None, None).map(drop)?,
+                13 => bytes(&mut col, &self.delivery_days)?,
+                14 => col
+                    .typed::<Int32Type>()
+                    .write_batch(&self.delivery_min, Some(&self.delivery_def), None)
+                    .map(drop)?,
+                15 => col
+                    .typed::<Int32Type>()
+                    .write_batch(&self.delivery_max, Some(&self.delivery_def), None)
+                    .map(drop)?,
+                16 => bytes(&mut col, &self.category)?,
+                17 => bytes(&mut col, &self.subcategory)?,
+                18 => bytes(&mut col, &self.integration)?,
+                19 => bytes(&mut col, &self.integration_name)?,
+                20 => bytes(&mut col, &self.file_name)?,
+                _ => col.typed::<Int64Type>().write_batch(&self.imported_at, None, None).map(drop)?,
+            }
+            col.close()?;
+            idx += 1;
+        }
+        rg.close()?;
+        *self = Columns::default();
+        Ok(())
+    }
+}
+
+fn bytes(
+    col: &mut parquet::file::writer::SerializedColumnWriter<'_>,
+    values: &[ByteArray],
+) -> parquet::errors::Result<()> {
+    col.typed::<ByteArrayType>().write_batch(values, None, None).map(drop)
+}
+
+struct Partition {
+    path: PathBuf,
+    writer: SerializedFileWriter<File>,
+    columns: Columns,
+}
+
+// =============================================================================
+// Per-file sink — one open writer per (integration, stockCode) partition
+// =============================================================================
+// A file that fails part-way must not leave partitions behind: a .parquet
+// without its footer is unreadable, so on any error every file this sink
+// created is deleted.
+pub struct ParquetFileSink<'a> {
+    config: &'a ParquetConfig,
+    stem: String,
+    imported_at_millis: i64,
+    /// integration → stockCode → partition, looked up by &str without allocating
+    partitions: HashMap<String, HashMap<String, Partition>>,
+    error: Option<String>,
+}
+
+impl<'a> ParquetFileSink<'a> {
+    pub fn new(config: &'a ParquetConfig, stem: &str, imported_at_millis: u64) -> Self {
+        ParquetFileSink {
+            config,
+            stem: stem.to_string(),
+            imported_at_millis: imported_at_millis as i64,
+            partitions: HashMap::new(),
+            error: None,
+        }
+    }
+
+    pub fn push(&mut self, record: &PartRecord) {
+        if self.error.is_some() {
+            return;
+        }
+        let (integration, stock_code) = (record.integration, record.stock_code);
+        let open = self
+            .partitions
+            .get(integration)
+            .is_some_and(|codes| codes.contains_key(stock_code));
+        if !open {
+            match self.open_partition(integration, stock_code) {
+                Ok(p) => {
+                    self.partitions
+                        .entry(integration.to_string())
+                        .or_default()
+                        .insert(stock_code.to_string(), p);
+                }
+                Err(e) => {
+                    self.error = Some(e);
+                    return;
+                }
+            }
+        }
+        let partition = self
+            .partitions
+            .get_mut(integration)
+            .and_then(|codes| codes.get_mut(stock_code))
+            .expect("partition opened above");
+
+        partition.columns.push(record, self.imported_at_millis);
+        if partition.columns.len() >= ROW_GROUP_ROWS {
+            if let Err(e) = partition.columns.write_row_group(&mut partition.writer) {
+                self.error = Some(format!("parquet write {} failed: {}", partition.path.display(), e));
+            }
+        }
+    }
+
+    fn open_partition(&self, integration: &str, stock_code: &str) -> Result<Partition, String> {
+        let dir = self
+            .config
+            .dir
+            .join(format!("integration={}", partition_value(integration)))
+            .join(format!("stockCode={}", partition_value(stock_code)));
+        fs::create_dir_all(&dir).map_err(|e| format!("create {} failed: {}", dir.display(), e))?;
+        let path = dir.join(format!("{}.parquet", self.stem));
+        let file = File::create(&path).map_err(|e| format!("create {} failed: {}", path.display(), e))?;
+        let writer = match SerializedFileWriter::new(file, self.config.schema.clone(), self.config.props.clone()) {
+            Ok(w) => w,
+            Err(e) => {
+                let _ = fs::remove_file(&path);
+                return Err(format!("parquet writer {} failed: {}", path.display(), e));
+            }
+        };
+        Ok(Partition {
+            path,
+            writer,
+            columns: Columns::default(),
+        })
+    }
+
+    /// Every file this sink created, sorted
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .partitions
+            .values()
+            .flat_map(|codes| codes.values().map(|p| p.path.clone()))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Drop the open writers and delete every file this sink created
+    pub fn discard(self) {
+        for p in self.partitions.into_values().flat_map(HashMap::into_values) {
+            remove_partition(p);
+        }
+    }
+
+    /// Flush the last row groups and write footers. Returns bytes written.
+    /// On any error, every partition is deleted instead.
+    pub fn finish(self) -> Result<u64, String> {
+        let mut error = self.error;
+        let mut total = 0u64;
+        let mut closed = Vec::new();
+        for mut p in self.partitions.into_values().flat_map(HashMap::into_values) {
+            if error.is_some() {
+                remove_partition(p);
+                continue;
+            }
+            let written = match p.columns.len() {
+                0 => Ok(()),
+                _ => p.columns.write_row_group(&mut p.writer),
+            }
+            .map_err(|e| format!("parquet write {} failed: {}", p.path.display(), e))
+            .and_then(|_| {
+                p.writer
+                    .finish()
+                    .map(drop)
+                    .map_err(|e| format!("parquet close {} failed: {}", p.path.display(), e))
+            });
+            match written {
+                Ok(()) => {
+                    total += fs::metadata(&p.path).map(|m| m.len()).unwrap_or(0);
+                    closed.push(p.path);
+                }
+                Err(e) => {
+                    error = Some(e);
+                    remove_partition(p);
+                }
+            }
+        }
+        match error {
+            None => Ok(total),
+            Some(e) => {
+                for path in closed {
+                    remove_file_and_dir(&path);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+fn remove_partition(p: Partition) {
+    drop(p.writer);
+    remove_file_and_dir(&p.path);
+}
+
+fn remove_file_and_dir(path: &Path) {
+    let _ = fs::remove_file(path);
+    // Only succeeds when no other file wrote to this partition
+    if let Some(dir) = path.parent() {
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+
+    fn record<'a>(integration: &'a str, stock_code: &'a str, part_number: &'a str, price: f64) -> PartRecord<'a> {
+        PartRecord {
+            part_number,
+            description: "Brake disc",
+            supplier: "APMG",
+            price,
+            quantity: 4,
+            stock_code,
+            weight: 0.5,
+            delivery_time: "3/6",
+            delivery_days: if price > 0.0 { "3/6" } else { "n/a" },
+            integration,
+            integration_name: "APMG",
+            file_name: "prices_AB4_part1.csv",
+            ..PartRecord::sample()
+        }
+    }
+
+    /// (partNumber, price, deliveryDaysMin) of every row of `path`
+    fn read(path: &Path) -> Vec<(String, f64, Option<i32>)> {
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().schema().get_fields().len(), 22);
+        reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                (row.get_string(0).unwrap().clone(), row.get_double(4).unwrap(), row.get_int(14).ok())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn partitions_read_back() {
+        let dir = scratch("parquet-read-back");
+        let config = ParquetConfig::new(dir.clone()).unwrap();
+        let mut sink = ParquetFileSink::new(&config, "prices_AB4_part1", 1_700_000_000_000);
+        sink.push(&record("A", "AB4", "P1", 1.5));
+        sink.push(&record("B", "AB4", "P2", 2.5));
+        sink.push(&record("A", "D S/1", "P3", 0.0));
+        sink.push(&record("A", "AB4", "P4", 4.5));
+        sink.push(&record("A", "", "P5", 5.5));
+        let paths = sink.paths();
+        assert!(sink.finish().unwrap() > 0);
+
+        let file = |integration: &str, code: &str| {
+            dir.join(format!("integration={}", integration))
+                .join(format!("stockCode={}", code))
+                .join("prices_AB4_part1.parquet")
+        };
+        let expected = [
+            (file("A", "AB4"), vec![("P1", 1.5, Some(3)), ("P4", 4.5, Some(3))]),
+            (file("A", "D%20S%2F1"), vec![("P3", 0.0, None)]),
+            (file("A", "__HIVE_DEFAULT_PARTITION__"), vec![("P5", 5.5, Some(3))]),
+            (file("B", "AB4"), vec![("P2", 2.5, Some(3))]),
+        ];
+        assert_eq!(paths, expected.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>());
+        for (path, rows) in &expected {
+            let rows: Vec<_> = rows.iter().map(|&(p, price, min)| (p.to_string(), price, min)).collect();
+            assert_eq!(read(path), rows, "{}", path.display());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_partition_deletes_the_others() {
+        let dir = scratch("parquet-error");
+        // integration=B cannot become a directory
+        fs::write(dir.join("integration=B"), "").unwrap();
+        let config = ParquetConfig::new(dir.clone()).unwrap();
+        let mut sink = ParquetFileSink::new(&config, "prices_AB4_part1", 0);
+        sink.push(&record("A", "AB4", "P1", 1.5));
+        sink.push(&record("B", "AB4", "P2", 2.5));
+        sink.push(&record("A", "AB4", "P3", 3.5));
+        let good = dir.join("integration=A").join("stockCode=AB4");
+        assert!(good.join("prices_AB4_part1.parquet").exists());
+
+        let error = sink.finish().unwrap_err();
+        assert!(error.contains("integration=B"), "{}", error);
+        assert!(!good.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
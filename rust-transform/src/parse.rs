@@ -0,0 +1,279 @@
+// =============================================================================
+// Field parsing helpers — the normalisation rules every output shares
+// =============================================================================
+// Supplier CSVs come from Excel exports in several locales, so values are
+// cleaned rather than rejected: currency symbols are stripped, "1.234,56"
+// and "1,234.56" both parse, and ="3/6" formula wrappers are unwrapped.
+// Unparseable numbers become 0, never an error.
+// =============================================================================
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Trimmed, unquoted value of column `idx`; "" when the column is missing.
+/// The row is UTF-8 already (Records checks it); a field that is not reads "".
+#[inline(always)]
+pub fn get_field(record: &csv::ByteRecord, idx: Option<usize>) -> &str {
+    match std::str::from_utf8(get_bytes(record, idx)) {
+        Ok(s) => s.trim().trim_matches(|c: char| c == '"' || c == '\''),
+        Err(_) => "",
+    }
+}
+
+/// Raw bytes of column `idx`, for the numeric parsers (they skip quotes and
+/// whitespace themselves); empty when the column is missing
+#[inline(always)]
+pub fn get_bytes(record: &csv::ByteRecord, idx: Option<usize>) -> &[u8] {
+    idx.and_then(|i| record.get(i)).unwrap_or(b"")
+}
+
+/// Cleaned numbers up to this length are parsed from the stack
+const NUMBER_BUF: usize = 64;
+
+/// Lenient decimal parse: "AED 1,234.50" → 1234.5, "12,34" → 12.34, junk → 0.0
+#[inline(always)]
+pub fn parse_f64(s: impl AsRef<[u8]>) -> f64 {
+    let s = s.as_ref();
+    if s.is_empty() {
+        return 0.0;
+    }
+    // Strip currency symbols and spaces
+    let mut buf = [0u8; NUMBER_BUF];
+    let mut len = 0;
+    for &b in s {
+        if b.is_ascii_digit() || b == b'.' || b == b'-' || b == b',' {
+            if len == NUMBER_BUF {
+                return parse_long_f64(s);
+            }
+            buf[len] = b;
+            len += 1;
+        }
+    }
+    parse_cleaned_f64(&mut buf[..len])
+}
+
+/// parse_f64 for the odd value longer than NUMBER_BUF
+#[cold]
+fn parse_long_f64(s: &[u8]) -> f64 {
+    let mut cleaned: Vec<u8> = s
+        .iter()
+        .copied()
+        .filter(|&b| b.is_ascii_digit() || b == b'.' || b == b'-' || b == b',')
+        .collect();
+    parse_cleaned_f64(&mut cleaned)
+}
+
+/// `cleaned` holds only digits, '.', '-' and ','; it is rewritten in place
+fn parse_cleaned_f64(cleaned: &mut [u8]) -> f64 {
+    let Some(&last) = cleaned.last() else {
+        return 0.0;
+    };
+    // European format: "1234,56" → "1234.56"
+    // But "1,234.56" should drop commas, and so does "1,234" or "1,234,567"
+    let mut commas = cleaned.iter().filter(|&&b| b == b',');
+    let single_comma = commas.next().is_some() && commas.next().is_none();
+    let len = match memchr::memchr(b',', cleaned) {
+        // Decimal comma: one comma, no dot, 1-2 digits after it
+        Some(at) if single_comma && last != b',' && !cleaned.contains(&b'.') && cleaned.len() - at - 1 <= 2 => {
+            cleaned[at] = b'.';
+            cleaned.len()
+        }
+        Some(_) => {
+            // Thousand separators — drop them
+            let mut len = 0;
+            for i in 0..cleaned.len() {
+                if cleaned[i] != b',' {
+                    cleaned[len] = cleaned[i];
+                    len += 1;
+                }
+            }
+            len
+        }
+        None => cleaned.len(),
+    };
+    std::str::from_utf8(&cleaned[..len])
+        .ok()
+        .and_then(|n| n.parse::<f64>().ok())
+        .unwrap_or(0.0)
+}
+
+/// First run of digits: "12 pcs" → 12, "-" → 0, beyond i64 → 0
+#[inline(always)]
+pub fn parse_i64(s: impl AsRef<[u8]>) -> i64 {
+    let mut n: i64 = 0;
+    let mut digits = false;
+    for &b in s.as_ref() {
+        if b.is_ascii_digit() {
+            n = match n.checked_mul(10).and_then(|n| n.checked_add((b - b'0') as i64)) {
+                Some(n) => n,
+                None => return 0,
+            };
+            digits = true;
+        } else if digits {
+            break;
+        }
+    }
+    n
+}
+
+/// Parse delivery field - preserve original format as STRING (e.g. "10", "45", "3/6", "7/14").
+/// Cleans Excel formula wrapper like ="3/6" or ="=""3/6""".
+pub fn parse_delivery(raw: &str) -> &str {
+    let trimmed = raw.trim().trim_matches(|c: char| c == '"' || c == '\'');
+    if trimmed.starts_with('=') {
+        trimmed.trim_start_matches('=').trim_matches('"').trim()
+    } else {
+        trimmed
+    }
+}
+
+/// Numeric lead time from a delivery string: "3/6" → (3, 6), "10" → (10, 10),
+/// "" or "n/a" → None
+pub fn delivery_range(s: &str) -> Option<(i32, i32)> {
+    let mut parts = s.split(['/', '-']).map(|p| p.trim().parse::<i32>());
+    let min = parts.next()?.ok()?;
+    let max = match parts.next() {
+        Some(Ok(v)) => v,
+        Some(Err(_)) => return None,
+        None => min,
+    };
+    Some((min, max))
+}
+
+/// Stock code from a filename like "APMG price 1 day_DS1_part1.csv" → "DS1"
+pub fn extract_stock_code_from_filename(filename: &str) -> &str {
+    // Match _XXXX_part pattern
+    if let Some(start) = filename.rfind("_part") {
+        let before = &filename[..start];
+        if let Some(underscore) = before.rfind('_') {
+            return &before[underscore + 1..];
+        }
+    }
+    ""
+}
+
+/// Delimiter for a header line: ';' when it has more semicolons than commas
+pub fn delimiter_of(first_line: &str) -> u8 {
+    let semicolons = first_line.matches(';').count();
+    let commas = first_line.matches(',').count();
+    if semicolons > commas {
+        b';'
+    } else {
+        b','
+    }
+}
+
+/// Best guess at a file's text encoding from its first bytes: "ascii",
+/// "utf-8", "utf-8-bom", "utf-16le", "utf-16be" or "non-utf-8"
+pub fn sniff_encoding(head: &[u8]) -> &'static str {
+    if head.starts_with(b"\xEF\xBB\xBF") {
+        "utf-8-bom"
+    } else if head.starts_with(b"\xFF\xFE") {
+        "utf-16le"
+    } else if head.starts_with(b"\xFE\xFF") {
+        "utf-16be"
+    } else if head.is_ascii() {
+        "ascii"
+    } else {
+        match std::str::from_utf8(head) {
+            Ok(_) => "utf-8",
+            // A multi-byte character cut off at the end of the sample
+            Err(e) if e.error_len().is_none() => "utf-8",
+            Err(_) => "non-utf-8",
+        }
+    }
+}
+
+/// Detect the CSV delimiter from the first line of a file
+pub fn detect_delimiter(path: &Path) -> u8 {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return b',',
+    };
+    let reader = BufReader::new(file);
+    match reader.lines().next() {
+        Some(Ok(first_line)) => delimiter_of(&first_line),
+        _ => b',',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f64_commas_and_dots() {
+        assert_eq!(parse_f64("12,34"), 12.34);
+        assert_eq!(parse_f64("12,5"), 12.5);
+        assert_eq!(parse_f64("1,234.56"), 1234.56);
+        assert_eq!(parse_f64("1.234"), 1.234);
+        // Three digits after a lone comma, or several commas: thousands
+        assert_eq!(parse_f64("1,234"), 1234.0);
+        assert_eq!(parse_f64("1,234,567"), 1234567.0);
+        assert_eq!(parse_f64("5,"), 5.0);
+    }
+
+    #[test]
+    fn f64_whitespace_symbols_and_empty() {
+        assert_eq!(parse_f64(" 12.5 "), 12.5);
+        assert_eq!(parse_f64("AED 1,234.50"), 1234.5);
+        assert_eq!(parse_f64("\"7,5\""), 7.5);
+        assert_eq!(parse_f64("12 500"), 12500.0);
+        assert_eq!(parse_f64(""), 0.0);
+        assert_eq!(parse_f64("   "), 0.0);
+        assert_eq!(parse_f64("n/a"), 0.0);
+        assert_eq!(parse_f64("1.2.3"), 0.0);
+    }
+
+    #[test]
+    fn f64_negatives() {
+        assert_eq!(parse_f64("-3.5"), -3.5);
+        assert_eq!(parse_f64("-12,5"), -12.5);
+        assert_eq!(parse_f64("€ -1,000.25"), -1000.25);
+        assert_eq!(parse_f64("-"), 0.0);
+    }
+
+    #[test]
+    fn f64_longer_than_the_stack_buffer() {
+        let long = "1".repeat(NUMBER_BUF + 6);
+        assert_eq!(parse_f64(&long), long.parse::<f64>().unwrap());
+        let decimal = format!("{},5", "9".repeat(NUMBER_BUF));
+        assert_eq!(parse_f64(&decimal), format!("{}.5", "9".repeat(NUMBER_BUF)).parse::<f64>().unwrap());
+        // Out of f64 range saturates like str::parse
+        assert_eq!(parse_f64("9".repeat(400)), f64::INFINITY);
+    }
+
+    #[test]
+    fn i64_first_run_of_digits() {
+        assert_eq!(parse_i64("12 pcs"), 12);
+        assert_eq!(parse_i64("  42  "), 42);
+        assert_eq!(parse_i64("qty: 7, min 2"), 7);
+        assert_eq!(parse_i64("1,000"), 1);
+        assert_eq!(parse_i64(""), 0);
+        assert_eq!(parse_i64("-"), 0);
+        // No sign: quantities are never negative, "-5" reads as 5
+        assert_eq!(parse_i64("-5"), 5);
+    }
+
+    #[test]
+    fn i64_overflow_is_zero() {
+        assert_eq!(parse_i64(i64::MAX.to_string()), i64::MAX);
+        assert_eq!(parse_i64("9223372036854775808"), 0);
+        assert_eq!(parse_i64("99999999999999999999 pcs"), 0);
+    }
+
+    #[test]
+    fn non_utf8_bytes() {
+        assert_eq!(parse_f64(b"\xff12,5\xfe"), 12.5);
+        assert_eq!(parse_f64(b"\xc3\x2812.75"), 12.75);
+        assert_eq!(parse_i64(b"\xff7 pcs"), 7);
+        let record = csv::ByteRecord::from(vec![&b"P1"[..], &b"\xffAB"[..], &b" 3,5 "[..]]);
+        assert_eq!(get_field(&record, Some(0)), "P1");
+        assert_eq!(get_field(&record, Some(1)), "");
+        assert_eq!(get_field(&record, Some(9)), "");
+        assert_eq!(get_field(&record, None), "");
+        assert_eq!(parse_f64(get_bytes(&record, Some(2))), 3.5);
+        assert_eq!(get_bytes(&record, Some(1)), b"\xffAB");
+    }
+}
@@ -0,0 +1,240 @@
+// =============================================================================
+// Data-quality checks — per-file metrics against configurable thresholds
+// =============================================================================
+// A supplier file can "succeed" while being useless: most rows rejected,
+// every price parsed to 0, no brands, the same part listed many times.
+// QualityStats is fed every record of a file; QualityReport turns the counts
+// into rates and lists every threshold that was exceeded, by rule name:
+//
+//   reject_rate         rows that produced no record / rows read
+//   zero_price_rate     records with price 0 / records
+//   empty_brand_rate    records with an empty brand / records
+//   duplicate_key_rate  repeated partNumber+brand+stockCode / records
+//   min_records         fewer records than required
+// =============================================================================
+
+use crate::reader::RowStats;
+use crate::record::PartRecord;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct QualityThresholds {
+    pub max_reject_rate: f64,
+    pub max_zero_price_rate: f64,
+    pub max_empty_brand_rate: f64,
+    pub max_duplicate_key_rate: f64,
+    pub min_records: u64,
+}
+
+impl Default for QualityThresholds {
+    fn default() -> Self {
+        QualityThresholds {
+            max_reject_rate: 0.10,
+            max_zero_price_rate: 0.10,
+            max_empty_brand_rate: 0.25,
+            max_duplicate_key_rate: 0.05,
+            min_records: 1,
+        }
+    }
+}
+
+/// Counters for one file, fed record by record
+#[derive(Default)]
+pub struct QualityStats {
+    records: u64,
+    zero_price: u64,
+    empty_brand: u64,
+    duplicate_keys: u64,
+    /// Hashes of partNumber+brand+stockCode seen so far
+    keys: HashSet<u64>,
+}
+
+impl QualityStats {
+    pub fn add(&mut self, record: &PartRecord) {
+        self.records += 1;
+        if record.price == 0.0 {
+            self.zero_price += 1;
+        }
+        if record.brand.is_empty() {
+            self.empty_brand += 1;
+        }
+        let mut h = DefaultHasher::new();
+        (record.part_number, record.brand, record.stock_code).hash(&mut h);
+        if !self.keys.insert(h.finish()) {
+            self.duplicate_keys += 1;
+        }
+    }
+
+    /// Rates for the file, checked against `thresholds`
+    pub fn report(&self, rows: &RowStats, thresholds: &QualityThresholds) -> QualityReport {
+        let rate = |n: u64, of: u64| if of == 0 { 0.0 } else { n as f64 / of as f64 };
+        let mut report = QualityReport {
+            rows: rows.rows,
+            records: self.records,
+            rejected: rows.rejected(),
+            zero_price: self.zero_price,
+            empty_brand: self.empty_brand,
+            duplicate_keys: self.duplicate_keys,
+            reject_rate: rate(rows.rejected(), rows.rows),
+            zero_price_rate: rate(self.zero_price, self.records),
+            empty_brand_rate: rate(self.empty_brand, self.records),
+            duplicate_key_rate: rate(self.duplicate_keys, self.records),
+            violations: Vec::new(),
+        };
+
+        let checks = [
+            ("reject_rate", report.reject_rate, thresholds.max_reject_rate),
+            ("zero_price_rate", report.zero_price_rate, thresholds.max_zero_price_rate),
+            ("empty_brand_rate", report.empty_brand_rate, thresholds.max_empty_brand_rate),
+            ("duplicate_key_rate", report.duplicate_key_rate, thresholds.max_duplicate_key_rate),
+        ];
+        for (rule, value, threshold) in checks {
+            if value > threshold {
+                report.violations.push(Violation { rule, value, threshold });
+            }
+        }
+        if self.records < thresholds.min_records {
+            report.violations.push(Violation {
+                rule: "min_records",
+                value: self.records as f64,
+                threshold: thresholds.min_records as f64,
+            });
+        }
+        report
+    }
+}
+
+/// One exceeded threshold
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub value: f64,
+    pub threshold: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct QualityReport {
+    pub rows: u64,
+    pub records: u64,
+    pub rejected: u64,
+    pub zero_price: u64,
+    pub empty_brand: u64,
+    pub duplicate_keys: u64,
+    pub reject_rate: f64,
+    pub zero_price_rate: f64,
+    pub empty_brand_rate: f64,
+    pub duplicate_key_rate: f64,
+    /// Empty when the file passed
+    pub violations: Vec<Violation>,
+}
+
+impl QualityReport {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// "reject_rate 0.420 > 0.100; min_records 0 < 1"
+    pub fn summary(&self) -> String {
+        self.violations
+            .iter()
+            .map(|v| match v.rule {
+                "min_records" => format!("{} {} < {}", v.rule, v.value, v.threshold),
+                _ => format!("{} {:.3} > {:.3}", v.rule, v.value, v.threshold),
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record<'a>(part_number: &'a str, brand: &'a str, price: f64) -> PartRecord<'a> {
+        PartRecord { part_number, brand, price, ..PartRecord::sample() }
+    }
+
+    /// Ten records: the first `zero_price` priced 0, the first `empty_brand`
+    /// without a brand, and the last `duplicates` repeating the one before them
+    fn stats(zero_price: usize, empty_brand: usize, duplicates: usize) -> QualityStats {
+        let parts: Vec<String> = (0..10).map(|i| format!("P{}", i)).collect();
+        let mut stats = QualityStats::default();
+        for (i, part) in parts.iter().enumerate() {
+            let part = if i >= 10 - duplicates { &parts[9 - duplicates] } else { part };
+            let brand = if i < empty_brand { "" } else { "BOSCH" };
+            let price = if i < zero_price { 0.0 } else { 9.5 };
+            stats.add(&record(part, brand, price));
+        }
+        stats
+    }
+
+    fn rows(rows: u64, rejected: u64) -> RowStats {
+        RowStats { rows, missing_part_number: rejected, ..Default::default() }
+    }
+
+    fn rules(report: &QualityReport) -> Vec<&'static str> {
+        report.violations.iter().map(|v| v.rule).collect()
+    }
+
+    #[test]
+    fn rates_at_the_threshold_pass() {
+        let thresholds = QualityThresholds {
+            max_reject_rate: 0.5,
+            max_zero_price_rate: 0.1,
+            max_empty_brand_rate: 0.2,
+            max_duplicate_key_rate: 0.3,
+            min_records: 10,
+        };
+        let report = stats(1, 2, 3).report(&rows(20, 10), &thresholds);
+
+        assert_eq!((report.reject_rate, report.zero_price_rate), (0.5, 0.1));
+        assert_eq!((report.empty_brand_rate, report.duplicate_key_rate), (0.2, 0.3));
+        assert!(report.passed(), "{}", report.summary());
+    }
+
+    #[test]
+    fn each_rate_over_its_threshold_is_a_violation() {
+        let thresholds = QualityThresholds::default();
+        assert!(stats(1, 2, 0).report(&rows(10, 0), &thresholds).passed());
+
+        assert_eq!(rules(&stats(0, 0, 0).report(&rows(12, 2), &thresholds)), ["reject_rate"]);
+        assert_eq!(rules(&stats(2, 0, 0).report(&rows(10, 0), &thresholds)), ["zero_price_rate"]);
+        assert_eq!(rules(&stats(0, 3, 0).report(&rows(10, 0), &thresholds)), ["empty_brand_rate"]);
+        assert_eq!(rules(&stats(0, 0, 1).report(&rows(10, 0), &thresholds)), ["duplicate_key_rate"]);
+        assert_eq!(
+            rules(&stats(5, 5, 5).report(&rows(20, 10), &thresholds)),
+            ["reject_rate", "zero_price_rate", "empty_brand_rate", "duplicate_key_rate"]
+        );
+    }
+
+    #[test]
+    fn duplicates_need_the_same_part_brand_and_stock_code() {
+        let mut stats = QualityStats::default();
+        stats.add(&record("P1", "BOSCH", 1.0));
+        stats.add(&record("P1", "MANN", 1.0));
+        stats.add(&record("P1", "BOSCH", 2.0));
+        assert_eq!(stats.report(&rows(3, 0), &QualityThresholds::default()).duplicate_keys, 1);
+    }
+
+    #[test]
+    fn too_few_records_is_a_violation() {
+        let thresholds = QualityThresholds { min_records: 11, ..Default::default() };
+        let report = stats(0, 0, 0).report(&rows(10, 0), &thresholds);
+        assert_eq!(rules(&report), ["min_records"]);
+        assert_eq!(report.summary(), "min_records 10 < 11");
+
+        // An empty file: no division by zero, just too few records
+        let report = QualityStats::default().report(&rows(0, 0), &QualityThresholds::default());
+        assert_eq!((report.reject_rate, report.zero_price_rate), (0.0, 0.0));
+        assert_eq!(report.summary(), "min_records 0 < 1");
+    }
+
+    #[test]
+    fn summary_lists_every_violation() {
+        let report = stats(5, 0, 0).report(&rows(20, 10), &QualityThresholds::default());
+        assert_eq!(report.summary(), "reject_rate 0.500 > 0.100; zero_price_rate 0.500 > 0.100");
+    }
+}
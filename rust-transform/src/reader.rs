@@ -0,0 +1,222 @@
+// =============================================================================
+// Record reader — CSV bytes in, normalised PartRecords out
+// =============================================================================
+// The delimiter is sniffed from the header line, ragged rows are tolerated,
+// malformed rows and rows without a part number are skipped. This is the
+// loop every output of the transform is fed from.
+//
+//   let mut records = Records::new(file, "prices_AB4_part1.csv", &mapping)?;
+//   while let Some(r) = records.next_record() { ... }   // borrowed, no allocs
+//   let parts: Vec<Part> = records.collect();           // owned
+// =============================================================================
+
+use crate::mapping::{ColumnMap, FileContext, MappingConfig};
+use crate::parse::{delimiter_of, get_field};
+use crate::record::{Part, PartRecord};
+use csv::ReaderBuilder;
+use std::io::{BufRead, BufReader, Read};
+
+/// 256KB read buffer — saturates NVMe read bandwidth per thread
+const READ_BUFFER: usize = 256 * 1024;
+
+/// Row-level warnings kept per file; the counters in RowStats keep going
+const MAX_WARNINGS: usize = 20;
+
+/// What happened to the data rows read so far
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RowStats {
+    /// Data rows read, header excluded
+    pub rows: u64,
+    /// Rows the CSV parser rejected (invalid UTF-8, I/O errors)
+    pub malformed: u64,
+    /// Rows skipped because the part number column was empty
+    pub missing_part_number: u64,
+    /// Rows whose field count differs from the header (still used)
+    pub ragged: u64,
+}
+
+impl RowStats {
+    /// Rows that did not become a record
+    pub fn rejected(&self) -> u64 {
+        self.malformed + self.missing_part_number
+    }
+}
+
+pub struct Records<R: Read> {
+    reader: csv::Reader<BufReader<R>>,
+    columns: ColumnMap,
+    headers: csv::StringRecord,
+    delimiter: u8,
+    context: FileContext,
+    row: csv::ByteRecord,
+    stats: RowStats,
+    warnings: Vec<String>,
+}
+
+impl<R: Read> Records<R> {
+    /// Read the header row and resolve the column map. `file_name` is
+    /// stamped on every record and is where the fallback stock code comes
+    /// from. Fails when the header can't be read or has no part number column.
+    pub fn new(reader: R, file_name: &str, config: &MappingConfig) -> Result<Self, String> {
+        let records = Self::open(reader, file_name, config)?;
+        if records.columns.part_number.is_none() {
+            return Err("no part number column detected".into());
+        }
+        Ok(records)
+    }
+
+    /// Like `new`, but also accepts a header without a part number column
+    /// (every row is then counted as missing one). For diagnostics.
+    pub fn open(reader: R, file_name: &str, config: &MappingConfig) -> Result<Self, String> {
+        let mut buf_reader = BufReader::with_capacity(READ_BUFFER, reader);
+
+        // Detect delimiter from the first line without consuming it
+        let delimiter = {
+            let head = buf_reader
+                .fill_buf()
+                .map_err(|e| format!("header parse failed: {}", e))?;
+            let line = match memchr::memchr(b'\n', head) {
+                Some(end) => &head[..end],
+                None => head,
+            };
+            match std::str::from_utf8(line) {
+                Ok(line) => delimiter_of(line),
+                Err(_) => b',',
+            }
+        };
+
+        let mut reader = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(true)
+            .flexible(true) // tolerate ragged rows
+            // Fields are trimmed by get_field; csv's own field trim copies every row
+            .trim(csv::Trim::Headers)
+            .from_reader(buf_reader);
+
+        let headers = match reader.headers() {
+            Ok(h) => h.clone(),
+            Err(e) => return Err(format!("header parse failed: {}", e)),
+        };
+        let columns = ColumnMap::from_headers(&headers);
+
+        Ok(Records {
+            reader,
+            columns,
+            headers,
+            delimiter,
+            context: FileContext::new(config, file_name),
+            row: csv::ByteRecord::new(),
+            stats: RowStats::default(),
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Stock code for rows without one, instead of the one in the file name
+    pub fn with_stock_code(mut self, stock_code: &str) -> Self {
+        self.context.filename_stock_code = stock_code.to_string();
+        self
+    }
+
+    pub fn columns(&self) -> &ColumnMap {
+        &self.columns
+    }
+
+    /// The header row, as read
+    pub fn headers(&self) -> &csv::StringRecord {
+        &self.headers
+    }
+
+    /// The field delimiter sniffed from the header line
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    /// Counters for the rows read so far
+    pub fn stats(&self) -> &RowStats {
+        &self.stats
+    }
+
+    /// The first malformed/ragged row messages, with line numbers
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    fn warn(&mut self, message: impl FnOnce() -> String) {
+        if self.warnings.len() < MAX_WARNINGS {
+            self.warnings.push(message());
+        }
+    }
+
+    /// Next record, borrowed from the internal row buffer
+    pub fn next_record(&mut self) -> Option<PartRecord<'_>> {
+        loop {
+            match self.reader.read_byte_record(&mut self.row) {
+                Ok(true) => self.stats.rows += 1,
+                Ok(false) => return None, // EOF
+                Err(e) => {
+                    // skip malformed rows
+                    self.stats.rows += 1;
+                    self.stats.malformed += 1;
+                    self.warn(|| e.to_string());
+                    continue;
+                }
+            }
+            // A row that is not UTF-8 is malformed; get_field reads the rest as &str
+            if !is_utf8(&self.row) {
+                self.stats.malformed += 1;
+                if self.warnings.len() < MAX_WARNINGS {
+                    self.warnings.push(utf8_warning(&self.row));
+                }
+                continue;
+            }
+            if self.row.len() != self.headers.len() {
+                self.stats.ragged += 1;
+                let (fields, expected) = (self.row.len(), self.headers.len());
+                let line = self.row.position().map_or(0, |p| p.line());
+                self.warn(|| format!("line {}: {} fields, header has {}", line, fields, expected));
+            }
+            if !get_field(&self.row, self.columns.part_number).is_empty() {
+                break;
+            }
+            self.stats.missing_part_number += 1;
+        }
+        self.columns.map_row(&self.row, &self.context)
+    }
+}
+
+/// Same check as csv's StringRecord: all-ASCII rows skip per-field validation
+fn is_utf8(row: &csv::ByteRecord) -> bool {
+    row.as_slice().is_ascii() || row.iter().all(|field| std::str::from_utf8(field).is_ok())
+}
+
+/// The message csv gives for a row that is not UTF-8
+#[cold]
+fn utf8_warning(row: &csv::ByteRecord) -> String {
+    // csv validates after trimming, which moves the byte index it reports
+    let position = row.position();
+    let mut row = row.clone();
+    row.trim();
+    let Err(e) = csv::StringRecord::from_byte_record(row) else {
+        return String::new();
+    };
+    let e = e.utf8_error();
+    match position {
+        Some(p) => format!(
+            "CSV parse error: record {} (line {}, field: {}, byte: {}): {}",
+            p.record(),
+            p.line(),
+            e.field(),
+            p.byte(),
+            e
+        ),
+        None => format!("CSV parse error: field {}: {}", e.field(), e),
+    }
+}
+
+impl<R: Read> Iterator for Records<R> {
+    type Item = Part;
+
+    fn next(&mut self) -> Option<Part> {
+        self.next_record().map(|r| Part::from(&r))
+    }
+}
@@ -0,0 +1,290 @@
+// =============================================================================
+// Output records — borrowed views over one CSV row, plus an owned copy
+// =============================================================================
+// PartRecord / PartRecordES borrow from the csv::ByteRecord they were
+// mapped from, so the per-file hot loop never allocates for string fields.
+// Part is the owned equivalent for callers that keep records around.
+// =============================================================================
+
+use serde::Serialize;
+
+// =============================================================================
+// Output record for NDJSON (MongoDB) — all fields
+// Matches the exact schema the Node.js turboSyncEngine produces.
+// =============================================================================
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartRecord<'a> {
+    pub part_number: &'a str,
+    pub description: &'a str,
+    pub brand: &'a str,
+    pub supplier: &'a str,
+    pub price: f64,
+    pub currency: &'a str,
+    pub quantity: i64,
+    pub min_order_qty: i64,
+    pub stock: &'a str,
+    pub stock_code: &'a str,
+    pub weight: f64,
+    pub weight_unit: &'a str,
+    pub volume: f64,
+    pub delivery_time: &'a str,
+    pub delivery_days: &'a str,
+    pub category: &'a str,
+    pub subcategory: &'a str,
+    pub integration: &'a str,
+    pub integration_name: &'a str,
+    pub file_name: &'a str,
+    pub imported_at: &'a str,
+}
+
+impl<'a> PartRecord<'a> {
+    /// Append this record as one NDJSON line (the mongoimport format)
+    pub fn write_ndjson(&self, out: &mut Vec<u8>) -> serde_json::Result<()> {
+        serde_json::to_writer(&mut *out, self)?;
+        out.push(b'\n');
+        Ok(())
+    }
+
+    /// The ES document for this record — same fields minus imported_at
+    pub fn es(&self) -> PartRecordES<'a> {
+        PartRecordES {
+            part_number: self.part_number,
+            description: self.description,
+            brand: self.brand,
+            supplier: self.supplier,
+            price: self.price,
+            currency: self.currency,
+            quantity: self.quantity,
+            min_order_qty: self.min_order_qty,
+            stock: self.stock,
+            stock_code: self.stock_code,
+            weight: self.weight,
+            weight_unit: self.weight_unit,
+            volume: self.volume,
+            delivery_time: self.delivery_time,
+            delivery_days: self.delivery_days,
+            category: self.category,
+            subcategory: self.subcategory,
+            integration: self.integration,
+            integration_name: self.integration_name,
+            file_name: self.file_name,
+        }
+    }
+}
+
+// =============================================================================
+// Output record for ES _bulk — same fields minus imported_at
+// =============================================================================
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartRecordES<'a> {
+    pub part_number: &'a str,
+    pub description: &'a str,
+    pub brand: &'a str,
+    pub supplier: &'a str,
+    pub price: f64,
+    pub currency: &'a str,
+    pub quantity: i64,
+    pub min_order_qty: i64,
+    pub stock: &'a str,
+    pub stock_code: &'a str,
+    pub weight: f64,
+    pub weight_unit: &'a str,
+    pub volume: f64,
+    pub delivery_time: &'a str,
+    pub delivery_days: &'a str,
+    pub category: &'a str,
+    pub subcategory: &'a str,
+    pub integration: &'a str,
+    pub integration_name: &'a str,
+    pub file_name: &'a str,
+}
+
+// =============================================================================
+// Both JSON outputs from one serialisation
+// =============================================================================
+/// Encodes a record once for NDJSON and ES. PartRecord is PartRecordES with
+/// importedAt as its last field, so the NDJSON line is the ES document with
+/// `,"importedAt":"…"}` in place of its closing brace.
+///
+///   json.encode(&doc)?;
+///   bulk_doc.extend_from_slice(json.es());
+///   let (fields, imported_at) = json.ndjson();   // write both, back to back
+#[derive(Default)]
+pub struct RecordJson {
+    es: Vec<u8>,
+    /// `,"importedAt":"…"}` and the newline, for `imported_at`
+    tail: Vec<u8>,
+    imported_at: String,
+}
+
+impl RecordJson {
+    /// Serialise `doc`; es() and ndjson() return it until the next call
+    pub fn encode(&mut self, doc: &PartRecord) -> serde_json::Result<()> {
+        self.es.clear();
+        serde_json::to_writer(&mut self.es, &doc.es())?;
+        // The same for every record of a run — rebuilt only when it changes
+        if self.tail.is_empty() || doc.imported_at != self.imported_at {
+            self.imported_at = doc.imported_at.to_string();
+            self.tail.clear();
+            self.tail.extend_from_slice(br#","importedAt":"#);
+            serde_json::to_writer(&mut self.tail, doc.imported_at)?;
+            self.tail.extend_from_slice(b"}\n");
+        }
+        Ok(())
+    }
+
+    /// The ES document, without a newline
+    pub fn es(&self) -> &[u8] {
+        &self.es
+    }
+
+    /// The NDJSON line, as two slices: the shared fields, then importedAt
+    pub fn ndjson(&self) -> (&[u8], &[u8]) {
+        (&self.es[..self.es.len().saturating_sub(1)], &self.tail)
+    }
+}
+
+// =============================================================================
+// Owned record — serialises exactly like PartRecord
+// =============================================================================
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Part {
+    pub part_number: String,
+    pub description: String,
+    pub brand: String,
+    pub supplier: String,
+    pub price: f64,
+    pub currency: String,
+    pub quantity: i64,
+    pub min_order_qty: i64,
+    pub stock: String,
+    pub stock_code: String,
+    pub weight: f64,
+    pub weight_unit: String,
+    pub volume: f64,
+    pub delivery_time: String,
+    pub delivery_days: String,
+    pub category: String,
+    pub subcategory: String,
+    pub integration: String,
+    pub integration_name: String,
+    pub file_name: String,
+    pub imported_at: String,
+}
+
+#[cfg(test)]
+impl PartRecord<'_> {
+    /// A minimal BOSCH record in AED for tests; set what a test cares about
+    /// with struct update syntax
+    pub(crate) fn sample() -> Self {
+        PartRecord {
+            part_number: "P1",
+            description: "",
+            brand: "BOSCH",
+            supplier: "",
+            price: 1.0,
+            currency: "AED",
+            quantity: 1,
+            min_order_qty: 1,
+            stock: "unknown",
+            stock_code: "AB4",
+            weight: 0.0,
+            weight_unit: "kg",
+            volume: 0.0,
+            delivery_time: "",
+            delivery_days: "",
+            category: "",
+            subcategory: "",
+            integration: "",
+            integration_name: "",
+            file_name: "",
+            imported_at: "",
+        }
+    }
+}
+
+impl From<&PartRecord<'_>> for Part {
+    fn from(r: &PartRecord<'_>) -> Self {
+        Part {
+            part_number: r.part_number.to_string(),
+            description: r.description.to_string(),
+            brand: r.brand.to_string(),
+            supplier: r.supplier.to_string(),
+            price: r.price,
+            currency: r.currency.to_string(),
+            quantity: r.quantity,
+            min_order_qty: r.min_order_qty,
+            stock: r.stock.to_string(),
+            stock_code: r.stock_code.to_string(),
+            weight: r.weight,
+            weight_unit: r.weight_unit.to_string(),
+            volume: r.volume,
+            delivery_time: r.delivery_time.to_string(),
+            delivery_days: r.delivery_days.to_string(),
+            category: r.category.to_string(),
+            subcategory: r.subcategory.to_string(),
+            integration: r.integration.to_string(),
+            integration_name: r.integration_name.to_string(),
+            file_name: r.file_name.to_string(),
+            imported_at: r.imported_at.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record<'a>(text: &'a str, price: f64, imported_at: &'a str) -> PartRecord<'a> {
+        PartRecord {
+            part_number: text,
+            description: text,
+            brand: "Bosch \"Blau\"",
+            supplier: "Zulieferer Köln",
+            price,
+            currency: "€",
+            quantity: -3,
+            weight: 0.1,
+            volume: 1e21,
+            delivery_time: "=\"3/6\"",
+            delivery_days: "3/6",
+            subcategory: "\\",
+            integration: "507f1f77bcf86cd799439011",
+            integration_name: "APMG",
+            file_name: "prices\t_AB4_part1.csv",
+            imported_at,
+            ..PartRecord::sample()
+        }
+    }
+
+    #[test]
+    fn record_json_matches_serialising_each_output() {
+        let texts = [
+            "plain",
+            "quote \" backslash \\ slash /",
+            "newline\n tab\t cr\r nul\u{0} bell\u{7} del\u{7f}",
+            "Größe Ø12 — тормозной диск 制动盘 🚗",
+            "separators \u{2028}\u{2029} bom \u{feff}",
+        ];
+        let imported = ["2026-10-18T12:00:00.000Z", "2026-10-18T12:00:00.000Z", "naïve \"time\"\n"];
+        let prices = [0.0, -0.0, 12.5, 1234567.891, f64::NAN, f64::INFINITY];
+
+        let mut json = RecordJson::default();
+        for (i, text) in texts.iter().enumerate() {
+            for (j, price) in prices.iter().enumerate() {
+                let doc = record(text, *price, imported[(i + j) % imported.len()]);
+                json.encode(&doc).unwrap();
+
+                let mut ndjson = Vec::new();
+                doc.write_ndjson(&mut ndjson).unwrap();
+                let (fields, imported_at) = json.ndjson();
+                assert_eq!([fields, imported_at].concat(), ndjson, "{:?} {}", text, price);
+                assert_eq!(json.es(), serde_json::to_vec(&doc.es()).unwrap(), "{:?} {}", text, price);
+                assert_eq!(ndjson, [serde_json::to_vec(&Part::from(&doc)).unwrap(), b"\n".to_vec()].concat());
+            }
+        }
+    }
+}
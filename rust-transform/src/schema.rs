@@ -0,0 +1,228 @@
+// =============================================================================
+// ES index mapping + JSON Schema derived from PartRecord
+// =============================================================================
+// The field list and base types come from serialising a blank PartRecord, so
+// a field added to the struct shows up here without touching this file.
+// FIELD_OVERRIDES only carries what serde can't tell us (analyzers, dates).
+// The `schema` command (src/cli/schema.rs) prints either document.
+// =============================================================================
+
+use crate::PartRecord;
+use serde_json::{json, Map, Value};
+
+pub const DEFAULT_SHARDS: u64 = 5;
+pub const DEFAULT_REPLICAS: u64 = 0;
+
+/// Fields ES needs beyond plain keyword/float/integer
+fn field_override(name: &str) -> Option<Value> {
+    Some(match name {
+        "partNumber" => json!({
+            "type": "keyword",
+            "fields": {
+                "text": { "type": "text", "analyzer": "part_number_analyzer" },
+                "autocomplete": {
+                    "type": "text",
+                    "analyzer": "autocomplete_analyzer",
+                    "search_analyzer": "autocomplete_search_analyzer"
+                }
+            }
+        }),
+        "description" => json!({
+            "type": "text",
+            "analyzer": "standard",
+            "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } }
+        }),
+        "brand" | "supplier" => json!({
+            "type": "keyword",
+            "fields": { "text": { "type": "text", "analyzer": "standard" } }
+        }),
+        "importedAt" => json!({ "type": "date" }),
+        _ => return None,
+    })
+}
+
+/// Not emitted by the transform, but set downstream and queried by the app
+const EXTRA_ES_FIELDS: [(&str, &str); 1] = [("createdAt", "date")];
+
+/// Serialised field names and values of a blank record, in struct order
+fn record_fields() -> Map<String, Value> {
+    let blank = PartRecord {
+        part_number: "",
+        description: "",
+        brand: "",
+        supplier: "",
+        price: 0.0,
+        currency: "",
+        quantity: 0,
+        min_order_qty: 0,
+        stock: "",
+        stock_code: "",
+        weight: 0.0,
+        weight_unit: "",
+        volume: 0.0,
+        delivery_time: "",
+        delivery_days: "",
+        category: "",
+        subcategory: "",
+        integration: "",
+        integration_name: "",
+        file_name: "",
+        imported_at: "",
+    };
+    match serde_json::to_value(&blank) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+fn es_base_type(sample: &Value) -> &'static str {
+    match sample {
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "integer",
+        Value::Bool(_) => "boolean",
+        _ => "keyword",
+    }
+}
+
+fn json_schema_type(sample: &Value) -> &'static str {
+    match sample {
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::Bool(_) => "boolean",
+        _ => "string",
+    }
+}
+
+/// Index settings + mappings, as passed to `PUT /<index>`
+pub fn es_index_mapping(shards: u64, replicas: u64) -> Value {
+    let mut properties = Map::new();
+    for (name, sample) in record_fields() {
+        let mapping = field_override(&name).unwrap_or_else(|| json!({ "type": es_base_type(&sample) }));
+        properties.insert(name, mapping);
+    }
+    for (name, ty) in EXTRA_ES_FIELDS {
+        properties.insert(name.to_string(), json!({ "type": ty }));
+    }
+
+    json!({
+        "settings": {
+            "number_of_shards": shards,
+            "number_of_replicas": replicas,
+            "refresh_interval": "-1",
+            "max_result_window": 50000,
+            "index.translog.durability": "async",
+            "index.translog.sync_interval": "120s",
+            "index.translog.flush_threshold_size": "2gb",
+            "index.merge.scheduler.max_thread_count": 1,
+            "analysis": {
+                "analyzer": {
+                    "part_number_analyzer": {
+                        "type": "custom",
+                        "tokenizer": "keyword",
+                        "filter": ["lowercase"]
+                    },
+                    "autocomplete_analyzer": {
+                        "type": "custom",
+                        "tokenizer": "standard",
+                        "filter": ["lowercase", "autocomplete_filter"]
+                    },
+                    "autocomplete_search_analyzer": {
+                        "type": "custom",
+                        "tokenizer": "standard",
+                        "filter": ["lowercase"]
+                    }
+                },
+                "filter": {
+                    "autocomplete_filter": {
+                        "type": "edge_ngram",
+                        "min_gram": 2,
+                        "max_gram": 20
+                    }
+                }
+            }
+        },
+        "mappings": { "properties": properties }
+    })
+}
+
+/// JSON Schema (draft 2020-12) for one NDJSON line
+pub fn ndjson_json_schema() -> Value {
+    let fields = record_fields();
+    let mut properties = Map::new();
+    for (name, sample) in &fields {
+        let mut prop = json!({ "type": json_schema_type(sample) });
+        if name == "importedAt" {
+            prop["format"] = json!("date-time");
+        }
+        properties.insert(name.clone(), prop);
+    }
+    let required: Vec<&String> = fields.keys().collect();
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "turbo-transform/part-record.schema.json",
+        "title": "PartRecord",
+        "description": "One line of turbo-transform .ndjson output",
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populated() -> Value {
+        let record = PartRecord {
+            part_number: "0 986 479 A",
+            description: "Brake disc",
+            supplier: "APMG",
+            price: 12.5,
+            quantity: 4,
+            stock: "in stock",
+            weight: 0.5,
+            volume: 0.25,
+            delivery_time: "3/6",
+            delivery_days: "3/6",
+            category: "Brakes",
+            subcategory: "Discs",
+            integration: "507f1f77bcf86cd799439011",
+            integration_name: "APMG",
+            file_name: "prices_AB4_part1.csv",
+            imported_at: "2024-05-01T00:00:00.000Z",
+            ..PartRecord::sample()
+        };
+        serde_json::to_value(record).unwrap()
+    }
+
+    #[test]
+    fn every_field_is_mapped_with_its_type() {
+        let record = populated();
+        let es = es_index_mapping(DEFAULT_SHARDS, DEFAULT_REPLICAS);
+        let es = &es["mappings"]["properties"];
+        let schema = ndjson_json_schema();
+        let properties = schema["properties"].as_object().unwrap();
+        let required: Vec<&str> = schema["required"].as_array().unwrap().iter().filter_map(Value::as_str).collect();
+
+        let record = record.as_object().unwrap();
+        assert_eq!(properties.len(), record.len(), "the schema has no fields the record lacks");
+        for (name, value) in record {
+            let (es_types, schema_type): (&[&str], &str) = match value {
+                Value::String(_) if name == "importedAt" => (&["date"], "string"),
+                Value::String(_) => (&["keyword", "text"], "string"),
+                Value::Number(n) if n.is_f64() => (&["float"], "number"),
+                Value::Number(_) => (&["integer"], "integer"),
+                other => panic!("{}: unexpected {}", name, other),
+            };
+            let es_type = es[name]["type"].as_str().unwrap_or_else(|| panic!("{} is not in the ES mapping", name));
+            assert!(es_types.contains(&es_type), "{}: ES type {}", name, es_type);
+            assert_eq!(properties[name]["type"], schema_type, "{}", name);
+            assert!(required.contains(&name.as_str()), "{} is not required", name);
+        }
+        assert_eq!(properties["importedAt"]["format"], "date-time");
+        assert_eq!(es["createdAt"]["type"], "date");
+        assert_eq!(es["partNumber"]["fields"]["text"]["analyzer"], "part_number_analyzer");
+    }
+}
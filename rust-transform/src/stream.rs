@@ -0,0 +1,206 @@
+// =============================================================================
+// Streaming mode — one CSV from any reader to one output on any writer
+// =============================================================================
+// No directories and no temporary files, so the transform can sit inside a
+// pipeline (FTP download | turbo-transform stream | mongoimport) or behind
+// Node streams. The records are exactly those `Transformer` writes for a file
+// with the same name; the output is one of:
+//
+//   Ndjson   <stem>.ndjson lines, for mongoimport
+//   Bson     concatenated BSON documents, for mongorestore
+//   Bulk     _bulk body (action line + document), for POST /_bulk
+// =============================================================================
+
+use crate::bson_out;
+use crate::bulk_action::{BulkAction, BulkActionConfig};
+use crate::mapping::MappingConfig;
+use crate::reader::{Records, RowStats};
+use std::io::{BufWriter, Read, Write};
+
+pub enum StreamFormat {
+    Ndjson,
+    Bson,
+    Bulk { es_index: String, action: BulkActionConfig },
+}
+
+impl StreamFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamFormat::Ndjson => "ndjson",
+            StreamFormat::Bson => "bson",
+            StreamFormat::Bulk { .. } => "bulk",
+        }
+    }
+}
+
+pub struct StreamConfig {
+    pub mapping: MappingConfig,
+    /// Stamped on every record as fileName; the fallback stock code comes
+    /// from it unless `stock_code` is set
+    pub file_name: String,
+    pub stock_code: Option<String>,
+    pub format: StreamFormat,
+}
+
+pub struct StreamSummary {
+    /// Records written to `output`; one that fails to encode is not counted
+    pub records: u64,
+    pub bytes_written: u64,
+    pub rows: RowStats,
+}
+
+/// Transform the CSV read from `input` into `output`. Fails on an unusable
+/// header or when `output` stops accepting writes (e.g. a closed pipe).
+pub fn run(config: &StreamConfig, input: impl Read, output: impl Write) -> Result<StreamSummary, String> {
+    let bulk_action = match config.format {
+        StreamFormat::Bulk { ref es_index, ref action } => {
+            action.validate()?;
+            Some(BulkAction::new(action, es_index, config.mapping.imported_at_millis))
+        }
+        _ => None,
+    };
+    let integration_oid = match config.format {
+        StreamFormat::Bson => bson_out::parse_object_id(&config.mapping.integration_id),
+        _ => None,
+    };
+
+    let mut records = Records::new(input, &config.file_name, &config.mapping)?;
+    if let Some(ref code) = config.stock_code {
+        records = records.with_stock_code(code);
+    }
+    let mut out = BufWriter::with_capacity(1024 * 1024, output);
+
+    // Reusable serialization buffers, as in Transformer
+    let mut doc_buf = Vec::with_capacity(1024);
+    let mut action_buf = Vec::with_capacity(256);
+    let mut id_buf = String::with_capacity(64);
+    let mut record_count: u64 = 0;
+    let mut bytes_written: u64 = 0;
+
+    while let Some(doc) = records.next_record() {
+        doc_buf.clear();
+        let encoded = match (&config.format, &bulk_action) {
+            (StreamFormat::Bson, _) => {
+                bson_out::encode_part(&mut doc_buf, &doc, integration_oid.as_ref(), config.mapping.imported_at_millis);
+                true
+            }
+            (StreamFormat::Bulk { .. }, Some(action)) => {
+                let es_doc = doc.es();
+                let ok = action.write_doc(&mut doc_buf, &es_doc).is_ok();
+                if ok {
+                    action.write_action(&mut action_buf, &mut id_buf, &es_doc);
+                }
+                ok
+            }
+            _ => doc.write_ndjson(&mut doc_buf).is_ok(),
+        };
+        if !encoded {
+            continue;
+        }
+        if bulk_action.is_some() {
+            out.write_all(&action_buf).map_err(|e| format!("write failed: {}", e))?;
+            bytes_written += action_buf.len() as u64;
+        }
+        out.write_all(&doc_buf).map_err(|e| format!("write failed: {}", e))?;
+        bytes_written += doc_buf.len() as u64;
+        record_count += 1;
+    }
+    out.flush().map_err(|e| format!("write failed: {}", e))?;
+
+    Ok(StreamSummary {
+        records: record_count,
+        bytes_written,
+        rows: *records.stats(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bulk_action::DocField;
+    use serde_json::Value;
+
+    const CSV: &str = "part_number;brand;price;stock code\nP1;BOSCH;9,50;\nP2;TRW;12.25;DS9\n;NONE;1\n";
+
+    fn config(file_name: &str, stock_code: Option<&str>, format: StreamFormat) -> StreamConfig {
+        StreamConfig {
+            mapping: MappingConfig::new("507f1f77bcf86cd799439011", "APMG"),
+            file_name: file_name.to_string(),
+            stock_code: stock_code.map(str::to_string),
+            format,
+        }
+    }
+
+    fn lines(config: &StreamConfig, csv: &str) -> (StreamSummary, Vec<Value>) {
+        let mut out = Vec::new();
+        let summary = run(config, csv.as_bytes(), &mut out).unwrap();
+        assert_eq!(summary.bytes_written, out.len() as u64);
+        let lines = out.split(|&b| b == b'\n').filter(|l| !l.is_empty());
+        (summary, lines.map(|l| serde_json::from_slice(l).unwrap()).collect())
+    }
+
+    #[test]
+    fn ndjson_takes_the_stock_code_from_the_file_name() {
+        let (summary, docs) = lines(&config("prices_AB4_part1.csv", None, StreamFormat::Ndjson), CSV);
+        assert_eq!(summary.records, 2);
+        assert_eq!(summary.rows.rows, 3);
+        assert_eq!(summary.rows.missing_part_number, 1);
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0]["partNumber"], "P1");
+        assert_eq!(docs[0]["price"], 9.5);
+        assert_eq!(docs[0]["fileName"], "prices_AB4_part1.csv");
+        assert_eq!(docs[0]["integration"], "507f1f77bcf86cd799439011");
+        // The file name fills in a missing stock code; the row's own one wins
+        assert_eq!(docs[0]["stockCode"], "AB4");
+        assert_eq!(docs[1]["stockCode"], "DS9");
+        assert_eq!(docs[1]["price"], 12.25);
+    }
+
+    #[test]
+    fn stock_code_hint_overrides_the_file_name() {
+        let (_, docs) = lines(&config("prices_AB4_part1.csv", Some("XY7"), StreamFormat::Ndjson), CSV);
+        assert_eq!(docs[0]["stockCode"], "XY7");
+        assert_eq!(docs[1]["stockCode"], "DS9");
+    }
+
+    #[test]
+    fn bulk_writes_an_action_line_before_every_document() {
+        let format = StreamFormat::Bulk {
+            es_index: "parts".to_string(),
+            action: BulkActionConfig { id_fields: vec![DocField::PartNumber], ..BulkActionConfig::default() },
+        };
+        let (summary, lines) = lines(&config("prices_AB4_part1.csv", None, format), CSV);
+        assert_eq!(summary.records, 2);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["index"]["_index"], "parts");
+        assert_eq!(lines[0]["index"]["_id"], "P1");
+        assert_eq!(lines[1]["partNumber"], "P1");
+        assert_eq!(lines[1]["stockCode"], "AB4");
+        assert_eq!(lines[1]["fileName"], "prices_AB4_part1.csv");
+        assert!(lines[1].get("importedAt").is_none());
+        assert_eq!(lines[2]["index"]["_id"], "P2");
+        assert_eq!(lines[3]["stockCode"], "DS9");
+    }
+
+    #[test]
+    fn no_records_is_not_an_error() {
+        let (summary, docs) = lines(&config("empty.csv", None, StreamFormat::Ndjson), "part_number;price\n");
+        assert_eq!(summary.records, 0);
+        assert!(docs.is_empty());
+    }
+
+    #[test]
+    fn closed_output_fails_the_stream() {
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let err = run(&config("a.csv", None, StreamFormat::Ndjson), CSV.as_bytes(), Closed).err().unwrap();
+        assert!(err.starts_with("write failed"), "{}", err);
+    }
+}
@@ -0,0 +1,14 @@
+// =============================================================================
+// Test helpers shared by the library's and the command line's test modules
+// =============================================================================
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A fresh, empty turbo-<name>-<pid> directory under the system temp dir
+pub(crate) fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("turbo-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
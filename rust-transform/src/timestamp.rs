@@ -0,0 +1,49 @@
+// =============================================================================
+// Minimal ISO8601 timestamp without pulling in chrono crate
+// =============================================================================
+
+use std::time::{Duration, SystemTime};
+
+/// Milliseconds since the Unix epoch
+pub fn epoch_millis_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_millis() as u64
+}
+
+/// UTC "YYYY-MM-DDTHH:MM:SS.mmmZ", the importedAt format
+pub fn iso8601(epoch_millis: u64) -> String {
+    let secs = epoch_millis / 1000;
+
+    // Calculate UTC date/time components
+    let days = secs / 86400;
+    let time_of_day = secs % 86400;
+    let hours = time_of_day / 3600;
+    let minutes = (time_of_day % 3600) / 60;
+    let seconds = time_of_day % 60;
+    let millis = epoch_millis % 1000;
+
+    // Days since epoch to Y-M-D (simplified Gregorian)
+    let (year, month, day) = epoch_days_to_ymd(days as i64);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day, hours, minutes, seconds, millis
+    )
+}
+
+fn epoch_days_to_ymd(mut days: i64) -> (i64, u32, u32) {
+    // Algorithm from Howard Hinnant
+    days += 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let doe = (days - era * 146097) as u32;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = if m <= 2 { y + 1 } else { y };
+    (y, m, d)
+}
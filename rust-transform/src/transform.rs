@@ -0,0 +1,1297 @@
+// =============================================================================
+// Transformer — CSV files → NDJSON/BSON + ES _bulk (+ optional sinks)
+// =============================================================================
+// One Transformer per run. `run` processes files in parallel, one rayon
+// thread per file; each thread streams Records into every configured sink
+// in a single pass:
+//
+//   output_dir/<stem>.ndjson | <stem>.bson   always (mongoimport/mongorestore;
+//                                            .bson with <stem>.metadata.json)
+//   output_dir/<stem>.bulk[.NNN]             unless an EsSink is configured
+//   ES _bulk API                             with an EsSink
+//   <parquet_dir>/.../<stem>.parquet         with a parquet dir
+//   output_dir/parts_best_offer.ndjson       with best-offer aggregation
+//   output_dir/<run>.profile.json            with a profile run name
+//
+// With an input root, <stem> is the file's path relative to it minus the
+// extension (supplier/2024-05-01/parts), so the outputs mirror the input
+// tree and same-named files in different folders never share an output;
+// events name files by that relative path too. Parquet and dead-letter
+// files flatten it to supplier__2024-05-01__parts. Stems that would still
+// share an output — equal ignoring case (parts.csv and parts.CSV), or once
+// flattened — are detected before any file is opened; each of those files
+// gets "-<hash of its relative path>" appended (stem_collision), so its
+// outputs do not depend on which other files are in the run. A resumed run
+// also keeps off the stems the checkpoint's other files own, so a watch
+// batch never overwrites an earlier batch's outputs. file_done lists every
+// output path of a file.
+//
+// In strict mode every file is also checked against QualityThresholds; a
+// file that fails has its outputs deleted and is reported as file_rejected
+// instead of file_done.
+//
+// With a cancel flag, setting it stops the run: files not yet started are
+// not opened, files being read stop at the next PROGRESS_BATCH and have
+// their outputs deleted (file_cancelled), finished files stay as they are
+// and the cross-file passes are skipped. With a checkpoint path, every file
+// whose outputs are complete is recorded there, and a resumed run skips the
+// files it lists (file_skipped). What a deleted file already handed on
+// stays: .bulk chunks announced by bulk_chunk, and _bulk requests sent to an
+// EsSink (its pending batch is dropped, not sent).
+//
+// Files are fault-isolated: a panic while a file is processed (a parser bug
+// hit by one odd row) becomes that file's FileResult error, its outputs are
+// deleted, and every other file completes normally. A failed NDJSON/BSON
+// write or flush (a full disk) fails the file the same way, so a truncated
+// output is never recorded in the checkpoint. Every file error is also
+// emitted as file_failed.
+//
+// Progress events (file_done, bulk_chunk, best_offer, ...) are typed Events
+// handed to the `on_event` callback; nothing is printed by the library itself.
+// Event::to_json gives the wire format. With a progress interval, a reporter
+// thread also emits a `progress` heartbeat for the run and every open file,
+// so a multi-GB file is never silent until it finishes.
+// =============================================================================
+
+use crate::best_offer::{self, BestOfferConfig, OfferGroups};
+use crate::bson_out;
+use crate::bulk_action::{BulkAction, BulkActionConfig};
+use crate::bulk_file::{BulkFileWriter, BulkLimits};
+use crate::checkpoint::{Checkpoint, CheckpointEntry, CheckpointTarget};
+use crate::discover;
+use crate::es_sink::{EsCounts, EsSink, EsSinkConfig};
+use crate::events::{Event, FileOutputs, FileProgressInfo, RowRejects};
+use crate::hash::hash_bytes;
+use crate::mapping::MappingConfig;
+use crate::parquet_sink::{ParquetConfig, ParquetFileSink};
+use crate::profile::{self, FileProfile};
+use crate::quality::{QualityReport, QualityStats, QualityThresholds};
+use crate::reader::Records;
+use crate::record::RecordJson;
+use rayon::prelude::*;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Receives each progress event
+pub type EventHandler = dyn Fn(&Event) + Send + Sync;
+
+// =============================================================================
+// Per-file result
+// =============================================================================
+#[derive(Clone, Debug)]
+pub struct FileResult {
+    pub file_name: String,
+    pub records: u64,
+    pub ndjson_bytes: u64,
+    pub bson_bytes: u64,
+    pub bulk_bytes: u64,
+    pub es_indexed: u64,
+    pub es_failed: u64,
+    pub es_unconfirmed: u64,
+    pub parquet_bytes: u64,
+    pub duration_ms: u64,
+    /// Set when the file was skipped or an output failed part-way
+    pub error: Option<String>,
+    /// Quality metrics, in strict mode
+    pub quality: Option<QualityReport>,
+    /// Files written, when the file is done (by the earlier run, when skipped)
+    pub outputs: FileOutputs,
+    /// Not started, or rolled back, because the run was cancelled
+    pub cancelled: bool,
+    /// Complete in the checkpoint of a resumed run, so not read again
+    pub skipped: bool,
+}
+
+impl FileResult {
+    fn empty(file_name: String, start: Instant) -> Self {
+        FileResult {
+            file_name,
+            records: 0,
+            ndjson_bytes: 0,
+            bson_bytes: 0,
+            bulk_bytes: 0,
+            es_indexed: 0,
+            es_failed: 0,
+            es_unconfirmed: 0,
+            parquet_bytes: 0,
+            duration_ms: start.elapsed().as_millis() as u64,
+            error: None,
+            quality: None,
+            outputs: FileOutputs::default(),
+            cancelled: false,
+            skipped: false,
+        }
+    }
+
+    fn failed(file_name: String, start: Instant, error: String) -> Self {
+        FileResult {
+            error: Some(error),
+            ..FileResult::empty(file_name, start)
+        }
+    }
+
+    fn cancelled(file_name: String, start: Instant) -> Self {
+        FileResult {
+            cancelled: true,
+            ..FileResult::empty(file_name, start)
+        }
+    }
+}
+
+/// Outcome of `Transformer::run`
+#[derive(Clone, Debug)]
+pub struct RunSummary {
+    /// One per input file, in input order
+    pub files: Vec<FileResult>,
+    /// Set when parts_best_offer.ndjson could not be written
+    pub best_offer_error: Option<String>,
+    /// Set when <run>.profile.json could not be written
+    pub profile_error: Option<String>,
+    /// Set when the checkpoint could not be written
+    pub checkpoint_error: Option<String>,
+    /// The cancel flag was raised; the cross-file passes were skipped
+    pub cancelled: bool,
+}
+
+// =============================================================================
+// Builder
+// =============================================================================
+pub struct TransformerBuilder {
+    mapping: MappingConfig,
+    output_dir: PathBuf,
+    es_index: String,
+    bulk_action: BulkActionConfig,
+    bulk_limits: BulkLimits,
+    bson: bool,
+    es_sink: Option<EsSinkConfig>,
+    parquet_dir: Option<PathBuf>,
+    best_offer: Option<BestOfferConfig>,
+    strict: Option<QualityThresholds>,
+    profile: Option<String>,
+    progress_interval: Option<Duration>,
+    cancel: Option<Arc<AtomicBool>>,
+    checkpoint: Option<(PathBuf, bool)>,
+    input_root: Option<PathBuf>,
+    on_event: Option<Box<EventHandler>>,
+}
+
+impl TransformerBuilder {
+    /// Index name for .bulk action lines (default automotive_parts)
+    pub fn es_index(mut self, name: &str) -> Self {
+        self.es_index = name.to_string();
+        self
+    }
+
+    /// Op type, _id, routing, pipeline and versioning of the action lines
+    pub fn bulk_action(mut self, config: BulkActionConfig) -> Self {
+        self.bulk_action = config;
+        self
+    }
+
+    /// Roll .bulk output over into numbered chunks
+    pub fn bulk_limits(mut self, limits: BulkLimits) -> Self {
+        self.bulk_limits = limits;
+        self
+    }
+
+    /// Write <stem>.bson + <stem>.metadata.json instead of <stem>.ndjson
+    pub fn bson(mut self, enabled: bool) -> Self {
+        self.bson = enabled;
+        self
+    }
+
+    /// Stream _bulk bodies to ES instead of writing .bulk files
+    pub fn es_sink(mut self, config: EsSinkConfig) -> Self {
+        self.es_sink = Some(config);
+        self
+    }
+
+    /// Also write partitioned Parquet under `dir`
+    pub fn parquet_dir(mut self, dir: PathBuf) -> Self {
+        self.parquet_dir = Some(dir);
+        self
+    }
+
+    /// Aggregate the best offer per group across all files of a run
+    pub fn best_offer(mut self, config: BestOfferConfig) -> Self {
+        self.best_offer = Some(config);
+        self
+    }
+
+    /// Reject (and delete the output of) files that fail these thresholds
+    pub fn strict(mut self, thresholds: QualityThresholds) -> Self {
+        self.strict = Some(thresholds);
+        self
+    }
+
+    /// Profile every field of every file into <output_dir>/<run>.profile.json
+    pub fn profile(mut self, run: &str) -> Self {
+        self.profile = Some(run.to_string());
+        self
+    }
+
+    /// Emit a `progress` heartbeat this often while files are being read
+    pub fn progress_interval(mut self, interval: Duration) -> Self {
+        self.progress_interval = Some(interval).filter(|d| !d.is_zero());
+        self
+    }
+
+    /// Stop the run gracefully once `flag` is set (e.g. from a signal handler)
+    pub fn cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(flag);
+        self
+    }
+
+    /// Record completed files in the checkpoint at `path`; with `resume`,
+    /// skip the files an existing checkpoint already lists as complete
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>, resume: bool) -> Self {
+        self.checkpoint = Some((path.into(), resume));
+        self
+    }
+
+    /// Name files by their path relative to `dir` and mirror it in the outputs
+    pub fn input_root(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.input_root = Some(dir.into());
+        self
+    }
+
+    /// Progress event callback, called from the file threads
+    pub fn on_event(mut self, handler: impl Fn(&Event) + Send + Sync + 'static) -> Self {
+        self.on_event = Some(Box::new(handler));
+        self
+    }
+
+    /// Validate the configuration and prepare the sinks
+    pub fn build(self) -> Result<Transformer, String> {
+        if self.es_sink.is_some() && self.bulk_limits.is_chunked() {
+            return Err("bulk chunk limits apply to .bulk files and cannot be combined with an ES sink".into());
+        }
+        self.bulk_action.validate()?;
+        if self.strict.is_some() && (self.es_sink.is_some() || self.bulk_limits.is_chunked()) {
+            return Err("strict mode discards a failing file's output, so it cannot be combined with an ES sink or chunked .bulk output".into());
+        }
+        let resume = self.checkpoint.as_ref().is_some_and(|(_, resume)| *resume);
+        if resume && (self.best_offer.is_some() || self.profile.is_some()) {
+            return Err("best offers and profiles cover every file of a run, so they cannot be combined with resuming from a checkpoint".into());
+        }
+        let target = CheckpointTarget {
+            output_dir: self.output_dir.display().to_string(),
+            es_index: self.es_index.clone(),
+            es_op: self.bulk_action.op.name().to_string(),
+            bson: self.bson,
+            es_url: self.es_sink.as_ref().map(|c| c.url.clone()),
+            parquet_dir: self.parquet_dir.as_ref().map(|d| d.display().to_string()),
+        };
+        let checkpoint = match self.checkpoint {
+            Some((path, true)) => {
+                let previous = match Checkpoint::load(&path)? {
+                    Some(c) if c.integration_id != self.mapping.integration_id => {
+                        return Err(format!(
+                            "{} belongs to integration {}, not {}",
+                            path.display(),
+                            c.integration_id,
+                            self.mapping.integration_id
+                        ));
+                    }
+                    Some(c) => match c.target.mismatch(&target) {
+                        Some(diff) => {
+                            return Err(format!(
+                                "{} was written by a run with other outputs ({}), so it cannot be resumed",
+                                path.display(),
+                                diff
+                            ));
+                        }
+                        None => c,
+                    },
+                    None => Checkpoint::new(&self.mapping.integration_id, target),
+                };
+                Some((path, previous))
+            }
+            Some((path, false)) => Some((path, Checkpoint::new(&self.mapping.integration_id, target))),
+            None => None,
+        };
+
+        // --bson: integration as a real ObjectId
+        let integration_oid = if self.bson {
+            bson_out::parse_object_id(&self.mapping.integration_id)
+        } else {
+            None
+        };
+        let parquet = self.parquet_dir.map(ParquetConfig::new).transpose()?;
+
+        Ok(Transformer {
+            bulk_action: BulkAction::new(&self.bulk_action, &self.es_index, self.mapping.imported_at_millis),
+            mapping: self.mapping,
+            output_dir: self.output_dir,
+            bulk_limits: self.bulk_limits,
+            bson: self.bson,
+            integration_oid,
+            es_sink: self.es_sink.map(EsSink::new),
+            parquet,
+            best_offer: self.best_offer,
+            strict: self.strict,
+            profile: self.profile,
+            progress_interval: self.progress_interval,
+            cancel: self.cancel,
+            checkpoint,
+            input_root: self.input_root,
+            on_event: self.on_event,
+        })
+    }
+}
+
+// =============================================================================
+// Transformer
+// =============================================================================
+pub struct Transformer {
+    mapping: MappingConfig,
+    output_dir: PathBuf,
+    bulk_action: BulkAction,
+    bulk_limits: BulkLimits,
+    bson: bool,
+    /// Some(oid) in BSON mode when integration_id is a valid ObjectId
+    integration_oid: Option<[u8; 12]>,
+    es_sink: Option<EsSink>,
+    parquet: Option<ParquetConfig>,
+    best_offer: Option<BestOfferConfig>,
+    strict: Option<QualityThresholds>,
+    /// Run name of the profile, when profiling
+    profile: Option<String>,
+    progress_interval: Option<Duration>,
+    cancel: Option<Arc<AtomicBool>>,
+    /// Checkpoint path and the checkpoint this run resumes from (empty if not resuming)
+    checkpoint: Option<(PathBuf, Checkpoint)>,
+    input_root: Option<PathBuf>,
+    on_event: Option<Box<EventHandler>>,
+}
+
+/// Counters shared by every file thread of one run
+struct RunState {
+    started: Instant,
+    completed_files: AtomicUsize,
+    total_files: usize,
+    /// One per input file, in input order
+    files: Vec<FileProgress>,
+    offer_groups: Mutex<OfferGroups>,
+    profiles: Mutex<Vec<FileProfile>>,
+    /// Completed files so far, and the last error saving them
+    checkpoint: Mutex<(Checkpoint, Option<String>)>,
+}
+
+/// Records are published to the reporter thread in batches of this many
+const PROGRESS_BATCH: u64 = 4096;
+
+/// Live counters of one file, read by the progress reporter
+struct FileProgress {
+    /// Relative to the input root
+    file_name: String,
+    /// Output path relative to the output directory, minus the extension
+    stem: String,
+    size: u64,
+    bytes_read: AtomicU64,
+    records: AtomicU64,
+    started: OnceLock<Instant>,
+    done: AtomicBool,
+}
+
+impl FileProgress {
+    fn new(path: &Path, file_name: String, stem: String) -> Self {
+        FileProgress {
+            file_name,
+            stem,
+            size: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            bytes_read: AtomicU64::new(0),
+            records: AtomicU64::new(0),
+            started: OnceLock::new(),
+            done: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Counts the bytes pulled from the CSV file (in read-buffer sized steps)
+struct CountingReader<'a, R> {
+    inner: R,
+    bytes_read: &'a AtomicU64,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Key under which two stems would share an output file
+fn stem_key(stem: &str) -> String {
+    stem.replace('/', "__").to_ascii_lowercase()
+}
+
+/// Output stem of every file (its relative name minus the extension), with
+/// colliding stems disambiguated; also returns the groups that collided.
+/// `reserved` are the stem_keys of outputs other files already own (earlier
+/// runs against the same checkpoint): a stem meeting one collides too.
+fn assign_stems(names: &[String], reserved: &HashSet<String>) -> (Vec<String>, Vec<Vec<usize>>) {
+    let mut stems: Vec<String> = names
+        .iter()
+        .map(|n| Path::new(n).with_extension("").to_string_lossy().to_string())
+        .collect();
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, stem) in stems.iter().enumerate() {
+        groups.entry(stem_key(stem)).or_default().push(i);
+    }
+    let mut collisions: Vec<Vec<usize>> = groups
+        .into_iter()
+        .filter(|(key, g)| g.len() > 1 || reserved.contains(key))
+        .map(|(_, g)| g)
+        .collect();
+    for group in &mut collisions {
+        group.sort_by(|&a, &b| names[a].cmp(&names[b]));
+        for &i in group.iter() {
+            stems[i] = format!("{}-{:08x}", stems[i], hash_bytes(names[i].as_bytes()) as u32);
+        }
+    }
+    collisions.sort_by(|a, b| names[a[0]].cmp(&names[b[0]]));
+
+    // A hashed stem can still meet a real one ("parts-1a2b3c4d.csv"):
+    // number the later ones, in name order
+    let mut order: Vec<usize> = (0..names.len()).collect();
+    order.sort_by(|&a, &b| names[a].cmp(&names[b]));
+    let mut taken = reserved.clone();
+    for i in order {
+        let base = stems[i].clone();
+        let mut n = 2;
+        while !taken.insert(stem_key(&stems[i])) {
+            stems[i] = format!("{}-{}", base, n);
+            n += 1;
+        }
+    }
+    (stems, collisions)
+}
+
+/// The message of a caught panic
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// Records per second and time left, from `done` of `total` bytes so far
+fn rate_and_eta(records: u64, done: u64, total: u64, elapsed: Duration) -> (u64, Option<u64>) {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 { (records as f64 / secs) as u64 } else { 0 };
+    let eta_ms = (done > 0 && secs > 0.0).then(|| {
+        let left = total.saturating_sub(done) as f64;
+        (left / (done as f64 / secs) * 1000.0) as u64
+    });
+    (rate, eta_ms)
+}
+
+impl Transformer {
+    /// Start configuring a run that writes into `output_dir` (which must exist)
+    pub fn builder(mapping: MappingConfig, output_dir: impl Into<PathBuf>) -> TransformerBuilder {
+        TransformerBuilder {
+            mapping,
+            output_dir: output_dir.into(),
+            es_index: "automotive_parts".into(),
+            bulk_action: BulkActionConfig::default(),
+            bulk_limits: BulkLimits::default(),
+            bson: false,
+            es_sink: None,
+            parquet_dir: None,
+            best_offer: None,
+            strict: None,
+            profile: None,
+            progress_interval: None,
+            cancel: None,
+            checkpoint: None,
+            input_root: None,
+            on_event: None,
+        }
+    }
+
+    pub fn mapping(&self) -> &MappingConfig {
+        &self.mapping
+    }
+
+    /// Process every file in parallel, then run the cross-file passes
+    pub fn run(&self, files: &[PathBuf]) -> RunSummary {
+        let names: Vec<String> = files
+            .iter()
+            .map(|p| discover::relative_name(self.input_root.as_deref(), p))
+            .collect();
+        let (stems, collisions) = assign_stems(&names, &self.reserved_stems(files));
+        for group in collisions {
+            self.emit(Event::StemCollision {
+                stem: Path::new(&names[group[0]]).with_extension("").to_string_lossy().to_string(),
+                files: group.iter().map(|&i| names[i].clone()).collect(),
+                stems: group.iter().map(|&i| stems[i].clone()).collect(),
+            });
+        }
+
+        let state = RunState {
+            started: Instant::now(),
+            completed_files: AtomicUsize::new(0),
+            total_files: files.len(),
+            files: files
+                .iter()
+                .zip(names)
+                .zip(stems)
+                .map(|((path, name), stem)| FileProgress::new(path, name, stem))
+                .collect(),
+            offer_groups: Mutex::new(OfferGroups::default()),
+            profiles: Mutex::new(Vec::new()),
+            checkpoint: Mutex::new((self.resumed().cloned().unwrap_or_default(), None)),
+        };
+        // Replace a stale checkpoint from an earlier run right away
+        self.save_checkpoint(&state);
+
+        let results: Vec<FileResult> = thread::scope(|scope| {
+            // Heartbeat reporter — stops when the sender is dropped below
+            let (stop, stopped) = mpsc::channel::<()>();
+            if let Some(interval) = self.progress_interval {
+                let state = &state;
+                scope.spawn(move || {
+                    while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                        self.emit(self.progress_event(state));
+                    }
+                });
+            }
+
+            // PARALLEL PROCESSING — one file per rayon thread
+            let results = files
+                .par_iter()
+                .zip(&state.files)
+                .map(|(csv_path, progress)| {
+                    let result = if let Some(entry) = self.resumed().and_then(|c| c.completed(csv_path)) {
+                        self.skip_file(progress, entry, &state)
+                    } else if self.is_cancelled() {
+                        FileResult::cancelled(progress.file_name.clone(), Instant::now())
+                    } else {
+                        // Backstop for panics outside the record loop (which
+                        // process_file catches itself, to roll back outputs)
+                        panic::catch_unwind(AssertUnwindSafe(|| self.process_file(csv_path, progress, &state)))
+                            .unwrap_or_else(|payload| {
+                                let start = progress.started.get().copied().unwrap_or_else(Instant::now);
+                                let error = format!("panicked: {}", panic_message(&*payload));
+                                FileResult::failed(progress.file_name.clone(), start, error)
+                            })
+                    };
+                    progress.done.store(true, Ordering::Relaxed);
+                    let rejected = result.quality.as_ref().is_some_and(|q| !q.passed());
+                    if let (Some(error), false) = (&result.error, rejected) {
+                        self.emit(Event::FileFailed {
+                            file: result.file_name.clone(),
+                            error: error.clone(),
+                            duration_ms: result.duration_ms,
+                        });
+                    }
+                    result
+                })
+                .collect();
+            drop(stop);
+            results
+        });
+
+        let cancelled = self.is_cancelled();
+        let checkpoint_error = state.checkpoint.into_inner().unwrap_or_else(|e| e.into_inner()).1;
+
+        // Cross-file aggregation pass — meaningless over part of the files
+        let mut best_offer_error = None;
+        if self.best_offer.is_some() && !cancelled {
+            let groups = state.offer_groups.into_inner().unwrap_or_else(|e| e.into_inner());
+            match groups.write(&self.output_dir) {
+                Ok((groups, offers, bytes)) => self.emit(Event::BestOffer {
+                    path: self.output_dir.join(best_offer::OUTPUT_NAME).display().to_string(),
+                    groups,
+                    offers,
+                    bytes,
+                }),
+                Err(e) => best_offer_error = Some(format!("{}: {}", best_offer::OUTPUT_NAME, e)),
+            }
+        }
+
+        let mut profile_error = None;
+        if let (Some(run), false) = (self.profile.as_ref(), cancelled) {
+            let mut profiles = state.profiles.into_inner().unwrap_or_else(|e| e.into_inner());
+            profiles.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+            match profile::write(&self.output_dir, run, self.mapping.imported_at(), &profiles) {
+                Ok(path) => self.emit(Event::Profile {
+                    path,
+                    files: profiles.len(),
+                }),
+                Err(e) => profile_error = Some(e),
+            }
+        }
+
+        RunSummary {
+            files: results,
+            best_offer_error,
+            profile_error,
+            checkpoint_error,
+            cancelled,
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// The checkpoint this run resumes from
+    fn resumed(&self) -> Option<&Checkpoint> {
+        self.checkpoint.as_ref().map(|(_, c)| c)
+    }
+
+    /// Stems the resumed checkpoint's files own, apart from `files` (which
+    /// are rewritten in place when they come again)
+    fn reserved_stems(&self, files: &[PathBuf]) -> HashSet<String> {
+        let Some(checkpoint) = self.resumed() else {
+            return HashSet::new();
+        };
+        let ours: HashSet<String> = files.iter().map(|p| p.display().to_string()).collect();
+        checkpoint
+            .files
+            .iter()
+            .filter(|(path, entry)| !ours.contains(*path) && !entry.stem.is_empty())
+            .map(|(_, entry)| stem_key(&entry.stem))
+            .collect()
+    }
+
+    /// A file the resumed checkpoint lists as complete and unchanged
+    fn skip_file(&self, progress: &FileProgress, entry: &CheckpointEntry, state: &RunState) -> FileResult {
+        state.completed_files.fetch_add(1, Ordering::Relaxed);
+        self.emit(Event::FileSkipped {
+            file: progress.file_name.clone(),
+            records: entry.records,
+            outputs: entry.outputs.clone(),
+        });
+        FileResult {
+            skipped: true,
+            outputs: entry.outputs.clone(),
+            ..FileResult::empty(progress.file_name.clone(), Instant::now())
+        }
+    }
+
+    /// Write the run's checkpoint; a failure is kept for the RunSummary
+    fn save_checkpoint(&self, state: &RunState) {
+        let Some((ref path, _)) = self.checkpoint else {
+            return;
+        };
+        let mut guard = state.checkpoint.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = guard.0.save(path) {
+            guard.1 = Some(e);
+        }
+    }
+
+    fn mark_completed(&self, state: &RunState, csv_path: &Path, records: u64, stem: &str, outputs: &FileOutputs) {
+        if self.checkpoint.is_some() {
+            state
+                .checkpoint
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .0
+                .mark_completed(csv_path, records, stem, outputs.clone());
+            self.save_checkpoint(state);
+        }
+    }
+
+    /// Snapshot of the run and of every file currently being read
+    fn progress_event(&self, state: &RunState) -> Event {
+        let mut bytes_read = 0;
+        let mut bytes_total = 0;
+        let mut records = 0;
+        let mut open = Vec::new();
+        for f in &state.files {
+            let done = f.done.load(Ordering::Relaxed);
+            let read = if done { f.size } else { f.bytes_read.load(Ordering::Relaxed).min(f.size) };
+            let n = f.records.load(Ordering::Relaxed);
+            bytes_read += read;
+            bytes_total += f.size;
+            records += n;
+            if let (Some(started), false) = (f.started.get(), done) {
+                let (rate_per_sec, eta_ms) = rate_and_eta(n, read, f.size, started.elapsed());
+                open.push(FileProgressInfo {
+                    file: f.file_name.clone(),
+                    bytes_read: read,
+                    bytes_total: f.size,
+                    records: n,
+                    rate_per_sec,
+                    eta_ms,
+                });
+            }
+        }
+        let elapsed = state.started.elapsed();
+        let (rate_per_sec, eta_ms) = rate_and_eta(records, bytes_read, bytes_total, elapsed);
+        Event::Progress {
+            elapsed_ms: elapsed.as_millis() as u64,
+            bytes_read,
+            bytes_total,
+            records,
+            rate_per_sec,
+            eta_ms,
+            files_done: state.completed_files.load(Ordering::Relaxed),
+            files_total: state.total_files,
+            files: open,
+        }
+    }
+
+    /// Hand `event` to the on_event handler — also for events a caller
+    /// emits around a run, like `start`
+    pub fn emit(&self, event: Event) {
+        if let Some(ref handler) = self.on_event {
+            handler(&event);
+        }
+    }
+
+    // =========================================================================
+    // Process a single CSV file → NDJSON + ES .bulk
+    // =========================================================================
+    fn process_file(&self, csv_path: &Path, progress: &FileProgress, state: &RunState) -> FileResult {
+        // Events name the file by its relative path; records see the bare
+        // name (fileName, stock code from _<CODE>_partN)
+        let file_name = progress.file_name.clone();
+        let base_name = csv_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let start = *progress.started.get_or_init(Instant::now);
+
+        // Output path: dir/input.csv → dir/input.ndjson (or .bson) + dir/input.bulk
+        let rel_stem = &progress.stem;
+        let stem = rel_stem.replace('/', "__");
+        let mongo_ext = if self.bson { "bson" } else { "ndjson" };
+        let ndjson_path = self.output_dir.join(format!("{}.{}", rel_stem, mongo_ext));
+        let bulk_path = self.output_dir.join(format!("{}.bulk", rel_stem));
+        if let Some(dir) = ndjson_path.parent().filter(|_| rel_stem.contains('/')) {
+            if let Err(e) = fs::create_dir_all(dir) {
+                return FileResult::failed(file_name, start, format!("create {} failed: {}", dir.display(), e));
+            }
+        }
+
+        let file = match File::open(csv_path) {
+            Ok(f) => f,
+            Err(e) => {
+                return FileResult::failed(file_name, start, format!("open failed: {}", e));
+            }
+        };
+
+        // Header row → column map; files without a part number column are skipped
+        let file = CountingReader {
+            inner: file,
+            bytes_read: &progress.bytes_read,
+        };
+        let mut records = match Records::new(file, &base_name, &self.mapping) {
+            Ok(r) => r,
+            Err(e) => return FileResult::failed(file_name, start, e),
+        };
+
+        // Open NDJSON/BSON output — 1MB write buffer for large sequential writes
+        let ndjson_file = match File::create(&ndjson_path) {
+            Ok(f) => f,
+            Err(e) => {
+                return FileResult::failed(file_name, start, format!("create {} output failed: {}", mongo_ext, e));
+            }
+        };
+        let mut ndjson_writer = BufWriter::with_capacity(1024 * 1024, ndjson_file);
+
+        // ES _bulk body goes either to a .bulk file or straight to the cluster
+        let emit = |event: Event| self.emit(event);
+        let mut bulk_writer = None;
+        let mut es_stream = None;
+        match self.es_sink {
+            Some(ref sink) => es_stream = Some(sink.stream(&stem)),
+            None => match BulkFileWriter::create(bulk_path, self.bulk_limits, &file_name, &emit) {
+                Ok(w) => bulk_writer = Some(w),
+                Err(e) => {
+                    return FileResult::failed(file_name, start, format!("create bulk output failed: {}", e));
+                }
+            },
+        }
+
+        // Optional analytics copy, partitioned by integration/stockCode
+        let mut parquet_sink = self
+            .parquet
+            .as_ref()
+            .map(|cfg| ParquetFileSink::new(cfg, &stem, self.mapping.imported_at_millis));
+
+        // Per-file offer groups, merged into the run-wide map when the file is done
+        let mut offer_groups = self.best_offer.as_ref().map(|_| OfferGroups::default());
+        let mut quality = self.strict.map(|_| QualityStats::default());
+        let mut field_profile = self.profile.as_ref().map(|_| FileProfile::new(&file_name));
+
+        // Reusable serialization buffers — avoids per-record allocation.
+        // `json` encodes the fields NDJSON and ES share once for both
+        let mut json = RecordJson::default();
+        let mut ndjson_buf = Vec::with_capacity(1024);
+        let mut bulk_action_buf = Vec::with_capacity(256);
+        let mut bulk_id_buf = String::with_capacity(64);
+        let mut bulk_doc_buf = Vec::with_capacity(1024);
+
+        let mut record_count: u64 = 0;
+        let mut interrupted = false;
+        let mut write_error = None;
+        let mut mongo_bytes_written: u64 = 0;
+        let mut bulk_bytes_written: u64 = 0;
+
+        // Main loop — stream records one by one. A panic in here, or a
+        // failed NDJSON/BSON write (full disk), is caught so that only this
+        // file fails, with its outputs rolled back
+        let looped = panic::catch_unwind(AssertUnwindSafe(|| {
+            while let Some(doc) = records.next_record() {
+                let es_doc = doc.es();
+                let json_ok = json.encode(&doc).is_ok();
+
+                // Write NDJSON (for mongoimport) or BSON (for mongorestore)
+                if self.bson {
+                    ndjson_buf.clear();
+                    bson_out::encode_part(
+                        &mut ndjson_buf,
+                        &doc,
+                        self.integration_oid.as_ref(),
+                        self.mapping.imported_at_millis,
+                    );
+                    if let Err(e) = ndjson_writer.write_all(&ndjson_buf) {
+                        write_error = Some(e);
+                        break;
+                    }
+                    mongo_bytes_written += ndjson_buf.len() as u64;
+                } else if json_ok {
+                    let (fields, imported_at) = json.ndjson();
+                    if let Err(e) = ndjson_writer.write_all(fields).and_then(|_| ndjson_writer.write_all(imported_at)) {
+                        write_error = Some(e);
+                        break;
+                    }
+                    mongo_bytes_written += (fields.len() + imported_at.len()) as u64;
+                }
+
+                if let Some(ref mut sink) = parquet_sink {
+                    sink.push(&doc);
+                }
+                if let (Some(groups), Some(cfg)) = (offer_groups.as_mut(), self.best_offer.as_ref()) {
+                    groups.add(cfg, &doc, &es_doc);
+                }
+                if let Some(ref mut q) = quality {
+                    q.add(&doc);
+                }
+                if let Some(ref mut p) = field_profile {
+                    p.add(&doc);
+                }
+
+                // Write ES _bulk body (action line + document)
+                if json_ok {
+                    self.bulk_action.write_doc_json(&mut bulk_doc_buf, json.es());
+                    self.bulk_action.write_action(&mut bulk_action_buf, &mut bulk_id_buf, &es_doc);
+                    let action_n = bulk_action_buf.len();
+                    let doc_n = bulk_doc_buf.len();
+                    if let Some(ref mut stream) = es_stream {
+                        stream.push(&bulk_action_buf, &bulk_doc_buf);
+                        bulk_bytes_written += (action_n + doc_n) as u64;
+                    } else if let Some(ref mut w) = bulk_writer {
+                        if w.write_pair(&bulk_action_buf, &bulk_doc_buf) {
+                            bulk_bytes_written += (action_n + doc_n) as u64;
+                        }
+                    }
+                }
+
+                record_count += 1;
+
+                // Publish the count for the progress reporter
+                if record_count.is_multiple_of(PROGRESS_BATCH) {
+                    progress.records.store(record_count, Ordering::Relaxed);
+                    if self.is_cancelled() {
+                        interrupted = true;
+                        break;
+                    }
+                }
+            }
+        }));
+        let mut failure = match looped {
+            Err(payload) => Some(format!("panicked: {}", panic_message(&*payload))),
+            Ok(()) => write_error.map(|e| format!("write {} failed: {}", ndjson_path.display(), e)),
+        };
+        if failure.is_none() && !interrupted {
+            if let Err(e) = ndjson_writer.flush() {
+                failure = Some(format!("flush {} failed: {}", ndjson_path.display(), e));
+            }
+        }
+        if let Some(error) = failure {
+            drop(ndjson_writer);
+            let _ = fs::remove_file(&ndjson_path);
+            if let Some(sink) = parquet_sink {
+                sink.discard();
+            }
+            if let Some(w) = bulk_writer {
+                w.discard();
+            }
+            if let Some(stream) = es_stream {
+                let _ = stream.discard();
+            }
+            progress.records.store(record_count, Ordering::Relaxed);
+            return FileResult::failed(file_name, start, error);
+        }
+
+        // Strict mode: a file that fails a quality rule contributes nothing
+        let quality = match (quality, self.strict.as_ref()) {
+            (Some(q), Some(thresholds)) => Some(q.report(records.stats(), thresholds)),
+            _ => None,
+        };
+        let rejected = quality.as_ref().is_some_and(|q| !q.passed());
+        let discard = rejected || interrupted;
+
+        // Close the other writers / drain the ES stream
+        drop(ndjson_writer);
+        let mut es = EsCounts::default();
+        let mut parquet_bytes = 0;
+        let mut error = None;
+        let mut outputs = FileOutputs::default();
+        let ndjson_name = ndjson_path.display().to_string();
+        if self.bson {
+            outputs.bson = Some(ndjson_name);
+            if !discard {
+                // Indexes for mongorestore, which finds them by the data file's name
+                let path = self.output_dir.join(format!("{}.metadata.json", rel_stem));
+                match bson_out::write_metadata(&path, "parts") {
+                    Ok(()) => outputs.bson_metadata = Some(path.display().to_string()),
+                    Err(e) => error = Some(e),
+                }
+            }
+        } else {
+            outputs.ndjson = Some(ndjson_name);
+        }
+        if let Some(sink) = parquet_sink {
+            if discard {
+                sink.discard();
+            } else {
+                let paths = sink.paths();
+                match sink.finish() {
+                    Ok(n) => {
+                        parquet_bytes = n;
+                        outputs.parquet = paths.iter().map(|p| p.display().to_string()).collect();
+                    }
+                    Err(e) => error = Some(e),
+                }
+            }
+        }
+        if let Some(w) = bulk_writer {
+            if discard {
+                w.discard();
+            } else {
+                match w.finish() {
+                    Ok(paths) => outputs.bulk = paths.iter().map(|p| p.display().to_string()).collect(),
+                    Err(e) => error = Some(e),
+                }
+            }
+        }
+        if discard {
+            let _ = fs::remove_file(&ndjson_path);
+        }
+        if let Some(stream) = es_stream {
+            match if discard { stream.discard() } else { stream.finish() } {
+                Ok(counts) => es = counts,
+                Err(e) => error = Some(e),
+            }
+        }
+
+        progress.records.store(record_count, Ordering::Relaxed);
+        if interrupted {
+            self.emit(Event::FileCancelled {
+                file: file_name.clone(),
+                records: record_count,
+            });
+            return FileResult {
+                es_indexed: es.indexed,
+                es_failed: es.failed,
+                es_unconfirmed: es.unconfirmed,
+                ..FileResult::cancelled(file_name, start)
+            };
+        }
+        if let Some(error) = error {
+            // A late failure (flush, rename, sink finish) fails the whole file
+            // like one in the loop: its outputs go and only file_failed is
+            // sent. Announced .bulk chunks stay, as with BulkFileWriter::discard
+            let _ = fs::remove_file(&ndjson_path);
+            let bulk = outputs.bulk.iter().filter(|_| !self.bulk_limits.is_chunked());
+            for path in outputs.bson_metadata.iter().chain(bulk).chain(&outputs.parquet) {
+                let _ = fs::remove_file(path);
+            }
+            return FileResult {
+                es_indexed: es.indexed,
+                es_failed: es.failed,
+                es_unconfirmed: es.unconfirmed,
+                ..FileResult::failed(file_name, start, error)
+            };
+        }
+        if let (Some(groups), Some(cfg), false) = (offer_groups, self.best_offer.as_ref(), discard) {
+            state
+                .offer_groups
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .merge(cfg, groups);
+        }
+        if let Some(p) = field_profile {
+            state.profiles.lock().unwrap_or_else(|e| e.into_inner()).push(p);
+        }
+        let done = state.completed_files.fetch_add(1, Ordering::Relaxed) + 1;
+
+        let (ndjson_bytes, bson_bytes) = if self.bson {
+            (0, mongo_bytes_written)
+        } else {
+            (mongo_bytes_written, 0)
+        };
+
+        // Per-file progress (JSON, machine-readable)
+        let elapsed = start.elapsed();
+        let rate = if elapsed.as_secs() > 0 {
+            record_count / elapsed.as_secs()
+        } else {
+            record_count
+        };
+
+        let input_bytes = progress.size;
+        let stats = *records.stats();
+        let row_rejects = RowRejects {
+            malformed: stats.malformed,
+            missing_part_number: stats.missing_part_number,
+        };
+        let progress = format!("{}/{}", done, state.total_files);
+        if rejected {
+            let report = quality.expect("rejected implies a quality report");
+            self.emit(Event::FileRejected {
+                file: file_name.clone(),
+                records: record_count,
+                duration_ms: elapsed.as_millis() as u64,
+                quality: report.clone(),
+                rejected: row_rejects,
+                progress,
+            });
+            let mut result = FileResult::failed(file_name, start, format!("quality check failed: {}", report.summary()));
+            result.quality = Some(report);
+            return result;
+        }
+
+        self.emit(Event::FileDone {
+            file: file_name.clone(),
+            input: csv_path.display().to_string(),
+            outputs: Box::new(outputs.clone()),
+            records: record_count,
+            input_bytes,
+            rows: stats.rows,
+            rejected: row_rejects,
+            ndjson_bytes,
+            bson_bytes,
+            bulk_bytes: bulk_bytes_written,
+            es_indexed: es.indexed,
+            es_failed: es.failed,
+            es_unconfirmed: es.unconfirmed,
+            parquet_bytes,
+            duration_ms: elapsed.as_millis() as u64,
+            rate_per_sec: rate,
+            quality: quality.clone(),
+            progress,
+        });
+        self.mark_completed(state, csv_path, record_count, rel_stem, &outputs);
+
+        FileResult {
+            file_name,
+            records: record_count,
+            ndjson_bytes,
+            bson_bytes,
+            bulk_bytes: bulk_bytes_written,
+            es_indexed: es.indexed,
+            es_failed: es.failed,
+            es_unconfirmed: es.unconfirmed,
+            parquet_bytes,
+            duration_ms: elapsed.as_millis() as u64,
+            error: None,
+            quality,
+            outputs,
+            cancelled: false,
+            skipped: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn hashed(stem: &str, name: &str) -> String {
+        format!("{}-{:08x}", stem, hash_bytes(name.as_bytes()) as u32)
+    }
+
+    #[test]
+    fn stem_key_folds_case_and_folders() {
+        assert_eq!(stem_key("Sub/Parts_AB4"), "sub__parts_ab4");
+        assert_eq!(stem_key("sub__parts_ab4"), stem_key("SUB/PARTS_AB4"));
+        assert_ne!(stem_key("sub/parts"), stem_key("sub_parts"));
+    }
+
+    #[test]
+    fn distinct_stems_are_kept() {
+        let (stems, collisions) = assign_stems(&names(&["a_AB4.csv", "sub/a_AB4.csv", "b.CSV"]), &HashSet::new());
+        assert_eq!(stems, ["a_AB4", "sub/a_AB4", "b"]);
+        assert!(collisions.is_empty());
+    }
+
+    #[test]
+    fn colliding_stems_get_the_hash_of_their_name() {
+        let files = names(&["x.csv", "Parts.csv", "sub/a.csv", "parts.CSV", "sub__a.csv"]);
+        let (stems, collisions) = assign_stems(&files, &HashSet::new());
+
+        assert_eq!(
+            stems,
+            [
+                "x".to_string(),
+                hashed("Parts", "Parts.csv"),
+                hashed("sub/a", "sub/a.csv"),
+                hashed("parts", "parts.CSV"),
+                hashed("sub__a", "sub__a.csv"),
+            ]
+        );
+        assert_ne!(stem_key(&stems[1]), stem_key(&stems[3]));
+        // Each group in name order, groups ordered by their first name
+        assert_eq!(collisions, [vec![1, 3], vec![2, 4]]);
+    }
+
+    #[test]
+    fn stems_do_not_depend_on_input_order() {
+        let forward = names(&["Parts.csv", "parts.csv", "PARTS.csv"]);
+        let backward: Vec<String> = forward.iter().rev().cloned().collect();
+        let (mut a, _) = assign_stems(&forward, &HashSet::new());
+        let (mut b, _) = assign_stems(&backward, &HashSet::new());
+        b.reverse();
+        assert_eq!(a, b);
+        a.sort();
+        a.dedup();
+        assert_eq!(a.len(), 3);
+    }
+
+    #[test]
+    fn reserved_stems_collide_too() {
+        let reserved: HashSet<String> = ["parts".to_string(), stem_key(&hashed("PARTS", "PARTS.csv"))].into();
+        let files = names(&["PARTS.csv", "other.csv"]);
+        let (stems, collisions) = assign_stems(&files, &reserved);
+
+        // Hashed away from "parts", then numbered away from the reserved hash
+        assert_eq!(stems, [format!("{}-2", hashed("PARTS", "PARTS.csv")), "other".to_string()]);
+        assert_eq!(collisions, [vec![0]]);
+    }
+
+    #[test]
+    fn hashed_stem_meeting_a_real_one_is_numbered() {
+        let taken = hashed("parts", "parts.csv");
+        let files = names(&["parts.csv", "PARTS.csv", &format!("{}.csv", taken)]);
+        let (stems, _) = assign_stems(&files, &HashSet::new());
+
+        // Name order: "PARTS.csv" < "parts-….csv" < "parts.csv"
+        assert_eq!(stems[0], format!("{}-2", taken));
+        assert_eq!(stems[1], hashed("PARTS", "PARTS.csv"));
+        assert_eq!(stems[2], taken);
+    }
+
+    #[test]
+    fn failed_ndjson_write_fails_the_file_and_skips_the_checkpoint() {
+        let dir = scratch("write-error");
+        let (input, output) = (dir.join("in"), dir.join("out"));
+        fs::create_dir_all(&input).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        // small.csv fails on the final flush, large.csv on a write mid-file
+        let files = vec![input.join("small_AB4.csv"), input.join("large_AB4.csv")];
+        fs::write(&files[0], "part_number;price\nP1;1,5\n").unwrap();
+        let mut large = String::from("part_number;price\n");
+        for i in 0..20_000 {
+            large.push_str(&format!("P{};{},5\n", i, i));
+        }
+        fs::write(&files[1], large).unwrap();
+        for stem in ["small_AB4", "large_AB4"] {
+            std::os::unix::fs::symlink("/dev/full", output.join(format!("{}.ndjson", stem))).unwrap();
+        }
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let checkpoint = output.join(crate::checkpoint::DEFAULT_NAME);
+        let summary = Transformer::builder(MappingConfig::new("", ""), &output)
+            .checkpoint(&checkpoint, false)
+            .on_event(move |e| seen.lock().unwrap().push(e.to_json()))
+            .build()
+            .unwrap()
+            .run(&files);
+
+        for result in &summary.files {
+            let error = result.error.as_deref().unwrap_or_default();
+            assert!(error.contains("failed"), "{}: {:?}", result.file_name, result.error);
+        }
+        assert!(summary.files[0].error.as_deref().unwrap().starts_with("flush"));
+        assert!(summary.files[1].error.as_deref().unwrap().starts_with("write"));
+        let events = events.lock().unwrap();
+        assert_eq!(events.iter().filter(|e| e.contains(r#""event":"file_failed""#)).count(), 2);
+        assert!(!events.iter().any(|e| e.contains(r#""event":"file_done""#)));
+        assert!(Checkpoint::load(&checkpoint).unwrap().unwrap().files.is_empty());
+        assert!(!output.join("large_AB4.bulk").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn late_failure_sends_file_failed_only() {
+        let dir = scratch("late-error");
+        let input = dir.join("in");
+        fs::create_dir_all(&input).unwrap();
+        let files = vec![input.join("parts_AB4.csv")];
+        fs::write(&files[0], "part_number;price\nP1;1,5\nP2;2\nP3;3\n").unwrap();
+
+        // Unchunked .bulk is deleted; chunks announced by bulk_chunk stay
+        let chunked = BulkLimits { max_bytes: 0, max_docs: 2 };
+        for (name, limits) in [("whole", BulkLimits::default()), ("chunked", chunked)] {
+            let output = dir.join(name);
+            // Every record is written before the metadata file fails to appear
+            fs::create_dir_all(output.join("parts_AB4.metadata.json")).unwrap();
+
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let seen = events.clone();
+            let checkpoint = output.join(crate::checkpoint::DEFAULT_NAME);
+            let summary = Transformer::builder(MappingConfig::new("", ""), &output)
+                .bson(true)
+                .bulk_limits(limits)
+                .checkpoint(&checkpoint, false)
+                .on_event(move |e| seen.lock().unwrap().push(e.to_json()))
+                .build()
+                .unwrap()
+                .run(&files);
+
+            assert!(summary.files[0].error.is_some(), "{}", name);
+            let events = events.lock().unwrap();
+            let terminal = ["file_done", "file_failed", "file_rejected", "file_cancelled"];
+            let terminal: Vec<&String> = events.iter().filter(|e| terminal.iter().any(|t| e.contains(t))).collect();
+            assert_eq!(terminal.len(), 1, "{}: {:?}", name, terminal);
+            assert!(terminal[0].starts_with(r#"{"event":"file_failed""#));
+            assert!(Checkpoint::load(&checkpoint).unwrap().unwrap().files.is_empty());
+            assert!(!output.join("parts_AB4.bson").exists());
+            assert!(!output.join("parts_AB4.bulk").exists());
+            let announced = events.iter().filter(|e| e.contains(r#""event":"bulk_chunk""#)).count();
+            assert_eq!(announced, if limits.is_chunked() { 2 } else { 0 }, "{}", name);
+            for chunk in 0..announced {
+                assert!(output.join(format!("parts_AB4.bulk.{:03}", chunk)).exists(), "{} chunk {}", name, chunk);
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
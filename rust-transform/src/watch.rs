@@ -0,0 +1,449 @@
+// =============================================================================
+// Drop-directory watching — which files have finished arriving
+// =============================================================================
+// Inotify says which entries of the directory changed; DropDir decides when a
+// changed file is complete:
+//
+//   Settled   nothing was written to it for the settle time (its mtime is
+//             that old) and its size did not change between two looks
+//   Marker    <file>.done exists — for senders that can write a marker
+//
+// A file is handed out once; it is offered again only after it changes
+// (a supplier re-uploading it). Only the directory's own entries are watched,
+// not subdirectories.
+// =============================================================================
+
+use crate::discover::Glob;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::{CString, OsStr, OsString};
+use std::fs;
+use std::io;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Appended to a file's name to mark it complete
+pub const MARKER_SUFFIX: &str = ".done";
+
+// =============================================================================
+// Inotify
+// =============================================================================
+pub enum Change {
+    /// An entry of the directory, by name
+    Entry(OsString),
+    /// The kernel dropped events; every entry has to be looked at again
+    Overflow,
+}
+
+pub struct Inotify {
+    fd: libc::c_int,
+    buf: Vec<u8>,
+}
+
+/// Size of struct inotify_event without its name
+const EVENT_HEADER: usize = 16;
+
+impl Inotify {
+    pub fn watch(dir: &Path) -> io::Result<Inotify> {
+        let path = CString::new(dir.as_os_str().as_bytes())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))?;
+        // SAFETY: plain syscalls; the fd is owned by the returned Inotify
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mask = libc::IN_CREATE
+            | libc::IN_MODIFY
+            | libc::IN_ATTRIB
+            | libc::IN_CLOSE_WRITE
+            | libc::IN_MOVED_TO
+            | libc::IN_MOVED_FROM
+            | libc::IN_DELETE
+            | libc::IN_DELETE_SELF
+            | libc::IN_MOVE_SELF;
+        // SAFETY: `path` is a valid NUL-terminated string
+        if unsafe { libc::inotify_add_watch(fd, path.as_ptr(), mask) } < 0 {
+            let e = io::Error::last_os_error();
+            // SAFETY: fd was opened above and is not used again
+            unsafe { libc::close(fd) };
+            return Err(e);
+        }
+        Ok(Inotify { fd, buf: vec![0; 64 * 1024] })
+    }
+
+    /// Changes seen within `timeout`; empty when there were none (or a
+    /// signal interrupted the wait)
+    pub fn wait(&mut self, timeout: Duration) -> io::Result<Vec<Change>> {
+        let mut pollfd = libc::pollfd { fd: self.fd, events: libc::POLLIN, revents: 0 };
+        // SAFETY: one valid pollfd
+        let n = unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis().min(i32::MAX as u128) as libc::c_int) };
+        if n < 0 {
+            let e = io::Error::last_os_error();
+            return if e.kind() == io::ErrorKind::Interrupted { Ok(Vec::new()) } else { Err(e) };
+        }
+
+        let mut changes = Vec::new();
+        loop {
+            // SAFETY: reads at most buf.len() bytes into buf
+            let len = unsafe { libc::read(self.fd, self.buf.as_mut_ptr() as *mut libc::c_void, self.buf.len()) };
+            if len < 0 {
+                let e = io::Error::last_os_error();
+                match e.kind() {
+                    io::ErrorKind::WouldBlock => return Ok(changes),
+                    io::ErrorKind::Interrupted => continue,
+                    _ => return Err(e),
+                }
+            }
+            decode(&self.buf[..len as usize], &mut changes)?;
+        }
+    }
+}
+
+/// Append the changes in `buf`, a whole number of struct inotify_event
+/// records as read(2) returns them
+fn decode(buf: &[u8], changes: &mut Vec<Change>) -> io::Result<()> {
+    let len = buf.len();
+    let mut at = 0;
+    while at + EVENT_HEADER <= len {
+        let field = |i: usize| u32::from_ne_bytes(buf[at + i..at + i + 4].try_into().unwrap());
+        let mask = field(4);
+        let name_len = field(12) as usize;
+        let name = &buf[at + EVENT_HEADER..(at + EVENT_HEADER + name_len).min(len)];
+        at += EVENT_HEADER + name_len;
+
+        if mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF | libc::IN_IGNORED) != 0 {
+            return Err(io::Error::other("the watched directory was removed or moved"));
+        }
+        if mask & libc::IN_Q_OVERFLOW != 0 {
+            changes.push(Change::Overflow);
+            continue;
+        }
+        // The name is NUL-padded to an alignment boundary
+        let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        if end > 0 {
+            changes.push(Change::Entry(OsString::from_vec(name[..end].to_vec())));
+        }
+    }
+    Ok(())
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        // SAFETY: the fd is owned by self
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+// =============================================================================
+// Readiness
+// =============================================================================
+#[derive(Clone, Copy, Debug)]
+pub enum Readiness {
+    /// No writes for this long, and the size held between two looks
+    Settled(Duration),
+    /// <file>.done exists
+    Marker,
+}
+
+/// Size and mtime, to tell a re-upload from the file already handed out
+type Fingerprint = (u64, SystemTime);
+
+fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let meta = fs::metadata(path).ok()?;
+    meta.is_file().then_some((meta.len(), meta.modified().ok()?))
+}
+
+pub struct DropDir {
+    dir: PathBuf,
+    readiness: Readiness,
+    /// Empty = *.csv
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    /// Settled mode: files still being written, with what they looked like last
+    settling: BTreeMap<OsString, Fingerprint>,
+    ready: BTreeSet<OsString>,
+    /// What every file looked like when it was handed out
+    taken: HashMap<OsString, Fingerprint>,
+}
+
+impl DropDir {
+    pub fn new(dir: &Path, readiness: Readiness, include: Vec<Glob>, exclude: Vec<Glob>) -> Result<DropDir, String> {
+        let include = if include.is_empty() { vec![Glob::new("*.csv")?] } else { include };
+        Ok(DropDir {
+            dir: dir.to_path_buf(),
+            readiness,
+            include,
+            exclude,
+            settling: BTreeMap::new(),
+            ready: BTreeSet::new(),
+            taken: HashMap::new(),
+        })
+    }
+
+    /// Look at every entry — at startup, and after an inotify overflow
+    pub fn rescan(&mut self) -> Result<(), String> {
+        let entries = fs::read_dir(&self.dir).map_err(|e| format!("cannot read {}: {}", self.dir.display(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("cannot read {}: {}", self.dir.display(), e))?;
+            self.changed(&entry.file_name());
+        }
+        Ok(())
+    }
+
+    /// The entry `name` was created, written, moved or removed
+    pub fn changed(&mut self, name: &OsStr) {
+        if let Readiness::Marker = self.readiness {
+            let bytes = name.as_bytes();
+            if let Some(file) = bytes.strip_suffix(MARKER_SUFFIX.as_bytes()) {
+                let file = OsStr::from_bytes(file);
+                if self.wanted(file) && self.dir.join(name).exists() {
+                    self.offer(file.to_os_string());
+                }
+            }
+            return;
+        }
+        if !self.wanted(name) {
+            return;
+        }
+        match fingerprint(&self.dir.join(name)) {
+            Some(now) if self.taken.get(name) != Some(&now) => {
+                self.ready.remove(name);
+                // A rewrite restarts settling; an unchanged file keeps its entry
+                self.settling.entry(name.to_os_string()).or_insert(now);
+            }
+            _ => {
+                self.settling.remove(name);
+                self.ready.remove(name);
+            }
+        }
+    }
+
+    fn wanted(&self, name: &OsStr) -> bool {
+        let name = name.to_string_lossy();
+        self.include.iter().any(|g| g.matches(&name)) && !self.exclude.iter().any(|g| g.matches(&name))
+    }
+
+    fn offer(&mut self, name: OsString) {
+        let Some(now) = fingerprint(&self.dir.join(&name)) else { return };
+        if self.taken.get(&name) != Some(&now) {
+            self.ready.insert(name);
+        }
+    }
+
+    /// Files that finished arriving, by name; each is handed out once per version
+    pub fn take_ready(&mut self) -> Vec<PathBuf> {
+        if let Readiness::Settled(settle) = self.readiness {
+            let wall = SystemTime::now();
+            let mut settled = Vec::new();
+            for (name, last) in self.settling.iter_mut() {
+                let Some(now) = fingerprint(&self.dir.join(name)) else { continue };
+                let quiet = wall.duration_since(now.1).is_ok_and(|age| age >= settle);
+                if now == *last && quiet {
+                    settled.push(name.clone());
+                }
+                *last = now;
+            }
+            for name in settled {
+                self.settling.remove(&name);
+                self.ready.insert(name);
+            }
+        }
+
+        let mut out = Vec::new();
+        for name in std::mem::take(&mut self.ready) {
+            let path = self.dir.join(&name);
+            if let Some(now) = fingerprint(&path) {
+                self.taken.insert(name, now);
+                out.push(path);
+            }
+        }
+        out
+    }
+
+    /// Files seen but not settled yet
+    pub fn settling(&self) -> usize {
+        self.settling.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use std::fs::File;
+
+    /// Write `path` with an mtime `age` in the past
+    fn write_aged(path: &Path, body: &str, age: Duration) {
+        fs::write(path, body).unwrap();
+        File::options().write(true).open(path).unwrap().set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    fn names(paths: Vec<PathBuf>) -> Vec<String> {
+        paths.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect()
+    }
+
+    fn event(mask: u32, name: &[u8], padded: usize) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&1i32.to_ne_bytes());
+        buf.extend_from_slice(&mask.to_ne_bytes());
+        buf.extend_from_slice(&0u32.to_ne_bytes());
+        buf.extend_from_slice(&(padded as u32).to_ne_bytes());
+        buf.extend_from_slice(name);
+        buf.resize(EVENT_HEADER + padded, 0);
+        buf
+    }
+
+    fn entries(changes: &[Change]) -> Vec<String> {
+        changes
+            .iter()
+            .map(|c| match c {
+                Change::Entry(name) => name.to_string_lossy().into_owned(),
+                Change::Overflow => "<overflow>".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn settled_needs_two_matching_looks_and_an_old_mtime() {
+        let dir = scratch("watch-settled");
+        let settle = Duration::from_secs(60);
+        let mut drop = DropDir::new(&dir, Readiness::Settled(settle), Vec::new(), Vec::new()).unwrap();
+
+        write_aged(&dir.join("parts.csv"), "a,b\n", Duration::from_secs(120));
+        drop.changed(OsStr::new("parts.csv"));
+        assert_eq!(names(drop.take_ready()), ["parts.csv"]);
+        assert_eq!(drop.settling(), 0);
+
+        // Grew between the change and the look: one more look before it counts
+        write_aged(&dir.join("grown.csv"), "a,b\n", Duration::from_secs(120));
+        drop.changed(OsStr::new("grown.csv"));
+        write_aged(&dir.join("grown.csv"), "a,b\n1,2\n", Duration::from_secs(120));
+        assert!(drop.take_ready().is_empty());
+        assert_eq!(names(drop.take_ready()), ["grown.csv"]);
+
+        // Not a CSV: never looked at
+        write_aged(&dir.join("notes.txt"), "x", Duration::from_secs(120));
+        drop.changed(OsStr::new("notes.txt"));
+        assert!(drop.take_ready().is_empty());
+        assert_eq!(drop.settling(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn half_written_file_is_not_offered() {
+        let dir = scratch("watch-half");
+        let mut drop = DropDir::new(&dir, Readiness::Settled(Duration::from_secs(60)), Vec::new(), Vec::new()).unwrap();
+
+        // Written just now: the size holds between looks but the mtime is fresh
+        fs::write(dir.join("parts.csv"), "a,b\n1,").unwrap();
+        drop.rescan().unwrap();
+        assert!(drop.take_ready().is_empty());
+        assert!(drop.take_ready().is_empty());
+        assert_eq!(drop.settling(), 1);
+
+        // The sender finished a while ago
+        write_aged(&dir.join("parts.csv"), "a,b\n1,2\n", Duration::from_secs(120));
+        drop.changed(OsStr::new("parts.csv"));
+        assert!(drop.take_ready().is_empty());
+        assert_eq!(names(drop.take_ready()), ["parts.csv"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn marker_offers_a_file_once_its_marker_exists() {
+        let dir = scratch("watch-marker");
+        let mut drop = DropDir::new(&dir, Readiness::Marker, Vec::new(), Vec::new()).unwrap();
+
+        fs::write(dir.join("parts.csv"), "a,b\n1,").unwrap();
+        drop.changed(OsStr::new("parts.csv"));
+        assert!(drop.take_ready().is_empty());
+
+        fs::write(dir.join("parts.csv"), "a,b\n1,2\n").unwrap();
+        fs::write(dir.join("parts.csv.done"), "").unwrap();
+        drop.changed(OsStr::new("parts.csv.done"));
+        assert_eq!(names(drop.take_ready()), ["parts.csv"]);
+
+        // A marker for a file that is not wanted, or that does not exist
+        fs::write(dir.join("notes.txt"), "x").unwrap();
+        fs::write(dir.join("notes.txt.done"), "").unwrap();
+        drop.changed(OsStr::new("notes.txt.done"));
+        fs::write(dir.join("missing.csv.done"), "").unwrap();
+        drop.changed(OsStr::new("missing.csv.done"));
+        assert!(drop.take_ready().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn re_upload_is_offered_again() {
+        let dir = scratch("watch-reupload");
+        let mut drop = DropDir::new(&dir, Readiness::Settled(Duration::from_secs(60)), Vec::new(), Vec::new()).unwrap();
+        let path = dir.join("parts.csv");
+
+        write_aged(&path, "a,b\n1,2\n", Duration::from_secs(300));
+        drop.changed(OsStr::new("parts.csv"));
+        assert_eq!(names(drop.take_ready()), ["parts.csv"]);
+
+        // Same size and mtime (a touch of its attributes, a rescan): already taken
+        drop.changed(OsStr::new("parts.csv"));
+        drop.rescan().unwrap();
+        assert!(drop.take_ready().is_empty());
+        assert_eq!(drop.settling(), 0);
+
+        // The supplier uploads it again
+        write_aged(&path, "a,b\n3,4\n", Duration::from_secs(120));
+        drop.changed(OsStr::new("parts.csv"));
+        assert_eq!(names(drop.take_ready()), ["parts.csv"]);
+
+        // Marker mode: the same marker again is ignored until the file changes
+        let mut drop = DropDir::new(&dir, Readiness::Marker, Vec::new(), Vec::new()).unwrap();
+        fs::write(dir.join("parts.csv.done"), "").unwrap();
+        drop.changed(OsStr::new("parts.csv.done"));
+        assert_eq!(names(drop.take_ready()), ["parts.csv"]);
+        drop.changed(OsStr::new("parts.csv.done"));
+        assert!(drop.take_ready().is_empty());
+        write_aged(&path, "a,b\n5,6\n", Duration::from_secs(60));
+        drop.changed(OsStr::new("parts.csv.done"));
+        assert_eq!(names(drop.take_ready()), ["parts.csv"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn decode_reads_padded_names_and_overflow() {
+        let mut buf = event(libc::IN_CLOSE_WRITE, b"parts.csv", 16);
+        buf.extend(event(libc::IN_Q_OVERFLOW, b"", 0));
+        buf.extend(event(libc::IN_MOVED_TO, b"caf\xe9.csv", 32));
+        let mut changes = Vec::new();
+        decode(&buf, &mut changes).unwrap();
+        assert_eq!(entries(&changes), ["parts.csv", "<overflow>", "caf\u{fffd}.csv"]);
+        let Change::Entry(name) = &changes[2] else { panic!() };
+        assert_eq!(name.as_bytes(), b"caf\xe9.csv");
+
+        // The watched directory going away ends the watch
+        for mask in [libc::IN_DELETE_SELF, libc::IN_MOVE_SELF, libc::IN_IGNORED] {
+            assert!(decode(&event(mask, b"", 0), &mut Vec::new()).is_err());
+        }
+    }
+
+    #[test]
+    fn inotify_reports_directory_entries() {
+        let dir = scratch("watch-inotify");
+        let mut inotify = Inotify::watch(&dir).unwrap();
+        fs::write(dir.join("parts.csv"), "a,b\n").unwrap();
+        let changes = inotify.wait(Duration::from_secs(5)).unwrap();
+        assert!(entries(&changes).iter().any(|n| n == "parts.csv"));
+
+        fs::remove_dir_all(&dir).unwrap();
+        let mut gone = Vec::new();
+        for _ in 0..10 {
+            match inotify.wait(Duration::from_secs(1)) {
+                Ok(changes) => gone.extend(entries(&changes)),
+                Err(_) => return,
+            }
+        }
+        panic!("no error after the directory was removed; saw {:?}", gone);
+    }
+}
@@ -0,0 +1,79 @@
+#!/usr/bin/env node
+/**
+ * Native vs JS CSV parse comparison
+ * Parses one fixture with csvParserService.parseCSV both ways (JS parser and
+ * { native: true }) and checks that the records differ only in the ways
+ * parseCSV documents. Needs the addon:
+ *   cd rust-transform && cargo build --release -p turbo-transform-node
+ */
+const csvParserService = require('../services/csvParserService');
+const turboTransformNative = require('../services/turboTransformNative');
+
+const FIXTURE_CSV = `Part Number;Description;Brand;Supplier;Price;Currency;Quantity;Weight;Volume;Delivery;MIN_LOT
+TST-001;Brake Pad Front;BREMBO;EuroParts;125,50;USD;100;1,2;0,5;="3/6";2
+TST-002;"Air Filter; round";K&N;EuroParts;1,234.00;;50;;;1-3;
+TST-003;Oil Filter;MANN;EuroParts;;;0;0,35;;;
+"TST-004";Spark Plug;NGK;EuroParts;7.9;AED;12;;;0/0;10
+`;
+
+// Only in native records
+const NATIVE_ONLY = [
+  'stock', 'weightUnit', 'category', 'subcategory',
+  'integration', 'integrationName', 'fileName', 'importedAt',
+];
+// null in JS records, 0 in native ones, when the column is empty
+const ZERO_NOT_NULL = ['price', 'weight', 'volume'];
+
+let testsPassed = 0;
+let testsFailed = 0;
+
+function check(name, ok, details = '') {
+  if (ok) {
+    testsPassed++;
+    console.log(`  ✅ ${name}`);
+  } else {
+    testsFailed++;
+    console.log(`  ❌ ${name} ${details}`);
+  }
+}
+
+async function runTests() {
+  console.log('\n🔬 Native vs JS CSV parse\n');
+
+  if (!turboTransformNative.load()) {
+    console.error(`❌ Addon not built (${turboTransformNative.ADDON_PATH})`);
+    process.exit(1);
+  }
+
+  const buffer = Buffer.from(FIXTURE_CSV);
+  const js = await csvParserService.parseCSV(buffer);
+  const native = await csvParserService.parseCSV(buffer, {}, { native: true });
+
+  check('same number of records', js.length === native.length, `(${js.length} vs ${native.length})`);
+  check('native records carry native-only fields', native.every((r) => NATIVE_ONLY.every((f) => f in r)));
+  check('JS records stay in their own shape', js.every((r) => NATIVE_ONLY.every((f) => !(f in r))));
+
+  js.forEach((jsRecord, i) => {
+    const nativeRecord = native[i] || {};
+    for (const [field, jsValue] of Object.entries(jsRecord)) {
+      const nativeValue = nativeRecord[field];
+      let ok = jsValue === nativeValue;
+      if (!ok && ZERO_NOT_NULL.includes(field)) {
+        ok = jsValue === null && nativeValue === 0;
+      }
+      if (!ok && field === 'currency') {
+        // JS always says AED; native reads the Currency column
+        ok = jsValue === 'AED';
+      }
+      check(`${jsRecord.partNumber}.${field}`, ok, `(JS ${JSON.stringify(jsValue)}, native ${JSON.stringify(nativeValue)})`);
+    }
+  });
+
+  console.log(`\n📊 ${testsPassed} passed, ${testsFailed} failed`);
+  process.exit(testsFailed > 0 ? 1 : 0);
+}
+
+runTests().catch((error) => {
+  console.error('\n❌ CRITICAL ERROR:', error.message);
+  process.exit(1);
+});
@@ -8,12 +8,36 @@ const { Readable } = require('stream');
 const fs = require('fs');
 const Part = require('../models/Part');
 const elasticsearchService = require('./elasticsearchService');
+const turboTransformNative = require('./turboTransformNative');
 
 class CSVParserService {
   /**
    * Parse CSV buffer to records
+   * @param {Buffer} buffer - CSV content
+   * @param {Object} columnMapping - explicit column names (JS parser only)
+   * @param {Object} options
+   * @param {boolean} options.native - parse with the Rust addon instead, when
+   *   it is built and no columnMapping is given. Records then have the shape
+   *   the bulk sync writes, not normalizeRecord's: price/weight/volume are 0
+   *   (not null) when missing, currency comes from the file (AED only as the
+   *   default), and stock, weightUnit, category, subcategory, integration,
+   *   integrationName, fileName and importedAt are added.
+   *   scripts/testNativeCsvParse.js compares both on a fixture.
    */
-  async parseCSV(buffer, columnMapping = {}) {
+  async parseCSV(buffer, columnMapping = {}, { native = false } = {}) {
+    // Same mapping + normalisation as the Rust bulk sync, when asked for
+    if (native && buffer && buffer.length > 0 && Object.keys(columnMapping).length === 0) {
+      try {
+        const records = turboTransformNative.parseRecords(buffer);
+        if (records) {
+          console.log(`📊 Parsed ${records.length} valid records (native)`);
+          return records;
+        }
+      } catch (e) {
+        console.warn(`⚠️ Native CSV parse failed (${e.message}), using JS parser`);
+      }
+    }
+
     return new Promise((resolve, reject) => {
       if (!buffer || buffer.length === 0) {
         reject(new Error('CSV file is empty or invalid'));
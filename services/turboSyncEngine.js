@@ -23,7 +23,7 @@
  */
 
 require('dotenv').config();
-const { spawn, execSync } = require('child_process');
+const { spawn, execSync, execFileSync } = require('child_process');
 const fs = require('fs');
 const path = require('path');
 const os = require('os');
@@ -304,6 +304,8 @@ async function downloadAllFiles(integration) {
 // ============================================
 // RUST BINARY LOCATION
 // ============================================
+// Version of the turbo-transform event lines this engine understands
+const RUST_EVENT_SCHEMA_VERSION = 1;
 const RUST_BINARY_PATH = path.join(__dirname, '..', 'rust-transform', 'target', 'release', 'turbo-transform');
 
 function isRustBinaryAvailable() {
@@ -314,6 +316,19 @@ function isRustBinaryAvailable() {
   }
 }
 
+// Prefer the mapping derived from the Rust record definition so the two
+// can't drift; fall back to the hand-maintained ES_INDEX_MAPPING.
+function loadESIndexMapping() {
+  if (!isRustBinaryAvailable()) return ES_INDEX_MAPPING;
+  try {
+    const out = execFileSync(RUST_BINARY_PATH, ['schema', '--es', '--shards', String(CONFIG.ES_SHARDS)], { encoding: 'utf8' });
+    return JSON.parse(out);
+  } catch (e) {
+    log(`Rust schema export failed, using built-in mapping: ${e.message}`, 'ERROR');
+    return ES_INDEX_MAPPING;
+  }
+}
+
 // ============================================
 // PHASE 2+3+4 MERGED: PIPELINE
 // Rust transforms all files → as each file completes,
@@ -338,7 +353,7 @@ async function runPipeline(downloadDir, files, integration, esClient, esAliasNam
   try {
     await esClient.indices.create({
       index: esIndexName,
-      body: ES_INDEX_MAPPING,
+      body: loadESIndexMapping(),
     });
     log(`ES index ${esIndexName} created`, 'SUCCESS');
   } catch (e) {
@@ -715,13 +730,17 @@ async function runPipeline(downloadDir, files, integration, esClient, esAliasNam
   if (useRust) {
     log(`🦀 Starting Rust transform (ES index: ${esIndexName})...`, 'PROGRESS');
 
+    // No --resume: every sync re-downloads into a fresh WORK_DIR and writes
+    // a new timestamped index, so a cancelled sync starts over. Checkpoints
+    // are for manual `turbo-transform transform --resume` runs only.
     await new Promise((resolveRust, rejectRust) => {
       const child = spawn(RUST_BINARY_PATH, [
-        downloadDir,
-        outputDir,
-        integrationId,
-        integrationName,
-        esIndexName,
+        'transform',
+        '--input', downloadDir,
+        '--output', outputDir,
+        '--integration-id', integrationId,
+        '--integration-name', integrationName,
+        '--es-index', esIndexName,
       ], {
         stdio: ['ignore', 'pipe', 'pipe'],
         env: {
@@ -733,28 +752,39 @@ async function runPipeline(downloadDir, files, integration, esClient, esAliasNam
       let stdoutData = '';
       let lastProgressLog = Date.now();
 
-      // stderr: per-file progress — IMMEDIATELY queue completed files
+      // stderr: per-file progress — IMMEDIATELY queue completed files.
+      // Events are JSON lines; a chunk can end mid-line, so keep the tail.
+      let stderrTail = '';
+      let warnedVersion = false;
+      const queuedBulkChunks = new Set();
       child.stderr.on('data', (data) => {
-        const lines = data.toString().split('\n').filter(l => l.trim());
-        for (const line of lines) {
+        const lines = (stderrTail + data.toString()).split('\n');
+        stderrTail = lines.pop();
+        for (const line of lines.filter(l => l.trim())) {
           try {
             const event = JSON.parse(line);
+            if (event.v > RUST_EVENT_SCHEMA_VERSION && !warnedVersion) {
+              log(`Rust event schema v${event.v} is newer than supported v${RUST_EVENT_SCHEMA_VERSION}`, 'ERROR');
+              warnedVersion = true;
+            }
             if (event.event === 'file_done') {
-              // Extract stem from filename (e.g., "foo.csv" → "foo")
-              const stem = event.file.replace(/\.csv$/i, '');
-              const ndjsonPath = path.join(outputDir, `${stem}.ndjson`);
-              const bulkPath = path.join(outputDir, `${stem}.bulk`);
+              // Rust reports the exact output paths (stems can be
+              // disambiguated or nested, so never rebuild them from the name)
+              const { ndjson, bulk = [] } = event.outputs || {};
 
               totalRecords += event.records;
 
               // Queue for MongoDB import immediately
-              if (fs.existsSync(ndjsonPath)) {
-                pushMongoItem(ndjsonPath);
+              if (ndjson && fs.existsSync(ndjson)) {
+                pushMongoItem(ndjson);
               }
 
-              // Queue for ES bulk streaming immediately
-              if (fs.existsSync(bulkPath)) {
-                pushESItem(bulkPath);
+              // Queue for ES bulk streaming immediately (chunks announced
+              // by bulk_chunk events are already queued)
+              for (const bulkPath of bulk) {
+                if (!queuedBulkChunks.has(bulkPath) && fs.existsSync(bulkPath)) {
+                  pushESItem(bulkPath);
+                }
               }
 
               if (Date.now() - lastProgressLog > 2000) {
@@ -775,6 +805,27 @@ async function runPipeline(downloadDir, files, integration, esClient, esAliasNam
                   });
                 }
               }
+            } else if (event.event === 'bulk_chunk') {
+              // Chunked .bulk output (--bulk-max-bytes/--bulk-max-docs):
+              // stream each chunk to ES as soon as Rust closes it
+              if (fs.existsSync(event.path)) {
+                queuedBulkChunks.add(event.path);
+                pushESItem(event.path);
+              }
+            } else if (event.event === 'progress') {
+              // Heartbeat while large files are still being read
+              const pct = event.bytes_total ? Math.floor((event.bytes_read / event.bytes_total) * 100) : 0;
+              const eta = event.eta_ms != null ? `, ETA ${formatDuration(event.eta_ms)}` : '';
+              log(`Rust: ${pct}% read — ${formatNumber(event.records)} records (${formatNumber(event.rate_per_sec)}/sec${eta})`, 'PROGRESS');
+              lastProgressLog = Date.now();
+            } else if (event.event === 'stem_collision') {
+              log(`Rust: ${event.files.join(', ')} would share outputs — written as ${event.stems.join(', ')}`, 'INFO');
+            } else if (event.event === 'file_cancelled') {
+              log(`Rust: cancelled ${event.file} after ${formatNumber(event.records)} records — outputs removed`, 'PROGRESS');
+            } else if (event.event === 'file_failed') {
+              log(`Rust: ${event.file} failed — ${event.error}`, 'ERROR');
+            } else if (event.event === 'panic') {
+              log(`Rust: panic on ${event.thread} at ${event.location} — ${event.message}`, 'ERROR');
             } else if (event.event === 'start') {
               log(`Rust engine: ${event.files} files, ${event.threads} threads, ${(event.total_bytes / 1024 / 1024).toFixed(0)}MB input`, 'INFO');
             }
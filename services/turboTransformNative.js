@@ -0,0 +1,54 @@
+/**
+ * Turbo Transform native addon loader
+ * In-process access to the Rust column mapping + record normalisation, so
+ * small uploads produce the same documents as turboSyncEngine bulk syncs.
+ *
+ * Build: cd rust-transform && cargo build --release -p turbo-transform-node
+ * Returns null from load() when the addon isn't built; callers fall back to JS.
+ */
+const path = require('path');
+const fs = require('fs');
+
+const LIB_NAME = {
+  darwin: 'libturbo_transform_node.dylib',
+  win32: 'turbo_transform_node.dll',
+}[process.platform] || 'libturbo_transform_node.so';
+
+const ADDON_PATH = process.env.TURBO_TRANSFORM_ADDON
+  || path.join(__dirname, '..', 'rust-transform', 'target', 'release', LIB_NAME);
+
+let addon;
+
+function load() {
+  if (addon !== undefined) return addon;
+  addon = null;
+  try {
+    if (fs.existsSync(ADDON_PATH)) {
+      const mod = { exports: {} };
+      process.dlopen(mod, ADDON_PATH);
+      addon = mod.exports;
+    }
+  } catch (e) {
+    console.warn(`⚠️ turbo-transform addon failed to load (${e.message}), using JS parser`);
+  }
+  return addon;
+}
+
+module.exports = {
+  load,
+  ADDON_PATH,
+  /**
+   * @param {Buffer|string} input - CSV buffer or file path
+   * @param {{integrationId?: string, integrationName?: string, fileName?: string, importedAt?: number}} options
+   * @returns {Object[]|null} normalised part records, or null without the addon
+   */
+  parseRecords(input, options = {}) {
+    const native = load();
+    return native ? native.parseRecords(input, options) : null;
+  },
+  /** Same as parseRecords but returns the NDJSON Buffer the binary would write */
+  toNdjson(input, options = {}) {
+    const native = load();
+    return native ? native.toNdjson(input, options) : null;
+  },
+};
@@ -0,0 +1,102 @@
+/**
+ * Turbo Transform server client
+ * One long-lived `turbo-transform serve` process (JSON-RPC 2.0 over stdio)
+ * shared by syncWorker.js and the supplier upload path, instead of a fresh
+ * binary — and a cold thread pool — per sync.
+ *
+ *   const { job, done } = await turboServer.transform(
+ *     { input, output, 'integration-id': id, 'es-index': index, files: ['a.csv'] },
+ *     (event) => { ... same events as `transform` prints on stderr ... });
+ *   const { state, exit_code, errors } = await done;
+ *
+ * Options use the CLI names (see `turbo-transform transform --help`).
+ */
+const { spawn } = require('child_process');
+const path = require('path');
+const readline = require('readline');
+
+const BINARY_PATH = process.env.TURBO_TRANSFORM_BIN
+  || path.join(__dirname, '..', 'rust-transform', 'target', 'release', 'turbo-transform');
+
+let child = null;
+let nextId = 1;
+const pending = new Map(); // request id → { resolve, reject, onResult }
+const jobs = new Map();    // job id → { onEvent, resolve }
+
+function start() {
+  if (child) return child;
+  child = spawn(BINARY_PATH, ['serve'], { stdio: ['pipe', 'pipe', 'inherit'] });
+
+  readline.createInterface({ input: child.stdout }).on('line', (line) => {
+    let msg;
+    try { msg = JSON.parse(line); } catch { return; }
+
+    if (msg.id != null && pending.has(msg.id)) {
+      const { resolve, reject, onResult } = pending.get(msg.id);
+      pending.delete(msg.id);
+      if (msg.error) {
+        reject(new Error(`turbo-transform serve: ${msg.error.message} (${msg.error.code})`));
+      } else {
+        // Synchronously: the lines after this one may already be its events
+        if (onResult) onResult(msg.result);
+        resolve(msg.result);
+      }
+    } else if (msg.method === 'event') {
+      const job = jobs.get(msg.params.job);
+      if (job && job.onEvent) job.onEvent(msg.params.event);
+    } else if (msg.method === 'job_done') {
+      const job = jobs.get(msg.params.job);
+      if (job) {
+        jobs.delete(msg.params.job);
+        job.resolve(msg.params);
+      }
+    }
+  });
+
+  child.on('exit', (code) => {
+    child = null;
+    const err = new Error(`turbo-transform serve exited with code ${code}`);
+    for (const { reject } of pending.values()) reject(err);
+    pending.clear();
+    for (const job of jobs.values()) job.resolve({ state: 'failed', exit_code: code, errors: [err.message] });
+    jobs.clear();
+  });
+  return child;
+}
+
+function call(method, params, onResult) {
+  const proc = start();
+  const id = nextId++;
+  return new Promise((resolve, reject) => {
+    pending.set(id, { resolve, reject, onResult });
+    proc.stdin.write(JSON.stringify({ jsonrpc: '2.0', id, method, params }) + '\n');
+  });
+}
+
+module.exports = {
+  BINARY_PATH,
+  /**
+   * @param {Object} options - transform options, plus optional `files` relative to `input`
+   * @param {(event: Object) => void} [onEvent]
+   * @returns {Promise<{job: number, done: Promise<{state: string, exit_code: number, errors: string[]}>}>}
+   */
+  async transform(options, onEvent) {
+    let resolveDone;
+    const done = new Promise((resolve) => { resolveDone = resolve; });
+    const { job } = await call('transform', options, (result) => {
+      jobs.set(result.job, { onEvent, resolve: resolveDone });
+    });
+    return { job, done };
+  },
+  cancel(job) {
+    return call('cancel', { job });
+  },
+  /** One job's status, or { jobs: [...] } without an id */
+  status(job) {
+    return call('status', job == null ? {} : { job });
+  },
+  async shutdown() {
+    if (!child) return;
+    await call('shutdown');
+  },
+};